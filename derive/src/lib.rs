@@ -0,0 +1,407 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Field, Fields, GenericArgument,
+    Ident, PathArguments, Type,
+};
+
+fn snake_case(ident: &Ident) -> Ident {
+    let name = ident.to_string();
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev_lower = chars[i - 1].is_lowercase() || chars[i - 1].is_numeric();
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev_lower || (chars[i - 1].is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    Ident::new(&out, Span::call_site())
+}
+
+#[proc_macro_derive(TokenKind)]
+pub fn derive_token_kind(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let Data::Enum(data) = &input.data else {
+        return Error::new_spanned(&input.ident, "TokenKind can only be derived for enums")
+            .to_compile_error()
+            .into();
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let kind = format_ident!("{}Kind", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variants: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+    let patterns: Vec<_> = data
+        .variants
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            match v.fields {
+                Fields::Named(_) => quote!(#name::#ident { .. }),
+                Fields::Unnamed(_) => quote!(#name::#ident(..)),
+                Fields::Unit => quote!(#name::#ident),
+            }
+        })
+        .collect();
+    let predicates: Vec<_> = variants
+        .iter()
+        .map(|v| format_ident!("is_{}", snake_case(v)))
+        .collect();
+    // kinds read as rule names in errors, so `LBracket` is "l_bracket"
+    let names: Vec<_> = variants.iter().map(|v| snake_case(v).to_string()).collect();
+
+    quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #vis enum #kind {
+            #(#variants),*
+        }
+
+        impl ::core::fmt::Display for #kind {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(match self {
+                    #(#kind::#variants => #names),*
+                })
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub fn kind(&self) -> #kind {
+                match self {
+                    #(#patterns => #kind::#variants),*
+                }
+            }
+
+            #(
+                pub fn #predicates(&self) -> bool {
+                    matches!(self, #patterns)
+                }
+            )*
+        }
+
+        impl #impl_generics ::roder::token::HasKind for #name #ty_generics #where_clause {
+            type Kind = #kind;
+
+            fn kind(&self) -> Self::Kind {
+                #name::kind(self)
+            }
+        }
+    }
+    .into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    token: Option<Expr>,
+    kind: Option<Expr>,
+    sep: Option<Expr>,
+}
+
+fn field_attrs(attrs: &[Attribute]) -> syn::Result<FieldAttrs> {
+    let mut out = FieldAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("parse")) {
+        attr.parse_nested_meta(|meta| {
+            let slot = if meta.path.is_ident("token") {
+                &mut out.token
+            } else if meta.path.is_ident("kind") {
+                &mut out.kind
+            } else if meta.path.is_ident("sep") {
+                &mut out.sep
+            } else {
+                return Err(meta.error("expected `token`, `kind` or `sep`"));
+            };
+            *slot = Some(meta.value()?.parse()?);
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+fn token_type(input: &DeriveInput) -> syn::Result<Type> {
+    let mut ty = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("parse")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("token_type") {
+                return Err(meta.error("expected `token_type`"));
+            }
+            ty = Some(meta.value()?.parse()?);
+            Ok(())
+        })?;
+    }
+    ty.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "Parseable needs the token type: #[parse(token_type = ...)]",
+        )
+    })
+}
+
+fn vec_item(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let last = path.path.segments.last()?;
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(item) if last.ident == "Vec" => Some(item),
+        _ => None,
+    }
+}
+
+// Tokens as written in the source, `Tk::Semi` rather than the `Tk :: Semi` quote prints, for
+// rule names.
+fn spelled(tokens: &proc_macro2::TokenStream) -> String {
+    let text = tokens.to_string();
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            c != ' '
+                || (word(i.checked_sub(1).map(|i| chars[i])) && word(chars.get(i + 1).copied()))
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+// The parser for one field, and a check run before converting it that the matched token is
+// the one the attribute asked for.
+fn field_parser(
+    field: &Field,
+    index: usize,
+    tt: &Type,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let attrs = field_attrs(&field.attrs)?;
+    let ty = &field.ty;
+    let root = quote!(::roder::parse);
+
+    if let Some(token) = &attrs.token {
+        let name = spelled(&quote!(#token));
+        let parser =
+            quote!(::roder::__private::Box::new(#root::OfType::from(#name, false, #token)));
+        let check = quote! {
+            let token: ::roder::token::Token<#tt> = data.child(#index)?;
+            if *token.ty() != #token {
+                return Err(::roder::parse::ParseError::from(#name.into(), token.span().clone()));
+            }
+        };
+        return Ok((parser, check));
+    }
+    if let Some(kind) = &attrs.kind {
+        let name = spelled(&quote!(#kind));
+        let parser = quote!(::roder::__private::Box::new(#root::OfKind::from(#name, false, #kind)));
+        let check = quote! {
+            let token: ::roder::token::Token<#tt> = data.child(#index)?;
+            if ::roder::token::HasKind::kind(token.ty()) != #kind {
+                return Err(::roder::parse::ParseError::from(#name.into(), token.span().clone()));
+            }
+        };
+        return Ok((parser, check));
+    }
+    if let Some(sep) = &attrs.sep {
+        let Some(item) = vec_item(ty) else {
+            return Err(Error::new_spanned(ty, "`sep` needs a Vec field"));
+        };
+        let name = spelled(&quote!(#ty));
+        let sep_name = spelled(&quote!(#sep));
+        let parser = quote! {
+            ::roder::__private::Box::new(#root::Choice::from(#name, false, ::roder::__private::vec![
+                ::roder::__private::Box::new(#root::Separated::from(
+                    #name,
+                    false,
+                    <#item as ::roder::convert::Parseable<#tt>>::rule(rules),
+                    ::roder::__private::Box::new(#root::OfType::from(#sep_name, false, #sep)),
+                )) as ::roder::__private::Box<dyn #root::Parser<#tt>>,
+                ::roder::__private::Box::new(#root::Empty::from("empty")),
+            ]))
+        };
+        return Ok((parser, quote!()));
+    }
+    Ok((
+        quote!(<#ty as ::roder::convert::Parseable<#tt>>::rule(rules)),
+        quote!(),
+    ))
+}
+
+// A sequence over the fields, and the expression building `path` from its tree.
+fn fields_parser(
+    fields: &Fields,
+    path: proc_macro2::TokenStream,
+    name: &str,
+    tt: &Type,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let mut parsers = vec![];
+    let mut checks = vec![];
+    let mut values = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let (parser, check) = field_parser(field, i, tt)?;
+        parsers.push(parser);
+        checks.push(check);
+        values.push(match &field.ident {
+            Some(ident) => quote!(#ident: data.child(#i)?),
+            None => quote!(data.child(#i)?),
+        });
+    }
+    let len = parsers.len();
+    let parser = quote! {
+        ::roder::__private::Box::new(::roder::parse::Sequence::from(
+            #name,
+            false,
+            ::roder::__private::vec![#(#parsers),*],
+        ))
+    };
+    let build = match fields {
+        Fields::Named(_) => quote!(#path { #(#values),* }),
+        _ => quote!(#path ( #(#values),* )),
+    };
+    let convert = quote! {
+        match data {
+            ::roder::parse::ParseData::Nested(items) if items.len() == #len => {}
+            _ => {
+                return Err(::roder::parse::ParseError::from(
+                    #name.into(),
+                    data.first_token().map(|t| t.span().clone()).unwrap_or_default(),
+                ));
+            }
+        }
+        #(#checks)*
+        Ok(#build)
+    };
+    Ok((parser, convert))
+}
+
+fn derive_parseable_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let tt = token_type(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (parser, convert) = match &input.data {
+        Data::Struct(data) => {
+            let (parser, convert) =
+                fields_parser(&data.fields, quote!(Self), &name.to_string(), &tt)?;
+            (parser, quote!({ #convert }))
+        }
+        Data::Enum(data) => {
+            let mut parsers = vec![];
+            let mut converts = vec![];
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let vname = format!("{name}::{ident}");
+                if let Fields::Unit = variant.fields {
+                    let attrs = field_attrs(&variant.attrs)?;
+                    let Some(token) = attrs.token else {
+                        return Err(Error::new_spanned(
+                            ident,
+                            "unit variants need #[parse(token = ...)]",
+                        ));
+                    };
+                    parsers.push(quote! {
+                        ::roder::__private::Box::new(::roder::parse::OfType::from(#vname, false, #token))
+                    });
+                    converts.push(quote! {
+                        if let ::roder::parse::ParseData::Token(t) = data {
+                            if *t.ty() == #token {
+                                return Ok(Self::#ident);
+                            }
+                        }
+                    });
+                    continue;
+                }
+                let (parser, convert) =
+                    fields_parser(&variant.fields, quote!(Self::#ident), &vname, &tt)?;
+                parsers.push(parser);
+                converts.push(quote! {
+                    let attempt = || -> Result<Self, ::roder::parse::ParseError> { #convert };
+                    match attempt() {
+                        Ok(value) => return Ok(value),
+                        Err(e) => error = Some(::roder::convert::furthest(error, e)),
+                    }
+                });
+            }
+            let sname = name.to_string();
+            let parser = quote! {
+                ::roder::__private::Box::new(::roder::parse::Choice::from(
+                    #sname,
+                    false,
+                    ::roder::__private::vec![#(#parsers),*],
+                ))
+            };
+            // of the variants that could have built the node, the error that got furthest
+            let convert = quote!({
+                let mut error: Option<::roder::parse::ParseError> = None;
+                #(#converts)*
+                Err(error.unwrap_or_else(|| {
+                    ::roder::parse::ParseError::from(
+                        #sname.into(),
+                        data.first_token().map(|t| t.span().clone()).unwrap_or_default(),
+                    )
+                }))
+            });
+            (parser, convert)
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "Parseable can't be derived for unions",
+            ));
+        }
+    };
+
+    // the grammar is kept in a parser shared by every reference to the type, found by TypeId
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(Self: 'static));
+    let parseable_where = &generics.where_clause;
+
+    Ok(quote! {
+        impl #impl_generics ::roder::convert::FromParse<#tt> for #name #ty_generics #where_clause {
+            fn from_parse(
+                data: &::roder::parse::ParseData<#tt>,
+            ) -> Result<Self, ::roder::parse::ParseError> #convert
+        }
+
+        impl #impl_generics ::roder::convert::Parseable<#tt> for #name #ty_generics #parseable_where {
+            fn parser() -> ::roder::__private::Box<dyn ::roder::parse::Parser<#tt>> {
+                ::roder::convert::Rules::new().deferred::<Self>()
+            }
+
+            fn rule(
+                rules: &::roder::convert::Rules<#tt>,
+            ) -> ::roder::__private::Box<dyn ::roder::parse::Parser<#tt>> {
+                #parser
+            }
+        }
+    })
+}
+
+/// Derives `Parseable` and `FromParse` for an AST type, with the grammar read off its shape.
+///
+/// `#[parse(token_type = Tk)]` on the type names the token type. A struct, or an enum variant
+/// with fields, is a sequence of its fields; an enum is a choice between its variants, tried
+/// in order. Fields are parsed by their own `Parseable` impl unless marked:
+///
+/// - `#[parse(token = Tk::Semi)]` matches exactly that token,
+/// - `#[parse(kind = TkKind::Ident)]` matches any token of that kind,
+/// - `#[parse(sep = Tk::Comma)]` on a `Vec` field matches a separated list.
+///
+/// Unit variants stand for a single token and need `#[parse(token = ...)]`.
+#[proc_macro_derive(Parseable, attributes(parse))]
+pub fn derive_parseable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_parseable_impl(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
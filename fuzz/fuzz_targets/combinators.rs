@@ -0,0 +1,35 @@
+//! Runs a grammar using every combinator over arbitrary token streams.
+//!
+//! `cargo +nightly fuzz run combinators -- -timeout=1`
+//!
+//! A panic is a crash and a parse that never returns is reported as a timeout, so this covers
+//! both the "never panics" and "always terminates" properties.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use roder::fuzz::token_stream;
+use roder::prelude::*;
+
+fn grammar() -> Box<dyn Parser<u8>> {
+    let atom = choice([just(0), pred(|t: &u8| *t > 8), empty()]);
+    let item = recover(seq([many(atom), not(just(1)), just(2)]), |t: &u8| *t == 3);
+
+    many(choice([item, just(3)]))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = arbitrary::Unstructured::new(data);
+    let Ok(tokens) = token_stream::<u8>(&mut u, 256) else {
+        return;
+    };
+    let ctx = Context::new(&tokens);
+    let parser = grammar();
+
+    for offset in 0..=tokens.len() {
+        let parse = parser.parse(&ctx, offset);
+        assert!(parse.end_offset() <= tokens.len());
+        assert_eq!(parse.start_offset(), offset);
+    }
+    let _ = parser.parse_all(&tokens);
+});
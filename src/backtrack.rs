@@ -0,0 +1,264 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::generate::Generator;
+use crate::parse::{Context, Parser, Structure};
+use crate::token::{Span, Token};
+
+// Inputs longer than this stop being grown, so the search favours dense blow-ups.
+const MAX_LEN: usize = 48;
+// Rule attempts allowed per parse; an exponential input would otherwise never finish.
+const MAX_STEPS: usize = 1_000_000;
+
+/// The input that made the parser work hardest, from [`worst_case`].
+pub struct BacktrackReport<T> {
+    pub input: Vec<Token<T>>,
+    /// Rule attempts the parser made on `input`.
+    pub steps: usize,
+    /// Input length and rule attempts with `input` repeated 1, 2, 4 and 8 times. Linear
+    /// grammars roughly double their steps from one to the next.
+    pub growth: Vec<(usize, usize)>,
+    /// The places the grammar can backtrack, each with the input aimed at it.
+    pub spots: Vec<Spot<T>>,
+}
+
+/// A Choice with an alternative that an earlier one starts like, or a repetition of a rule that
+/// is optional or repeated itself, from [`BacktrackReport::spots`].
+pub struct Spot<T> {
+    pub rule: String,
+    pub input: Vec<Token<T>>,
+    /// Rule attempts the parser made on `input`.
+    pub steps: usize,
+}
+
+impl<T> BacktrackReport<T> {
+    /// Rule attempts per token of the worst input.
+    pub fn steps_per_token(&self) -> f64 {
+        self.steps as f64 / self.input.len().max(1) as f64
+    }
+
+    /// How many times over the steps grew when the input last doubled, around 2 for linear
+    /// behaviour and far more for an exponential cliff.
+    pub fn growth_ratio(&self) -> f64 {
+        match self.growth.as_slice() {
+            [.., (_, a), (_, b)] => *b as f64 / (*a).max(1) as f64,
+            _ => 1.0,
+        }
+    }
+
+    /// Whether some parse ran into the step cap, which only pathological backtracking does.
+    pub fn hit_cap(&self) -> bool {
+        self.steps >= MAX_STEPS || self.growth.iter().any(|(_, s)| *s >= MAX_STEPS)
+    }
+}
+
+/// Searches for an input that maximises rule attempts per token, for `rounds` rounds. The
+/// search starts from inputs aimed at where the grammar can backtrack, found by walking its
+/// [`structure`](Parser::structure): Choice alternatives that an earlier alternative can start
+/// the same way as (by their [first sets](Parser::first_set)), and repetitions of rules that
+/// are optional or repeated themselves. Inputs `gen` generates join them. The worst input
+/// found is then mutated: stretches of it repeated to deepen nesting, cut short so
+/// alternatives fail late, and tokens swapped in from elsewhere. Steps are measured rather
+/// than predicted, so the report shows how bad each spot really is. Parsers that don't expose
+/// their structure are only reached through generated input. `None` if the parser can't
+/// generate input.
+pub fn worst_case<T: Clone + PartialEq>(
+    parser: &dyn Parser<T>,
+    gen: &mut Generator,
+    rounds: usize,
+) -> Option<BacktrackReport<T>> {
+    let mut seeds: Vec<Vec<T>> = (0..8)
+        .filter_map(|_| gen.tokens(parser))
+        .map(|tokens| tokens.into_iter().map(|t| t.ty().clone()).collect())
+        .collect();
+    let mut aims = vec![];
+    find_spots(parser, &mut vec![], 0, &mut aims);
+    let mut spots = vec![];
+    for (path, aim, rule) in aims {
+        let mut input = vec![];
+        if generate_along(parser, &path, aim, gen, &mut input) {
+            spots.push(Spot {
+                rule,
+                steps: steps(parser, &input),
+                input: layout(input.clone()),
+            });
+            seeds.push(input);
+        }
+    }
+    let vocabulary: Vec<T> = seeds.iter().flatten().cloned().collect();
+
+    let mut best = seeds
+        .iter()
+        .map(|s| (score(parser, s), s.clone()))
+        .max_by(|a, b| a.0.total_cmp(&b.0))?;
+
+    for _ in 0..rounds {
+        let candidate = mutate(&best.1, &vocabulary, gen);
+        let s = score(parser, &candidate);
+        if s > best.0 {
+            best = (s, candidate);
+        }
+    }
+
+    let input = best.1;
+    let growth = [1, 2, 4, 8]
+        .iter()
+        .map(|k| {
+            let repeated: Vec<T> = (0..*k).flat_map(|_| input.iter().cloned()).collect();
+            (repeated.len(), steps(parser, &repeated))
+        })
+        .collect();
+    Some(BacktrackReport {
+        steps: steps(parser, &input),
+        input: layout(input),
+        growth,
+        spots,
+    })
+}
+
+// Where a walk of the grammar can go wrong, and what to generate once there.
+#[derive(Clone, Copy)]
+enum Aim {
+    // input for this alternative, which an earlier one starts like
+    Take,
+    // this repetition's item many times over
+    Repeat,
+}
+
+// Items to aim at a nested repetition, enough for what it costs per item to show.
+const REPEATS: usize = 8;
+// Recursive grammars are only walked this deep.
+const MAX_DEPTH: usize = 16;
+
+// The paths, as child positions from `parser`, to the spots where it can backtrack, with the
+// rule at each.
+fn find_spots<T: PartialEq>(
+    parser: &dyn Parser<T>,
+    path: &mut Vec<usize>,
+    depth: usize,
+    spots: &mut Vec<(Vec<usize>, Aim, String)>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let children = match parser.structure() {
+        Structure::Opaque | Structure::Token | Structure::Reference(_) => vec![],
+        Structure::Sequence(items) => items,
+        Structure::Choice(alternatives) => {
+            let firsts: Vec<_> = alternatives.iter().map(|a| a.first_set()).collect();
+            for (j, b) in firsts.iter().enumerate() {
+                let overlaps = firsts[..j]
+                    .iter()
+                    .any(|a| a.items().iter().any(|e| b.items().contains(e)));
+                if overlaps {
+                    path.push(j);
+                    spots.push((path.clone(), Aim::Take, rule(parser)));
+                    path.pop();
+                }
+            }
+            alternatives
+        }
+        Structure::Repeat(item) => {
+            if item.first_set().is_nullable() || repeats(item) {
+                spots.push((path.clone(), Aim::Repeat, rule(parser)));
+            }
+            vec![item]
+        }
+        Structure::Wraps(inner) => vec![inner],
+        Structure::Handle(handle) => vec![handle.parser()],
+    };
+    for (i, child) in children.into_iter().enumerate() {
+        path.push(i);
+        find_spots(child, path, depth + 1, spots);
+        path.pop();
+    }
+}
+
+fn rule<T>(parser: &dyn Parser<T>) -> String {
+    parser.name().unwrap_or_default().into()
+}
+
+fn repeats<T>(parser: &dyn Parser<T>) -> bool {
+    match parser.structure() {
+        Structure::Repeat(_) => true,
+        Structure::Wraps(inner) => repeats(inner),
+        Structure::Handle(handle) => repeats(handle.parser()),
+        _ => false,
+    }
+}
+
+// Generates input for `parser` that follows `path` down to a spot and does what `aim` says
+// there, generating the rest as usual.
+fn generate_along<T>(
+    parser: &dyn Parser<T>,
+    path: &[usize],
+    aim: Aim,
+    gen: &mut Generator,
+    out: &mut Vec<T>,
+) -> bool {
+    let Some((&next, rest)) = path.split_first() else {
+        return match (aim, parser.structure()) {
+            (Aim::Repeat, Structure::Repeat(item)) => (0..REPEATS).all(|_| item.generate(gen, out)),
+            _ => parser.generate(gen, out),
+        };
+    };
+    match parser.structure() {
+        Structure::Sequence(items) => items.iter().enumerate().all(|(i, item)| {
+            if i == next {
+                generate_along(*item, rest, aim, gen, out)
+            } else {
+                item.generate(gen, out)
+            }
+        }),
+        Structure::Choice(children) => generate_along(children[next], rest, aim, gen, out),
+        Structure::Repeat(child) | Structure::Wraps(child) => {
+            generate_along(child, rest, aim, gen, out)
+        }
+        Structure::Handle(handle) => generate_along(handle.parser(), rest, aim, gen, out),
+        Structure::Opaque | Structure::Token | Structure::Reference(_) => false,
+    }
+}
+
+fn mutate<T: Clone>(input: &[T], vocabulary: &[T], gen: &mut Generator) -> Vec<T> {
+    let mut out = input.to_vec();
+    let len = out.len();
+    match gen.below(3) {
+        // repeat a stretch in place
+        0 if len > 0 && len < MAX_LEN => {
+            let start = gen.below(len);
+            let end = start + 1 + gen.below(len - start);
+            let stretch: Vec<T> = out[start..end].to_vec();
+            out.splice(end..end, stretch);
+            out.truncate(MAX_LEN);
+        }
+        // cut it short
+        1 if len > 1 => out.truncate(1 + gen.below(len - 1)),
+        // swap in a token from elsewhere
+        _ if len > 0 && !vocabulary.is_empty() => {
+            let at = gen.below(len);
+            out[at] = vocabulary[gen.below(vocabulary.len())].clone();
+        }
+        _ => (),
+    }
+    out
+}
+
+fn score<T: Clone>(parser: &dyn Parser<T>, input: &[T]) -> f64 {
+    steps(parser, input) as f64 / input.len().max(1) as f64
+}
+
+fn steps<T: Clone>(parser: &dyn Parser<T>, input: &[T]) -> usize {
+    let tokens = layout(input.to_vec());
+    let ctx = Context::new(&tokens).with_step_budget(MAX_STEPS);
+    parser.parse(&ctx, 0);
+    ctx.steps_taken()
+}
+
+fn layout<T>(tys: Vec<T>) -> Vec<Token<T>> {
+    let mut tokens = vec![];
+    for (i, ty) in tys.into_iter().enumerate() {
+        tokens.push(Token::new(ty, Span::new(1, i * 2 + 1, i * 2 + 1)));
+    }
+    tokens
+}
@@ -0,0 +1,97 @@
+use alloc::string::ToString;
+use core::ops::Range;
+
+use crate::input::TokenInput;
+use crate::parse::{Context, ParseData, ParseError, Parser};
+
+/// The parses of a stream of concatenated documents, such as newline-delimited records. See
+/// [`documents`].
+pub struct Documents<'a, T> {
+    parser: &'a dyn Parser<T>,
+    template: Context<'a, T>,
+    boundary: fn(&T) -> bool,
+    offset: usize,
+}
+
+/// Applies `parser` over and over along `tokens`, one document after another. Tokens
+/// `boundary` accepts separate documents and are skipped between them. A document that fails
+/// to parse, or that parses without reaching the next boundary, yields its error and the rest
+/// of it, up to the next boundary, is skipped, so errors never spill into the following
+/// documents. Each item carries the range of tokens the document covered.
+pub fn documents<'a, T>(
+    parser: &'a dyn Parser<T>,
+    tokens: &'a dyn TokenInput<T>,
+    boundary: fn(&T) -> bool,
+) -> Documents<'a, T> {
+    documents_in(parser, Context::new(tokens), boundary)
+}
+
+/// [`documents`] over the tokens of `template`, parsing each document in a context set up like
+/// it: the same skippable tokens, source, recorder and so on, and a step budget, deadline and
+/// cancellation token that apply to each document on its own.
+pub fn documents_in<'a, T>(
+    parser: &'a dyn Parser<T>,
+    template: Context<'a, T>,
+    boundary: fn(&T) -> bool,
+) -> Documents<'a, T> {
+    Documents {
+        parser,
+        template,
+        boundary,
+        offset: 0,
+    }
+}
+
+impl<T> Documents<'_, T> {
+    fn is_boundary(&self, offset: usize) -> bool {
+        self.template
+            .get(offset)
+            .is_some_and(|t| (self.boundary)(t.ty()))
+    }
+}
+
+impl<T> Iterator for Documents<'_, T> {
+    type Item = (Range<usize>, Result<ParseData<T>, ParseError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.offset = self.template.skip(self.offset);
+        while self.is_boundary(self.offset) {
+            self.offset = self.template.skip(self.offset + 1);
+        }
+        let start = self.offset;
+        if start >= self.template.len() {
+            return None;
+        }
+
+        let ctx = self.template.renewed();
+        let parse = self.parser.parse(&ctx, start);
+        let consumed = parse.consumed();
+        let error = match (ctx.abort_error(), parse.into_result()) {
+            (Some(abort), _) => Some(abort),
+            (None, Ok(Some(data))) if consumed > 0 => {
+                self.offset += consumed;
+                let end = ctx.skip(self.offset);
+                if end >= ctx.len() || self.is_boundary(end) {
+                    return Some((start..self.offset, Ok(data)));
+                }
+                let span = ctx.span_at(end).unwrap_or_default();
+                Some(ctx.settle(ParseError::new(
+                    "end of document".to_string(),
+                    span,
+                    "Unexpected trailing input",
+                )))
+            }
+            (None, Err(e)) => Some(ctx.settle(e)),
+            (None, Ok(_)) => None,
+        };
+
+        while self.offset < self.template.len() && !self.is_boundary(self.offset) {
+            self.offset += 1;
+        }
+        let error = error.unwrap_or_else(|| {
+            let span = ctx.span_at(start).unwrap_or_default();
+            ParseError::from("document".into(), span)
+        });
+        Some((start..self.offset, Err(error)))
+    }
+}
@@ -0,0 +1,109 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+use crate::coverage::Coverage;
+use crate::parse::{Context, ParseResult, Parser};
+use crate::token::Token;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts allocations, so [`bench`] can report them. Install it in the
+/// benchmark binary with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+#[derive(Debug)]
+pub struct BenchReport {
+    pub documents: usize,
+    pub tokens: usize,
+    pub elapsed: Duration,
+    pub errors: usize,
+    pub peak_nodes: usize,
+    pub max_depth: usize,
+    /// `None` unless [`CountingAllocator`] is the global allocator.
+    pub allocations: Option<usize>,
+    /// Rules by number of attempts, most attempted first.
+    pub hot_rules: Vec<(Arc<str>, usize)>,
+}
+
+impl BenchReport {
+    pub fn tokens_per_second(&self) -> f64 {
+        self.tokens as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Parses every document of `corpus` from offset 0. Timing and allocation counts come from a
+/// plain pass; rule attempts come from a second pass with [`Coverage`] attached, so counting
+/// them does not skew the timings.
+pub fn bench<T, D>(parser: &dyn Parser<T>, corpus: &[D]) -> BenchReport
+where
+    D: AsRef<[Token<T>]>,
+{
+    let mut report = BenchReport {
+        documents: corpus.len(),
+        tokens: 0,
+        elapsed: Duration::ZERO,
+        errors: 0,
+        peak_nodes: 0,
+        max_depth: 0,
+        allocations: None,
+        hot_rules: Vec::new(),
+    };
+
+    let allocated = allocations();
+    for doc in corpus {
+        let tokens = doc.as_ref();
+        let start = Instant::now();
+        let parse = parser.parse(&Context::new(&tokens), 0);
+        report.elapsed += start.elapsed();
+        report.tokens += tokens.len();
+
+        match parse.data() {
+            ParseResult::Ok(data) => {
+                report.peak_nodes = report.peak_nodes.max(data.node_count());
+                report.max_depth = report.max_depth.max(data.depth());
+                report.errors += data.errors().len();
+            }
+            ParseResult::Err(_) => report.errors += 1,
+            ParseResult::None => (),
+        }
+    }
+    report.allocations = Some(allocations() - allocated).filter(|a| *a > 0);
+
+    let coverage = Coverage::new();
+    for doc in corpus {
+        let tokens = doc.as_ref();
+        parser.parse(&Context::new(&tokens).with_coverage(&coverage), 0);
+    }
+    report.hot_rules = coverage
+        .rules()
+        .into_iter()
+        .map(|(rule, c)| (rule, c.attempts))
+        .collect();
+    report
+        .hot_rules
+        .sort_by_key(|(_, attempts)| core::cmp::Reverse(*attempts));
+    report
+}
@@ -0,0 +1,155 @@
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::parse::{ErrorKind, ParseError};
+use crate::token::Span;
+
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+// Reading the clock on every rule attempt would dominate small rules.
+#[cfg(feature = "std")]
+const CLOCK_INTERVAL: usize = 256;
+
+/// A flag another thread (or an editor's event loop) can raise to stop a parse in flight.
+/// Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Limits on how much work a parse may do. Once a limit is hit every further rule attempt fails
+/// immediately, so the parse unwinds quickly.
+pub(crate) struct Budget {
+    steps: Option<usize>,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    cancel: Option<CancellationToken>,
+    taken: Cell<usize>,
+    depth: Cell<usize>,
+    deepest: RefCell<(usize, usize, Option<Arc<str>>)>,
+    aborted: Cell<Option<ErrorKind>>,
+}
+
+impl Budget {
+    pub(crate) fn set_steps(&mut self, steps: usize) {
+        self.steps = Some(steps);
+    }
+
+    pub(crate) fn set_cancel(&mut self, cancel: CancellationToken) {
+        self.cancel = Some(cancel);
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// The same limits and cancellation flag with nothing spent, for another parse.
+    pub(crate) fn renewed(&self) -> Self {
+        Self {
+            steps: self.steps,
+            #[cfg(feature = "std")]
+            deadline: self.deadline,
+            cancel: self.cancel.clone(),
+            ..Self::new()
+        }
+    }
+
+    /// Counts a rule attempt. Returns false once the budget is spent or the parse cancelled.
+    pub(crate) fn enter(&self, rule: &Arc<str>, offset: usize) -> bool {
+        if self.aborted.get().is_some() {
+            return false;
+        }
+        let taken = self.taken.get() + 1;
+        self.taken.set(taken);
+
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            self.aborted.set(Some(ErrorKind::Cancelled));
+            return false;
+        }
+        if self.steps.is_some_and(|s| taken > s) || self.past_deadline(taken) {
+            self.aborted.set(Some(ErrorKind::BudgetExceeded));
+            return false;
+        }
+
+        let depth = self.depth.get() + 1;
+        self.depth.set(depth);
+        let mut deepest = self.deepest.borrow_mut();
+        if depth > deepest.0 {
+            *deepest = (depth, offset, Some(rule.clone()));
+        }
+        true
+    }
+
+    #[cfg(feature = "std")]
+    fn past_deadline(&self, taken: usize) -> bool {
+        taken.is_multiple_of(CLOCK_INTERVAL) && self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn past_deadline(&self, _taken: usize) -> bool {
+        false
+    }
+
+    pub(crate) fn exit(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    pub(crate) fn steps_taken(&self) -> usize {
+        self.taken.get()
+    }
+
+    pub(crate) fn aborted(&self) -> Option<ErrorKind> {
+        self.aborted.get()
+    }
+
+    /// The deepest rule reached and the offset it was tried at.
+    pub(crate) fn deepest(&self) -> Option<(Arc<str>, usize)> {
+        let deepest = self.deepest.borrow();
+        deepest.2.clone().map(|rule| (rule, deepest.1))
+    }
+
+    pub(crate) fn error(&self, span: Span) -> ParseError {
+        let rule = self
+            .deepest()
+            .map(|(r, _)| r.to_string())
+            .unwrap_or_default();
+        match self.aborted.get() {
+            Some(ErrorKind::Cancelled) => {
+                ParseError::new(rule, span, "Parse cancelled").with_kind(ErrorKind::Cancelled)
+            }
+            _ => ParseError::new(rule, span, "Parse budget exceeded")
+                .with_kind(ErrorKind::BudgetExceeded),
+        }
+    }
+
+    pub(crate) const fn new() -> Self {
+        Self {
+            steps: None,
+            #[cfg(feature = "std")]
+            deadline: None,
+            cancel: None,
+            taken: Cell::new(0),
+            depth: Cell::new(0),
+            deepest: RefCell::new((0, 0, None)),
+            aborted: Cell::new(None),
+        }
+    }
+}
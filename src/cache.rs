@@ -0,0 +1,157 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+use crate::input::TokenInput;
+use crate::parse::{ParseData, ParseError, Parser};
+
+/// Parse trees kept from earlier runs, keyed by a hash of the input they came from, so a build
+/// tool only reparses files or sections that changed. Only successful parses are kept.
+///
+/// Keys don't identify the grammar, so use one cache per grammar and bump `version` whenever
+/// it changes. With the `serde` feature the cache can be saved between runs; pass what was
+/// loaded through [`for_version`](Self::for_version) to drop trees from an older grammar.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseCache<T> {
+    version: u64,
+    entries: BTreeMap<u64, ParseData<T>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hits: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    misses: usize,
+}
+
+impl<T> ParseCache<T> {
+    pub const fn new(version: u64) -> Self {
+        Self {
+            version,
+            entries: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The cache as is if it was made for `version`, or an empty one for it.
+    pub fn for_version(self, version: u64) -> Self {
+        if self.version == version {
+            self
+        } else {
+            Self::new(version)
+        }
+    }
+
+    /// Parses `tokens`, or finds the tree from the last time the same tokens, spans included,
+    /// were parsed.
+    pub fn parse_all(
+        &mut self,
+        parser: &dyn Parser<T>,
+        tokens: &dyn TokenInput<T>,
+    ) -> Result<&ParseData<T>, Vec<ParseError>>
+    where
+        T: Hash,
+    {
+        let key = token_key(tokens);
+        if self.entries.contains_key(&key) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            let data = parser.parse_all(tokens)?;
+            self.entries.insert(key, data);
+        }
+        Ok(&self.entries[&key])
+    }
+
+    /// Looks a tree up by a key of the caller's, such as [`source_key`] for a chunk of source.
+    pub fn get(&mut self, key: u64) -> Option<&ParseData<T>> {
+        let data = self.entries.get(&key);
+        match data {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+        data
+    }
+
+    pub fn insert(&mut self, key: u64, data: ParseData<T>) {
+        self.entries.insert(key, data);
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// A hash of `tokens`, types, spans and trivia included. It is stable across runs and
+/// platforms, so it can key a saved cache.
+pub fn token_key<T: Hash>(tokens: &dyn TokenInput<T>) -> u64 {
+    let mut hasher = Fnv::new();
+    tokens.len().hash(&mut hasher);
+    for token in (0..tokens.len()).filter_map(|i| tokens.get(i)) {
+        token.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// A hash of `source`, stable across runs and platforms.
+pub fn source_key(source: &str) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(source.as_bytes());
+    hasher.finish()
+}
+
+// FNV-1a, which unlike the std hasher gives the same keys in every run.
+struct Fnv(u64);
+
+impl Fnv {
+    const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // integers are written little-endian and sizes as 64 bits, whatever the platform
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
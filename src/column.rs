@@ -0,0 +1,101 @@
+/// What a column in a [`Span`](crate::token::Span) counts, or the position encoding in protocol
+/// terms. Lexers should count columns the same way the diagnostics rendering them is told to,
+/// and [`Span::convert`](crate::token::Span::convert) translates between policies.
+///
+/// Grapheme clusters and display width are approximated without Unicode tables: combining
+/// marks, variation selectors, emoji modifiers, zero-width joiner sequences and flag pairs
+/// extend a cluster, and East Asian wide characters and emoji take two cells. That covers
+/// what usually turns up in source code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnPolicy {
+    /// UTF-8 bytes.
+    Bytes,
+    /// UTF-16 code units, as LSP counts by default.
+    Utf16,
+    /// Unicode scalar values.
+    #[default]
+    Chars,
+    Graphemes,
+    /// Terminal cells, so a column lines up with what the user sees.
+    Width,
+}
+
+impl ColumnPolicy {
+    /// The byte offset in `line` at which 1-based column `col` starts, or the line's length
+    /// for the column just past its end.
+    pub fn byte_offset(self, line: &str, col: usize) -> Option<usize> {
+        let col = col.checked_sub(1)?;
+        let mut at = 0;
+        for (start, len) in self.units(line) {
+            if at == col {
+                return Some(start);
+            }
+            at += len;
+            if at > col {
+                return None;
+            }
+        }
+        (at == col).then_some(line.len())
+    }
+
+    /// The 1-based column at which byte offset `byte` of `line` falls, for lexers.
+    pub fn column(self, line: &str, byte: usize) -> usize {
+        1 + self
+            .units(line)
+            .take_while(|(start, _)| *start < byte)
+            .map(|(_, len)| len)
+            .sum::<usize>()
+    }
+
+    // Each unit is where it starts and how many columns it takes.
+    fn units(self, line: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut chars = line.char_indices().peekable();
+        core::iter::from_fn(move || {
+            let (start, c) = chars.next()?;
+            match self {
+                ColumnPolicy::Bytes => Some((start, c.len_utf8())),
+                ColumnPolicy::Utf16 => Some((start, c.len_utf16())),
+                ColumnPolicy::Chars => Some((start, 1)),
+                ColumnPolicy::Graphemes | ColumnPolicy::Width => {
+                    let mut prev = c;
+                    let mut flags = is_regional(c) as usize;
+                    while let Some(&(_, next)) = chars.peek() {
+                        let pairs_flag = is_regional(next) && flags == 1;
+                        if !(extends(next) || prev == ZWJ || pairs_flag) {
+                            break;
+                        }
+                        flags += is_regional(next) as usize;
+                        prev = next;
+                        chars.next();
+                    }
+                    let width = match self {
+                        ColumnPolicy::Width if is_wide(c) || flags == 2 => 2,
+                        ColumnPolicy::Width if extends(c) => 0,
+                        _ => 1,
+                    };
+                    Some((start, width))
+                }
+            }
+        })
+    }
+}
+
+const ZWJ: char = '\u{200D}';
+
+fn extends(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+        | 0x200C | 0x200D | 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F)
+}
+
+fn is_regional(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD)
+}
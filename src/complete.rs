@@ -0,0 +1,78 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::input::{TokenInput, Truncated};
+use crate::parse::{Context, Parser};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected<T> {
+    /// A specific token type, from parsers that match one.
+    Token(T),
+    /// A named token class, from predicate and kind parsers.
+    Rule(Arc<str>),
+}
+
+/// Collects what leaf parsers expected at one offset.
+pub struct ExpectedSet<T> {
+    at: usize,
+    // each with the trace step of the rule that expected it
+    items: RefCell<Vec<(usize, Expected<T>)>>,
+}
+
+impl<T> ExpectedSet<T> {
+    pub(crate) fn record(
+        &self,
+        offset: usize,
+        step: usize,
+        expected: impl FnOnce() -> Expected<T>,
+    ) {
+        if offset == self.at {
+            self.items.borrow_mut().push((step, expected()));
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<Expected<T>> {
+        self.items
+            .into_inner()
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect()
+    }
+
+    // Items with the index of the Enter step of the rule that recorded them.
+    pub(crate) fn into_steps(self) -> Vec<(usize, Expected<T>)> {
+        self.items.into_inner()
+    }
+
+    pub const fn new(at: usize) -> Self {
+        Self {
+            at,
+            items: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+/// Everything that could come next after the first `cursor` tokens, in the order the parser
+/// tried it and without duplicates. Input from the cursor on is hidden, so every alternative
+/// that gets that far fails there and reports what it wanted.
+pub fn expected_at<T>(
+    parser: &dyn Parser<T>,
+    tokens: &dyn TokenInput<T>,
+    cursor: usize,
+) -> Vec<Expected<T>>
+where
+    T: PartialEq,
+{
+    let input = Truncated::new(tokens, cursor);
+    let expected = ExpectedSet::new(cursor);
+    parser.parse(&Context::new(&input).with_expected(&expected), 0);
+
+    let mut out: Vec<Expected<T>> = Vec::new();
+    for e in expected.into_vec() {
+        if !out.contains(&e) {
+            out.push(e);
+        }
+    }
+    out
+}
@@ -0,0 +1,282 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name, TypeId};
+use core::cell::{OnceCell, RefCell};
+
+use crate::index::first_span;
+use crate::parse::{
+    Choice, Deferred, Empty, ParseData, ParseError, Parser, Repeatable, Sequence, Slot,
+};
+use crate::token::Token;
+
+#[cfg(feature = "derive")]
+pub use roder_derive::Parseable;
+
+/// Conversion from a parse tree node, for lowering trees into typed structures without
+/// indexing into nested vectors by hand.
+/// Leaf types implement [`from_token`](FromParse::from_token), which also serves the items of
+/// a token list; composite types implement [`from_parse`](FromParse::from_parse).
+pub trait FromParse<T>: Sized {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        match data {
+            ParseData::Token(t) => Self::from_token(t),
+            _ => Err(shape(data, "a token")),
+        }
+    }
+
+    fn from_token(token: &Token<T>) -> Result<Self, ParseError> {
+        Err(ParseError::from("a node".to_string(), token.span().clone())
+            .with_message(String::from("Unexpected node shape, found a token")))
+    }
+}
+
+impl<T: Clone> FromParse<T> for Token<T> {
+    fn from_token(token: &Token<T>) -> Result<Self, ParseError> {
+        Ok(token.clone())
+    }
+}
+
+impl<T, U: FromParse<T>> FromParse<T> for Option<U> {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        data.optional()
+    }
+}
+
+impl<T, U: FromParse<T>> FromParse<T> for Box<U> {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        U::from_parse(data).map(Box::new)
+    }
+
+    fn from_token(token: &Token<T>) -> Result<Self, ParseError> {
+        U::from_token(token).map(Box::new)
+    }
+}
+
+impl<T, U: FromParse<T>> FromParse<T> for Vec<U> {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        data.collect()
+    }
+}
+
+impl<T, A: FromParse<T>, B: FromParse<T>> FromParse<T> for (A, B) {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        expect_len(data, 2)?;
+        Ok((data.child(0)?, data.child(1)?))
+    }
+}
+
+impl<T, A: FromParse<T>, B: FromParse<T>, C: FromParse<T>> FromParse<T> for (A, B, C) {
+    fn from_parse(data: &ParseData<T>) -> Result<Self, ParseError> {
+        expect_len(data, 3)?;
+        Ok((data.child(0)?, data.child(1)?, data.child(2)?))
+    }
+}
+
+/// A type that knows its own grammar, so a parser for it can be built from the type alone.
+/// Usually derived: see [`roder_derive::Parseable`](macro@Parseable) for the attributes.
+pub trait Parseable<T>: FromParse<T> {
+    /// A parser whose tree [`from_parse`](FromParse::from_parse) accepts.
+    fn parser() -> Box<dyn Parser<T>>;
+
+    /// This type's parser as part of the grammar `rules` is building, reusing the parsers it
+    /// already holds for boxed types. Defaults to a parser of its own.
+    #[doc(hidden)]
+    fn rule(_rules: &Rules<T>) -> Box<dyn Parser<T>> {
+        Self::parser()
+    }
+}
+
+/// The grammar of a set of types being built. Each boxed type gets one parser, built the first
+/// time the type is reached, that every other reference to it shares, so a recursive type is
+/// built once rather than once for every level the input nests.
+#[doc(hidden)]
+pub struct Rules<T> {
+    slots: RefCell<Vec<(TypeId, Slot<T>)>>,
+}
+
+impl<T: 'static> Rules<T> {
+    pub const fn new() -> Self {
+        Self {
+            slots: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The shared parser for `U`.
+    pub fn deferred<U: Parseable<T> + 'static>(&self) -> Box<dyn Parser<T>> {
+        let id = TypeId::of::<U>();
+        let pty: Arc<str> = ident::<U>().into();
+        let found = self
+            .slots
+            .borrow()
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, s)| s.clone());
+        match found {
+            // reached again from inside its own grammar
+            Some(slot) if slot.get().is_none() => Box::new(Deferred::within(pty, &slot)),
+            Some(slot) => Box::new(Deferred::shared(pty, slot)),
+            None => {
+                let slot: Slot<T> = Rc::new(OnceCell::new());
+                self.slots.borrow_mut().push((id, slot.clone()));
+                let _ = slot.set(U::rule(self));
+                Box::new(Deferred::shared(pty, slot))
+            }
+        }
+    }
+}
+
+impl<T: 'static> Default for Rules<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Of the errors converting a node every way it might have been parsed, the one that got
+/// furthest into the input, the earlier on a tie.
+#[doc(hidden)]
+pub fn furthest(best: Option<ParseError>, error: ParseError) -> ParseError {
+    match best {
+        Some(best)
+            if (error.span().ln(), error.span().cs()) <= (best.span().ln(), best.span().cs()) =>
+        {
+            best
+        }
+        _ => error,
+    }
+}
+
+// A type's name without the module paths `type_name` spells out, so rules read `Vec<Expr>`
+// rather than `alloc::vec::Vec<my_crate::ast::Expr>`.
+fn ident<U: ?Sized>() -> String {
+    let mut out = String::new();
+    let mut segment = 0;
+    let mut chars = type_name::<U>().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            out.truncate(segment);
+        } else {
+            out.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment = out.len();
+            }
+        }
+    }
+    out
+}
+
+// Lists and options never produce `ParseResult::None`: a sequence would drop it and shift the
+// positions of the fields after it.
+impl<T: Clone + 'static, U: Parseable<T>> Parseable<T> for Vec<U> {
+    fn parser() -> Box<dyn Parser<T>> {
+        Self::rule(&Rules::new())
+    }
+
+    fn rule(rules: &Rules<T>) -> Box<dyn Parser<T>> {
+        let pty = ident::<Self>();
+        let items = Repeatable::from(&pty, false, U::rule(rules));
+        Box::new(Choice::from(
+            &pty,
+            false,
+            vec![Box::new(items), Box::new(Empty::from("empty"))],
+        ))
+    }
+}
+
+impl<T: Clone + 'static, U: Parseable<T>> Parseable<T> for Option<U> {
+    fn parser() -> Box<dyn Parser<T>> {
+        Self::rule(&Rules::new())
+    }
+
+    fn rule(rules: &Rules<T>) -> Box<dyn Parser<T>> {
+        let pty = ident::<Self>();
+        let some = Sequence::from(&pty, false, vec![U::rule(rules)]);
+        Box::new(Choice::from(
+            &pty,
+            false,
+            vec![Box::new(some), Box::new(Empty::from("empty"))],
+        ))
+    }
+}
+
+// Boxes are how recursive types refer to themselves, so every box of a type shares the one
+// parser for it.
+impl<T: 'static, U: Parseable<T> + 'static> Parseable<T> for Box<U> {
+    fn parser() -> Box<dyn Parser<T>> {
+        Self::rule(&Rules::new())
+    }
+
+    fn rule(rules: &Rules<T>) -> Box<dyn Parser<T>> {
+        rules.deferred::<U>()
+    }
+}
+
+impl<T> ParseData<T> {
+    pub fn to<U: FromParse<T>>(&self) -> Result<U, ParseError> {
+        U::from_parse(self)
+    }
+
+    /// Converts every child of a list node, into a `Vec`, a map from `(key, value)` pairs, or
+    /// any other collection.
+    pub fn collect<C, U>(&self) -> Result<C, ParseError>
+    where
+        C: FromIterator<U>,
+        U: FromParse<T>,
+    {
+        match self {
+            ParseData::Nested(l) => l.iter().map(U::from_parse).collect(),
+            ParseData::TokenList(l) => l.iter().map(U::from_token).collect(),
+            _ => Err(shape(self, "a list")),
+        }
+    }
+
+    /// Converts the child at `index` of a sequence.
+    pub fn child<U: FromParse<T>>(&self, index: usize) -> Result<U, ParseError> {
+        match self {
+            ParseData::Nested(l) => match l.get(index) {
+                Some(d) => U::from_parse(d),
+                None => Err(shape(self, &format!("at least {} items", index + 1))),
+            },
+            ParseData::TokenList(l) => match l.get(index) {
+                Some(t) => U::from_token(t),
+                None => Err(shape(self, &format!("at least {} items", index + 1))),
+            },
+            _ => Err(shape(self, "a list")),
+        }
+    }
+
+    /// Converts a list of at most one item, as an optional construct wrapped in a repetition
+    /// produces.
+    pub fn optional<U: FromParse<T>>(&self) -> Result<Option<U>, ParseError> {
+        match self {
+            ParseData::Nested(l) if l.len() <= 1 => l.first().map(U::from_parse).transpose(),
+            ParseData::TokenList(l) if l.len() <= 1 => l.first().map(U::from_token).transpose(),
+            _ => Err(shape(self, "at most one item")),
+        }
+    }
+}
+
+fn expect_len<T>(data: &ParseData<T>, len: usize) -> Result<(), ParseError> {
+    match data {
+        ParseData::Nested(l) if l.len() == len => Ok(()),
+        ParseData::TokenList(l) if l.len() == len => Ok(()),
+        _ => Err(shape(data, &format!("{len} items"))),
+    }
+}
+
+fn shape<T>(data: &ParseData<T>, expected: &str) -> ParseError {
+    let found = match data {
+        ParseData::Nested(l) => format!("a sequence of {}", l.len()),
+        ParseData::TokenList(l) => format!("a list of {} tokens", l.len()),
+        ParseData::Token(_) => String::from("a token"),
+        ParseData::Error { .. } => String::from("an error node"),
+    };
+    let span = first_span(data).cloned().unwrap_or_default();
+    ParseError::from(expected.to_string(), span)
+        .with_message(format!("Unexpected node shape, found {found}"))
+}
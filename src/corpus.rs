@@ -0,0 +1,180 @@
+use std::boxed::Box;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+use std::{fs, io};
+
+use crate::parse::Parser;
+use crate::snapshot::parse_snapshot;
+use crate::token::Token;
+
+const EXPECTED: &str = "expected";
+
+pub use crate::token::Lexer;
+
+#[derive(Debug, PartialEq)]
+pub enum CaseOutcome {
+    Passed,
+    Failed {
+        expected: String,
+        actual: String,
+    },
+    /// No expectation file exists and bless mode is off.
+    Missing {
+        actual: String,
+    },
+    /// The expectation file was written or rewritten in bless mode.
+    Blessed,
+}
+
+pub struct Case {
+    pub path: PathBuf,
+    pub outcome: CaseOutcome,
+}
+
+pub struct CorpusReport {
+    pub cases: Vec<Case>,
+}
+
+impl CorpusReport {
+    pub fn is_ok(&self) -> bool {
+        self.cases
+            .iter()
+            .all(|c| matches!(c.outcome, CaseOutcome::Passed | CaseOutcome::Blessed))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Case> {
+        self.cases
+            .iter()
+            .filter(|c| !matches!(c.outcome, CaseOutcome::Passed | CaseOutcome::Blessed))
+    }
+}
+
+impl Display for CorpusReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for case in self.failures() {
+            match &case.outcome {
+                CaseOutcome::Failed { expected, actual } => {
+                    writeln!(f, "FAILED {}", case.path.display())?;
+                    write_diff(f, expected, actual)?;
+                }
+                CaseOutcome::Missing { .. } => {
+                    writeln!(f, "MISSING {}.{EXPECTED}", case.path.display())?
+                }
+                _ => (),
+            }
+        }
+        let failed = self.failures().count();
+        writeln!(f, "{} passed, {failed} failed", self.cases.len() - failed)
+    }
+}
+
+/// Parses every file in a directory and compares the snapshot (see
+/// [`parse_snapshot`]) with the sibling file of the same name plus `.expected`.
+pub struct Corpus<T> {
+    dir: PathBuf,
+    lex: Lexer<T>,
+    spans: bool,
+    bless: bool,
+}
+
+impl<T: Debug> Corpus<T> {
+    /// Writes actual output over the expectation files instead of comparing.
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    pub fn with_spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    pub fn run(&self, parser: &dyn Parser<T>) -> io::Result<CorpusReport> {
+        let mut inputs = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_none_or(|e| e != EXPECTED) {
+                inputs.push(path);
+            }
+        }
+        inputs.sort();
+
+        let mut cases = vec![];
+        for path in inputs {
+            let tokens = (self.lex)(&fs::read_to_string(&path)?);
+            let actual = parse_snapshot(parser, &tokens, self.spans);
+            let outcome = self.check(&expected_path(&path), actual)?;
+            cases.push(Case { path, outcome });
+        }
+        Ok(CorpusReport { cases })
+    }
+
+    fn check(&self, expected_path: &Path, actual: String) -> io::Result<CaseOutcome> {
+        let expected = match fs::read_to_string(expected_path) {
+            Ok(e) => Some(e),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(match expected {
+            Some(e) if e == actual => CaseOutcome::Passed,
+            _ if self.bless => {
+                fs::write(expected_path, actual)?;
+                CaseOutcome::Blessed
+            }
+            Some(expected) => CaseOutcome::Failed { expected, actual },
+            None => CaseOutcome::Missing { actual },
+        })
+    }
+
+    pub fn new(dir: impl Into<PathBuf>, lex: impl Fn(&str) -> Vec<Token<T>> + 'static) -> Self {
+        Self {
+            dir: dir.into(),
+            lex: Box::new(lex),
+            spans: false,
+            bless: false,
+        }
+    }
+}
+
+fn expected_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(EXPECTED);
+    path.with_file_name(name)
+}
+
+// A line diff over the longest common subsequence; corpus files are small enough for the
+// quadratic table.
+fn write_diff(f: &mut Formatter<'_>, expected: &str, actual: &str) -> std::fmt::Result {
+    let a: Vec<_> = expected.lines().collect();
+    let b: Vec<_> = actual.lines().collect();
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            writeln!(f, "  {}", a[i])?;
+            (i, j) = (i + 1, j + 1);
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            writeln!(f, "- {}", a[i])?;
+            i += 1;
+        } else {
+            writeln!(f, "+ {}", b[j])?;
+            j += 1;
+        }
+    }
+    Ok(())
+}
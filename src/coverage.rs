@@ -0,0 +1,100 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RuleCoverage {
+    pub attempts: usize,
+    pub matches: usize,
+}
+
+/// Counts how often each rule was attempted and matched, and which alternative of each Choice
+/// matched, across every parse run through a [`Context`](crate::parse::Context) it is attached
+/// to. Rules are keyed by name, so rules sharing a name are counted together.
+#[derive(Default)]
+pub struct Coverage {
+    rules: RefCell<BTreeMap<Arc<str>, RuleCoverage>>,
+    alternatives: RefCell<BTreeMap<Arc<str>, Vec<usize>>>,
+}
+
+impl Coverage {
+    pub(crate) fn rule(&self, rule: &Arc<str>, matched: bool) {
+        let mut rules = self.rules.borrow_mut();
+        let entry = rules.entry(rule.clone()).or_default();
+        entry.attempts += 1;
+        entry.matches += usize::from(matched);
+    }
+
+    pub(crate) fn alternatives(&self, rule: &Arc<str>, count: usize) {
+        let mut alternatives = self.alternatives.borrow_mut();
+        let hits = alternatives.entry(rule.clone()).or_default();
+        if hits.len() < count {
+            hits.resize(count, 0);
+        }
+    }
+
+    pub(crate) fn alternative(&self, rule: &Arc<str>, index: usize) {
+        if let Some(hits) = self.alternatives.borrow_mut().get_mut(rule) {
+            hits[index] += 1;
+        }
+    }
+
+    pub fn get(&self, rule: &str) -> Option<RuleCoverage> {
+        self.rules.borrow().get(rule).copied()
+    }
+
+    pub fn rules(&self) -> Vec<(Arc<str>, RuleCoverage)> {
+        self.rules
+            .borrow()
+            .iter()
+            .map(|(rule, c)| (rule.clone(), *c))
+            .collect()
+    }
+
+    /// Matches per alternative of the Choice named `rule`.
+    pub fn alternative_hits(&self, rule: &str) -> Option<Vec<usize>> {
+        self.alternatives.borrow().get(rule).cloned()
+    }
+
+    /// Rules from `rules` that never matched.
+    pub fn unmatched<'r>(&self, rules: &[&'r str]) -> Vec<&'r str> {
+        let seen = self.rules.borrow();
+        rules
+            .iter()
+            .filter(|r| seen.get(**r).is_none_or(|c| c.matches == 0))
+            .copied()
+            .collect()
+    }
+
+    /// Choice alternatives that were never the one to match, as (choice, index) pairs.
+    pub fn unmatched_alternatives(&self) -> Vec<(Arc<str>, usize)> {
+        let mut out = vec![];
+        for (rule, hits) in self.alternatives.borrow().iter() {
+            for (i, _) in hits.iter().enumerate().filter(|(_, h)| **h == 0) {
+                out.push((rule.clone(), i));
+            }
+        }
+        out
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let alternatives = self.alternatives.borrow();
+
+        for (rule, c) in self.rules.borrow().iter() {
+            writeln!(f, "{rule}: {}/{} matched", c.matches, c.attempts)?;
+            for (i, hits) in alternatives.get(rule).into_iter().flatten().enumerate() {
+                writeln!(f, "  #{i}: {hits}")?;
+            }
+        }
+        Ok(())
+    }
+}
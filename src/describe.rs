@@ -0,0 +1,97 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::complete::Expected;
+
+/// Renders what was expected as a sentence for users rather than a dump of rule names: tokens
+/// quoted, rule names spelt out with an article, the last two joined by "or", and anything past
+/// the limit summed up as "…and N more".
+#[derive(Clone, Debug)]
+pub struct ExpectedList {
+    open: String,
+    close: String,
+    articles: bool,
+    limit: usize,
+}
+
+impl ExpectedList {
+    /// Quotes tokens in backticks and shows up to five items.
+    pub fn new() -> Self {
+        Self {
+            open: "`".into(),
+            close: "`".into(),
+            articles: true,
+            limit: 5,
+        }
+    }
+
+    /// Puts `open` and `close` around tokens, such as `'` and `'`, or nothing for none.
+    pub fn with_quotes(mut self, open: &str, close: &str) -> Self {
+        self.open = open.into();
+        self.close = close.into();
+        self
+    }
+
+    /// Whether rule names get "a" or "an" in front, so `identifier` reads "an identifier".
+    pub fn with_articles(mut self, articles: bool) -> Self {
+        self.articles = articles;
+        self
+    }
+
+    /// How many items to show before summing up the rest; at least one is always shown.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// Formats `items` without duplicates, writing tokens with `token`. An empty list reads
+    /// "nothing".
+    pub fn format<T: PartialEq>(
+        &self,
+        items: &[Expected<T>],
+        token: impl Fn(&T) -> String,
+    ) -> String {
+        let mut unique: Vec<&Expected<T>> = Vec::new();
+        for item in items {
+            if !unique.contains(&item) {
+                unique.push(item);
+            }
+        }
+        let shown: Vec<String> = unique
+            .iter()
+            .take(self.limit)
+            .map(|item| match item {
+                Expected::Token(t) => format!("{}{}{}", self.open, token(t), self.close),
+                Expected::Rule(rule) => self.rule(rule),
+            })
+            .collect();
+
+        let hidden = unique.len() - shown.len();
+        match (shown.as_slice(), hidden) {
+            ([], _) => "nothing".into(),
+            ([only], 0) => only.clone(),
+            ([init @ .., last], 0) => format!("{} or {last}", init.join(", ")),
+            (all, n) => format!("{}, …and {n} more", all.join(", ")),
+        }
+    }
+
+    // `if_stmt` and `if-stmt` both read "an if stmt".
+    fn rule(&self, rule: &str) -> String {
+        let name = rule.replace(['_', '-'], " ");
+        if !self.articles {
+            return name;
+        }
+        let vowel = name
+            .chars()
+            .next()
+            .is_some_and(|c| "aeiouAEIOU".contains(c));
+        format!("{} {name}", if vowel { "an" } else { "a" })
+    }
+}
+
+impl Default for ExpectedList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
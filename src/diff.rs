@@ -0,0 +1,146 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::index::first_span;
+use crate::parse::ParseData;
+use crate::token::{Span, Token};
+
+/// One difference between two trees, pointing at the subtrees involved.
+pub enum TreeEdit<'d, T> {
+    /// A subtree of the new tree with no counterpart in the old one.
+    Inserted(&'d ParseData<T>),
+    /// A subtree of the old tree with no counterpart in the new one.
+    Deleted(&'d ParseData<T>),
+    /// A subtree that is unchanged but sits somewhere else.
+    Moved {
+        from: &'d ParseData<T>,
+        to: &'d ParseData<T>,
+    },
+}
+
+impl<T> TreeEdit<'_, T> {
+    /// Where the edit starts: in the new tree for insertions and moves, in the old one for
+    /// deletions.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            TreeEdit::Inserted(data) | TreeEdit::Deleted(data) => first_span(data),
+            TreeEdit::Moved { to, .. } => first_span(to),
+        }
+    }
+}
+
+/// Structural differences between `old` and `new`. Nodes match when they have the same shape
+/// and token types, whatever their spans, so reformatting alone gives no edits. Children are
+/// aligned by longest common subsequence; unaligned nested nodes facing each other are compared
+/// in turn, and a deleted subtree that reappears unchanged elsewhere is reported as moved.
+pub fn diff<'d, T: PartialEq>(
+    old: &'d ParseData<T>,
+    new: &'d ParseData<T>,
+) -> Vec<TreeEdit<'d, T>> {
+    let mut edits = vec![];
+    walk(old, new, &mut edits);
+    pair_moves(edits)
+}
+
+fn walk<'d, T: PartialEq>(
+    old: &'d ParseData<T>,
+    new: &'d ParseData<T>,
+    edits: &mut Vec<TreeEdit<'d, T>>,
+) {
+    if same(old, new) {
+        return;
+    }
+    let (ParseData::Nested(a), ParseData::Nested(b)) = (old, new) else {
+        edits.push(TreeEdit::Deleted(old));
+        edits.push(TreeEdit::Inserted(new));
+        return;
+    };
+
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in common(a, b).into_iter().chain([(a.len(), b.len())]) {
+        // the gap before the next pair of matching children
+        while i < mi && j < mj {
+            walk(&a[i], &b[j], edits);
+            i += 1;
+            j += 1;
+        }
+        edits.extend(a[i..mi].iter().map(TreeEdit::Deleted));
+        edits.extend(b[j..mj].iter().map(TreeEdit::Inserted));
+        (i, j) = (mi + 1, mj + 1);
+    }
+}
+
+// Index pairs of the longest common subsequence of matching children.
+fn common<T: PartialEq>(a: &[ParseData<T>], b: &[ParseData<T>]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(&a[i], &b[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn pair_moves<T: PartialEq>(edits: Vec<TreeEdit<'_, T>>) -> Vec<TreeEdit<'_, T>> {
+    let mut edits: Vec<Option<TreeEdit<'_, T>>> = edits.into_iter().map(Some).collect();
+    for k in 0..edits.len() {
+        let Some(TreeEdit::Deleted(from)) = edits[k] else {
+            continue;
+        };
+        let to = edits
+            .iter()
+            .position(|e| matches!(e, Some(TreeEdit::Inserted(to)) if same(from, to)));
+        if let Some(to) = to {
+            let Some(TreeEdit::Inserted(to)) = edits[to].take() else {
+                unreachable!()
+            };
+            edits[k] = Some(TreeEdit::Moved { from, to });
+        }
+    }
+    edits.into_iter().flatten().collect()
+}
+
+pub(crate) fn same<T: PartialEq>(a: &ParseData<T>, b: &ParseData<T>) -> bool {
+    match (a, b) {
+        (ParseData::Nested(a), ParseData::Nested(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (ParseData::TokenList(a), ParseData::TokenList(b)) => same_tokens(a, b),
+        (ParseData::Token(a), ParseData::Token(b)) => a.ty() == b.ty(),
+        (
+            ParseData::Error {
+                expected: a,
+                skipped: sa,
+                ..
+            },
+            ParseData::Error {
+                expected: b,
+                skipped: sb,
+                ..
+            },
+        ) => a == b && same_tokens(sa, sb),
+        _ => false,
+    }
+}
+
+fn same_tokens<T: PartialEq>(a: &[Token<T>], b: &[Token<T>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.ty() == b.ty())
+}
@@ -0,0 +1,128 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::parse::ParseData;
+use crate::token::Token;
+
+/// Rewrites a tree in place for refactoring tools. Nodes are addressed by path, the child
+/// indices from the root down. Content brought in by an edit keeps whatever spans it came
+/// with, so every edited node is marked dirty until the tool has re-spanned it, say by
+/// rendering the tree and parsing it again; paths in the dirty list follow later insertions
+/// and removals.
+pub struct Editor<'d, T> {
+    root: &'d mut ParseData<T>,
+    dirty: Vec<Vec<usize>>,
+}
+
+impl<'d, T> Editor<'d, T> {
+    pub fn new(root: &'d mut ParseData<T>) -> Self {
+        Self {
+            root,
+            dirty: vec![],
+        }
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&ParseData<T>> {
+        path.iter().try_fold(&*self.root, |node, i| match node {
+            ParseData::Nested(children) => children.get(*i),
+            _ => None,
+        })
+    }
+
+    fn get_mut(&mut self, path: &[usize]) -> Option<&mut ParseData<T>> {
+        path.iter().try_fold(&mut *self.root, |node, i| match node {
+            ParseData::Nested(children) => children.get_mut(*i),
+            _ => None,
+        })
+    }
+
+    fn children(&mut self, path: &[usize]) -> Option<&mut Vec<ParseData<T>>> {
+        match self.get_mut(path)? {
+            ParseData::Nested(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Puts `node` in place of the subtree at `path` and gives back the old one.
+    pub fn replace(&mut self, path: &[usize], node: ParseData<T>) -> Option<ParseData<T>> {
+        let old = core::mem::replace(self.get_mut(path)?, node);
+        self.dirty.retain(|d| !d.starts_with(path));
+        self.mark(path.to_vec());
+        Some(old)
+    }
+
+    /// Inserts `node` as child `index` of the nested node at `path`.
+    pub fn insert(&mut self, path: &[usize], index: usize, node: ParseData<T>) -> bool {
+        let Some(children) = self.children(path).filter(|c| index <= c.len()) else {
+            return false;
+        };
+        children.insert(index, node);
+        self.shift(path, index, 1);
+        let mut at = path.to_vec();
+        at.push(index);
+        self.mark(at);
+        true
+    }
+
+    /// Removes child `index` of the nested node at `path`, leaving its parent dirty.
+    pub fn remove(&mut self, path: &[usize], index: usize) -> Option<ParseData<T>> {
+        let children = self.children(path).filter(|c| index < c.len())?;
+        let old = children.remove(index);
+        let mut at = path.to_vec();
+        at.push(index);
+        self.dirty.retain(|d| !d.starts_with(&at));
+        self.shift(path, index + 1, -1);
+        self.mark(path.to_vec());
+        Some(old)
+    }
+
+    /// Replaces `range` of the token list at `path` with `tokens`, giving back the old ones.
+    pub fn splice(
+        &mut self,
+        path: &[usize],
+        range: Range<usize>,
+        tokens: impl IntoIterator<Item = Token<T>>,
+    ) -> Option<Vec<Token<T>>> {
+        let ParseData::TokenList(list) = self.get_mut(path)? else {
+            return None;
+        };
+        if range.start > range.end || range.end > list.len() {
+            return None;
+        }
+        let old = list.splice(range, tokens).collect();
+        self.mark(path.to_vec());
+        Some(old)
+    }
+
+    /// Paths of the edited nodes, whose spans may no longer match the source.
+    pub fn dirty(&self) -> &[Vec<usize>] {
+        &self.dirty
+    }
+
+    pub fn is_dirty(&self, path: &[usize]) -> bool {
+        self.dirty.iter().any(|d| path.starts_with(d))
+    }
+
+    /// Takes `path` as re-spanned.
+    pub fn clean(&mut self, path: &[usize]) {
+        self.dirty.retain(|d| !d.starts_with(path));
+    }
+
+    fn mark(&mut self, path: Vec<usize>) {
+        if !self.is_dirty(&path) {
+            self.dirty.retain(|d| !d.starts_with(&path));
+            self.dirty.push(path);
+        }
+    }
+
+    // Moves dirty paths through siblings from `from` on under `parent` by `by` places.
+    fn shift(&mut self, parent: &[usize], from: usize, by: isize) {
+        let depth = parent.len();
+        for d in &mut self.dirty {
+            if d.len() > depth && d.starts_with(parent) && d[depth] >= from {
+                d[depth] = d[depth].saturating_add_signed(by);
+            }
+        }
+    }
+}
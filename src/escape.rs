@@ -0,0 +1,164 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::parse::ParseError;
+use crate::token::Span;
+
+/// Escape sequences understood inside string tokens. The default set is Rust's: `\n`, `\r`,
+/// `\t`, `\0`, `\\`, `\'`, `\"`, `\xNN` for ASCII and `\u{N..}` for any scalar value.
+#[derive(Clone, Debug)]
+pub struct Escapes {
+    simple: Vec<(char, char)>,
+    hex: bool,
+    unicode: bool,
+}
+
+impl Escapes {
+    /// Adds (or replaces) a one-character escape: `\escape` decodes to `value`.
+    pub fn with(mut self, escape: char, value: char) -> Self {
+        self.simple.retain(|(e, _)| *e != escape);
+        self.simple.push((escape, value));
+        self
+    }
+
+    pub fn with_hex(mut self, hex: bool) -> Self {
+        self.hex = hex;
+        self
+    }
+
+    pub fn with_unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Decodes the escapes in `text`, the contents of a string token without its quotes, whose
+    /// first character is at line `ln`, column `col` (counting characters). Every bad escape is
+    /// reported, spanning just that escape.
+    pub fn decode(&self, text: &str, ln: usize, col: usize) -> Result<String, Vec<ParseError>> {
+        let mut out = String::with_capacity(text.len());
+        let mut errors = vec![];
+        let mut chars = Cursor {
+            chars: text.chars().peekable(),
+            ln,
+            col,
+        };
+
+        while let Some((c, at)) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match self.escape(&mut chars) {
+                Ok(c) => out.push(c),
+                Err(message) => {
+                    let end = chars.col.saturating_sub(1).max(at.cs());
+                    let span = Span::new(
+                        at.ln(),
+                        at.cs(),
+                        if chars.ln == at.ln() { end } else { at.cs() },
+                    );
+                    errors.push(
+                        ParseError::from("escape sequence".to_string(), span).with_message(message),
+                    );
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(out)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn escape(&self, chars: &mut Cursor) -> Result<char, String> {
+        let Some((c, _)) = chars.next() else {
+            return Err("Unfinished escape sequence".to_string());
+        };
+        if let Some((_, value)) = self.simple.iter().find(|(e, _)| *e == c) {
+            return Ok(*value);
+        }
+        match c {
+            'x' if self.hex => {
+                let digits: String = (0..2)
+                    .filter_map(|_| chars.next_if(|c| c.is_ascii_hexdigit()))
+                    .collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(n) if digits.len() == 2 && n <= 0x7F => Ok(n as char),
+                    Ok(_) if digits.len() == 2 => Err(format!("\\x{digits} is not ASCII")),
+                    _ => Err("Expected two hex digits after \\x".to_string()),
+                }
+            }
+            'u' if self.unicode => {
+                if chars.next_if(|c| c == '{').is_none() {
+                    return Err("Expected { after \\u".to_string());
+                }
+                let mut digits = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_hexdigit() || c == '_') {
+                    if c != '_' {
+                        digits.push(c);
+                    }
+                }
+                if chars.next_if(|c| c == '}').is_none() {
+                    return Err("Unclosed \\u{ escape".to_string());
+                }
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err("Expected one to six hex digits in \\u{...}".to_string());
+                }
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("\\u{{{digits}}} is not a Unicode scalar value"))
+            }
+            c => Err(format!("Unknown escape \\{c}")),
+        }
+    }
+}
+
+impl Default for Escapes {
+    fn default() -> Self {
+        Self {
+            simple: vec![
+                ('n', '\n'),
+                ('r', '\r'),
+                ('t', '\t'),
+                ('0', '\0'),
+                ('\\', '\\'),
+                ('\'', '\''),
+                ('"', '"'),
+            ],
+            hex: true,
+            unicode: true,
+        }
+    }
+}
+
+// Characters with the position each one starts at.
+struct Cursor<'s> {
+    chars: core::iter::Peekable<core::str::Chars<'s>>,
+    ln: usize,
+    col: usize,
+}
+
+impl Cursor<'_> {
+    fn next(&mut self) -> Option<(char, Span)> {
+        let c = self.chars.next()?;
+        let at = Span::new(self.ln, self.col, self.col);
+        if c == '\n' {
+            self.ln += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some((c, at))
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(&c) if f(c) => self.next().map(|(c, _)| c),
+            _ => None,
+        }
+    }
+}
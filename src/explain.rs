@@ -0,0 +1,205 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::complete::{Expected, ExpectedSet};
+use crate::describe::ExpectedList;
+use crate::input::TokenInput;
+use crate::parse::{Context, Parser};
+use crate::trace::{Outcome, Step, TraceRecorder};
+
+/// Tells, step by step, why `parser` rejects `tokens`, for people learning to write grammars.
+/// The account follows the rules that got furthest into the input: each is indented under the
+/// rule that tried it, rules that matched before the failure are listed with what they matched,
+/// and the rules that gave up there say what they expected and what they found instead. Tokens
+/// are written by `token`. `None` if the input parses.
+pub fn explain<T: PartialEq>(
+    parser: &dyn Parser<T>,
+    tokens: &dyn TokenInput<T>,
+    token: impl Fn(&T) -> String,
+) -> Option<String> {
+    let recorder = TraceRecorder::new();
+    parser
+        .parse_all_in(&Context::new(tokens).with_recorder(&recorder))
+        .err()?;
+    let steps = recorder.into_steps();
+    // with no rule failing, the parse stopped short of trailing input
+    let far = furthest_failure(&steps)
+        .or_else(|| attempts(&steps).iter().map(|a| a.end).max())
+        .unwrap_or(0);
+
+    // again, now knowing where to collect what was expected
+    let recorder = TraceRecorder::new();
+    let expected = ExpectedSet::new(far);
+    let ctx = Context::new(tokens)
+        .with_recorder(&recorder)
+        .with_expected(&expected);
+    let _ = parser.parse_all_in(&ctx);
+
+    let mut by_step: BTreeMap<usize, Vec<Expected<T>>> = BTreeMap::new();
+    for (step, e) in expected.into_steps() {
+        by_step.entry(step).or_default().push(e);
+    }
+    let explainer = Explainer {
+        tokens,
+        far,
+        by_step,
+        token,
+        list: ExpectedList::new(),
+    };
+
+    let mut out = format!(
+        "Parse failed at {}, where {} was found\n",
+        explainer.at(far),
+        explainer.found(far)
+    );
+    for root in attempts(&recorder.into_steps()) {
+        explainer.write(&root, 0, &mut out);
+    }
+    Some(out)
+}
+
+// One rule attempt from the trace, with the attempts it made.
+struct Attempt {
+    step: usize,
+    rule: Arc<str>,
+    start: usize,
+    end: usize,
+    outcome: Outcome,
+    children: Vec<Attempt>,
+}
+
+impl Attempt {
+    fn reaches(&self, far: usize) -> bool {
+        self.start == far || self.children.iter().any(|c| c.reaches(far))
+    }
+}
+
+fn attempts(steps: &[Step]) -> Vec<Attempt> {
+    let mut stack: Vec<Attempt> = vec![];
+    let mut roots = vec![];
+    for (i, step) in steps.iter().enumerate() {
+        match step {
+            Step::Enter { rule, offset, .. } => stack.push(Attempt {
+                step: i,
+                rule: rule.clone(),
+                start: *offset,
+                end: *offset,
+                outcome: Outcome::None,
+                children: vec![],
+            }),
+            Step::Exit { end, outcome, .. } => {
+                let Some(mut attempt) = stack.pop() else {
+                    continue;
+                };
+                attempt.end = *end;
+                attempt.outcome = *outcome;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(attempt),
+                    None => roots.push(attempt),
+                }
+            }
+        }
+    }
+    roots
+}
+
+// The furthest offset a rule was tried at and failed.
+fn furthest_failure(steps: &[Step]) -> Option<usize> {
+    fn walk(attempt: &Attempt, far: &mut Option<usize>) {
+        if attempt.outcome == Outcome::Err {
+            *far = Some(far.map_or(attempt.start, |f| f.max(attempt.start)));
+        }
+        attempt.children.iter().for_each(|c| walk(c, far));
+    }
+    let mut far = None;
+    attempts(steps).iter().for_each(|a| walk(a, &mut far));
+    far
+}
+
+struct Explainer<'a, T, F> {
+    tokens: &'a dyn TokenInput<T>,
+    far: usize,
+    by_step: BTreeMap<usize, Vec<Expected<T>>>,
+    token: F,
+    list: ExpectedList,
+}
+
+impl<T: PartialEq, F: Fn(&T) -> String> Explainer<'_, T, F> {
+    fn write(&self, attempt: &Attempt, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        let rule = &attempt.rule;
+        let at = self.at(attempt.start);
+
+        if !attempt.reaches(self.far) {
+            let line = match attempt.outcome {
+                Outcome::Ok => format!("{rule} matched {}", self.range(attempt)),
+                Outcome::None => format!("{rule} at {at} was optional and skipped"),
+                Outcome::Err => format!("{rule} at {at} failed"),
+            };
+            out.push_str(&format!("{indent}{line}\n"));
+            return;
+        }
+
+        if attempt.children.is_empty() {
+            let expected = match self.by_step.get(&attempt.step) {
+                Some(items) => self.list.format(items, &self.token),
+                None => self
+                    .list
+                    .format(&[Expected::Rule(rule.clone())], &self.token),
+            };
+            let verdict = match attempt.outcome {
+                Outcome::Ok => "matched",
+                Outcome::None => "was optional and skipped",
+                Outcome::Err => "failed",
+            };
+            out.push_str(&format!(
+                "{indent}{rule} at {at} {verdict}: expected {expected}, found {}\n",
+                self.found(attempt.start)
+            ));
+            return;
+        }
+
+        let line = match attempt.outcome {
+            Outcome::Ok => format!(
+                "{rule} matched {}, then stopped at {}",
+                self.range(attempt),
+                self.at(attempt.end)
+            ),
+            Outcome::None => format!("{rule} at {at} was optional and skipped"),
+            Outcome::Err => format!("{rule} at {at} failed, trying:"),
+        };
+        out.push_str(&format!("{indent}{line}\n"));
+        for child in &attempt.children {
+            self.write(child, depth + 1, out);
+        }
+    }
+
+    fn at(&self, offset: usize) -> String {
+        match self.tokens.span_at(offset) {
+            Some(span) => format!("{}:{}", span.ln(), span.cs()),
+            None => "the end of input".into(),
+        }
+    }
+
+    fn found(&self, offset: usize) -> String {
+        match self.tokens.get(offset) {
+            Some(t) => format!("`{}`", (self.token)(t.ty())),
+            None => "the end of input".into(),
+        }
+    }
+
+    fn range(&self, attempt: &Attempt) -> String {
+        if attempt.end == attempt.start {
+            return format!("nothing at {}", self.at(attempt.start));
+        }
+        let tokens: Vec<String> = (attempt.start..attempt.end)
+            .filter_map(|i| self.tokens.get(i))
+            .map(|t| (self.token)(t.ty()))
+            .collect();
+        format!("`{}` at {}", tokens.join(" "), self.at(attempt.start))
+    }
+}
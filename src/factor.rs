@@ -0,0 +1,75 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::parse::{Choice, Empty, Parser, Sequence, Shared};
+
+/// Builds the choice between `alternatives`, each a sequence of items, with common prefixes
+/// hoisted: neighbouring alternatives that start with the same items (the same [`Shared`]
+/// handles) become one sequence of those items and a helper choice named `{pty}_tail` between
+/// their remainders. The prefix is then parsed once instead of once per alternative.
+///
+/// Only neighbours are merged, so alternatives are still tried in the order given and the
+/// factored parser accepts the same input. Its trees differ: tails are nested one level deeper,
+/// single items aren't wrapped in a sequence, and an alternative the prefix used up leaves an
+/// empty node.
+pub fn left_factor<T>(pty: &str, alternatives: Vec<Vec<Shared<T>>>) -> Box<dyn Parser<T>>
+where
+    T: Clone + 'static,
+{
+    let mut out: Vec<Box<dyn Parser<T>>> = vec![];
+    let mut alternatives = alternatives.into_iter().peekable();
+    while let Some(first) = alternatives.next() {
+        let Some(head) = first.first().cloned() else {
+            out.push(sequence(pty, first));
+            continue;
+        };
+        let mut group = vec![first];
+        while let Some(next) = alternatives.next_if(|a| a.first().is_some_and(|h| h.same(&head))) {
+            group.push(next);
+        }
+
+        if group.len() == 1 {
+            out.push(sequence(pty, group.remove(0)));
+            continue;
+        }
+        // the items every alternative in the group starts with
+        let shared = (1..)
+            .take_while(|i| {
+                let item = group[0].get(*i);
+                group.iter().all(|a| match (a.get(*i), item) {
+                    (Some(a), Some(b)) => a.same(b),
+                    _ => false,
+                })
+            })
+            .count()
+            + 1;
+        let mut items: Vec<Box<dyn Parser<T>>> =
+            group[0][..shared].iter().cloned().map(boxed).collect();
+        let tails = group.into_iter().map(|a| a[shared..].to_vec()).collect();
+        items.push(left_factor(&format!("{pty}_tail"), tails));
+        out.push(Box::new(Sequence::from(pty, false, items)));
+    }
+
+    match out.len() {
+        1 => out.remove(0),
+        _ => Box::new(Choice::from(pty, false, out)),
+    }
+}
+
+fn sequence<T: Clone + 'static>(pty: &str, mut items: Vec<Shared<T>>) -> Box<dyn Parser<T>> {
+    match items.len() {
+        0 => Box::new(Empty::from("empty")),
+        1 => boxed(items.remove(0)),
+        _ => Box::new(Sequence::from(
+            pty,
+            false,
+            items.into_iter().map(boxed).collect(),
+        )),
+    }
+}
+
+fn boxed<T: 'static>(item: Shared<T>) -> Box<dyn Parser<T>> {
+    Box::new(item)
+}
@@ -0,0 +1,176 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::token::{Provenance, Span, Token};
+
+/// One stage of a [`Pipeline`]. Sees the stream a token at a time and writes whatever should
+/// take its place to `out`: nothing to delete it, the token itself to keep it, or any number of
+/// new ones.
+pub trait TokenFilter<T> {
+    fn filter(&mut self, token: Token<T>, out: &mut Emitter<T>);
+
+    /// Called after the last token, for filters that hold tokens back.
+    fn finish(&mut self, _out: &mut Emitter<T>) {}
+}
+
+impl<T, F> TokenFilter<T> for F
+where
+    F: FnMut(Token<T>, &mut Emitter<T>),
+{
+    fn filter(&mut self, token: Token<T>, out: &mut Emitter<T>) {
+        self(token, out)
+    }
+}
+
+/// Where a filter writes its output. Takes care of spans: merged tokens cover everything merged
+/// into them and injected tokens are placed at the token being filtered, with provenance saying
+/// why they exist.
+pub struct Emitter<T> {
+    tokens: Vec<Token<T>>,
+    anchor: Span,
+    origin: Option<Arc<Provenance>>,
+}
+
+impl<T> Emitter<T> {
+    pub fn emit(&mut self, token: Token<T>) {
+        self.tokens.push(token);
+    }
+
+    /// Emits `token` with its type replaced by `ty`, as for contextual keywords.
+    pub fn rewrite(&mut self, token: Token<T>, ty: T) {
+        self.tokens.push(token.with_ty(ty));
+    }
+
+    /// Folds `token` into the last token emitted, which becomes a `ty` covering both. Spans
+    /// can't cross lines, so merging across a line break keeps the first token's span. With
+    /// nothing emitted yet, `token` is emitted as `ty`.
+    pub fn merge(&mut self, token: Token<T>, ty: T) {
+        match self.tokens.last_mut() {
+            Some(last) => last.absorb(token, ty),
+            None => self.rewrite(token, ty),
+        }
+    }
+
+    /// Emits a token that isn't in the source, positioned at the token being filtered.
+    pub fn inject(&mut self, ty: T, reason: impl Into<Cow<'static, str>>) {
+        let mut provenance = Provenance::new(self.anchor.clone(), reason);
+        if let Some(origin) = &self.origin {
+            provenance = provenance.with_parent(origin.clone());
+        }
+        let token = Token::new(ty, self.anchor.clone()).with_provenance(Arc::new(provenance));
+        self.tokens.push(token);
+    }
+
+    pub fn last(&self) -> Option<&Token<T>> {
+        self.tokens.last()
+    }
+
+    pub fn pop(&mut self) -> Option<Token<T>> {
+        self.tokens.pop()
+    }
+
+    fn anchor_at(&mut self, token: &Token<T>) {
+        self.anchor = token.span().clone();
+        self.origin = token.provenance().cloned();
+    }
+}
+
+/// Filters run one after another over the whole stream before it is parsed.
+pub struct Pipeline<T> {
+    stages: Vec<Box<dyn TokenFilter<T>>>,
+}
+
+impl<T> Pipeline<T> {
+    pub fn then(mut self, stage: impl TokenFilter<T> + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn run(&mut self, mut tokens: Vec<Token<T>>) -> Vec<Token<T>> {
+        for stage in &mut self.stages {
+            let mut out = Emitter {
+                tokens: Vec::with_capacity(tokens.len()),
+                anchor: Span::default(),
+                origin: None,
+            };
+            for token in tokens {
+                out.anchor_at(&token);
+                stage.filter(token, &mut out);
+            }
+            stage.finish(&mut out);
+            tokens = out.tokens;
+        }
+        tokens
+    }
+
+    pub fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+}
+
+impl<T> Default for Pipeline<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A stage that deletes every token `drop` matches, trivia included.
+pub fn drop_where<T>(drop: fn(&T) -> bool) -> impl TokenFilter<T> {
+    move |token: Token<T>, out: &mut Emitter<T>| {
+        if !drop(token.ty()) {
+            out.emit(token);
+        }
+    }
+}
+
+/// A stage that retypes every token `rewrite` returns a new type for.
+pub fn rewrite_where<T>(rewrite: fn(&Token<T>) -> Option<T>) -> impl TokenFilter<T> {
+    move |token: Token<T>, out: &mut Emitter<T>| match rewrite(&token) {
+        Some(ty) => out.rewrite(token, ty),
+        None => out.emit(token),
+    }
+}
+
+/// A stage for newline-terminated languages: keeps `newline` tokens only where they can end a
+/// statement. Newlines inside any of the bracket `pairs`, at the start of the input and right
+/// after another kept newline are dropped, so a grammar can treat every remaining one as a
+/// terminator.
+pub struct SignificantNewlines<T> {
+    newline: T,
+    pairs: Vec<(T, T)>,
+    depth: usize,
+    at_line_start: bool,
+}
+
+impl<T> SignificantNewlines<T> {
+    pub fn new(newline: T, pairs: Vec<(T, T)>) -> Self {
+        Self {
+            newline,
+            pairs,
+            depth: 0,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<T: PartialEq> TokenFilter<T> for SignificantNewlines<T> {
+    fn filter(&mut self, token: Token<T>, out: &mut Emitter<T>) {
+        let ty = token.ty();
+        if *ty == self.newline {
+            if self.depth == 0 && !self.at_line_start {
+                self.at_line_start = true;
+                out.emit(token);
+            }
+            return;
+        }
+        if self.pairs.iter().any(|(open, _)| open == ty) {
+            self.depth += 1;
+        } else if self.pairs.iter().any(|(_, close)| close == ty) {
+            self.depth = self.depth.saturating_sub(1);
+        }
+        self.at_line_start = false;
+        out.emit(token);
+    }
+}
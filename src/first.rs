@@ -0,0 +1,123 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::complete::Expected;
+
+/// Two alternatives of a choice one token of lookahead can't tell apart, from
+/// [`Choice::conflicts`](crate::parse::Choice::conflicts).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict<T> {
+    pub first: usize,
+    pub second: usize,
+    /// The tokens and named classes both alternatives can start with.
+    pub on: Vec<Expected<T>>,
+    /// Whether both can match nothing.
+    pub nullable: bool,
+}
+
+/// The tokens a parser can start with, as reported by
+/// [`Parser::first_set`](crate::parse::Parser::first_set).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenSet<T> {
+    items: Vec<Expected<T>>,
+    nullable: bool,
+    open: bool,
+}
+
+impl<T> TokenSet<T> {
+    pub fn items(&self) -> &[Expected<T>] {
+        &self.items
+    }
+
+    /// Whether the parser can succeed without consuming anything.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Whether the parser may also start with tokens not listed, as custom parsers and error
+    /// recovery can.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether `ty` is listed explicitly. Named classes are opaque, so they never match.
+    pub fn contains(&self, ty: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.items
+            .iter()
+            .any(|e| matches!(e, Expected::Token(t) if t == ty))
+    }
+
+    pub fn contains_rule(&self, rule: &str) -> bool {
+        self.items
+            .iter()
+            .any(|e| matches!(e, Expected::Rule(r) if &**r == rule))
+    }
+
+    /// Adds `other`'s tokens and openness. Nullability is left to the caller since it depends
+    /// on how the two are combined.
+    pub fn extend(&mut self, other: TokenSet<T>) {
+        self.items.extend(other.items);
+        self.open |= other.open;
+    }
+
+    pub fn dedup(mut self) -> Self
+    where
+        T: PartialEq,
+    {
+        let mut items: Vec<Expected<T>> = Vec::with_capacity(self.items.len());
+        for e in self.items {
+            if !items.contains(&e) {
+                items.push(e);
+            }
+        }
+        self.items = items;
+        self
+    }
+
+    pub fn nullable(mut self, nullable: bool) -> Self {
+        self.nullable = nullable;
+        self
+    }
+
+    pub fn opened(mut self) -> Self {
+        self.open = true;
+        self
+    }
+
+    pub fn token(ty: T) -> Self {
+        Self {
+            items: alloc::vec![Expected::Token(ty)],
+            nullable: false,
+            open: false,
+        }
+    }
+
+    pub fn rule(pty: Arc<str>) -> Self {
+        Self {
+            items: alloc::vec![Expected::Rule(pty)],
+            nullable: false,
+            open: false,
+        }
+    }
+
+    /// Matches nothing but the empty input.
+    pub const fn empty() -> Self {
+        Self {
+            items: Vec::new(),
+            nullable: true,
+            open: false,
+        }
+    }
+
+    /// Nothing is known about the parser.
+    pub const fn unknown() -> Self {
+        Self {
+            items: Vec::new(),
+            nullable: true,
+            open: true,
+        }
+    }
+}
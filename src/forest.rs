@@ -0,0 +1,174 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+
+use crate::diff::same;
+use crate::input::TokenInput;
+use crate::parse::{Context, ParseData, ParseError, Parser};
+
+// Whole parses tried before giving up on finding more trees; each ambiguous Choice multiplies
+// the number of runs.
+const MAX_RUNS: usize = 4096;
+const UNTAKEN: usize = usize::MAX;
+
+/// Every tree a parser can build for one input, from [`parse_forest`].
+pub struct Forest<T> {
+    trees: Vec<ParseData<T>>,
+    truncated: Option<Truncation>,
+}
+
+/// Why [`parse_forest`] stopped with ways to parse the input still untried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    /// It found the number of trees it was asked for.
+    Limit,
+    /// It gave up after 4096 whole parses, so trees may be missing however high the limit.
+    Runs,
+}
+
+impl<T> Forest<T> {
+    /// The trees in the order found, the one an ordinary parse gives first.
+    pub fn trees(&self) -> &[ParseData<T>] {
+        &self.trees
+    }
+
+    pub fn into_trees(self) -> Vec<ParseData<T>> {
+        self.trees
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        self.trees.len() > 1
+    }
+
+    /// Whether every tree was found, rather than the search stopping short.
+    pub fn is_complete(&self) -> bool {
+        self.truncated.is_none()
+    }
+
+    /// Why the search stopped short, if it did.
+    pub fn truncated(&self) -> Option<Truncation> {
+        self.truncated
+    }
+
+    /// Drops the trees `keep` rejects, such as ones breaking a rule the grammar can't express.
+    pub fn retain(&mut self, keep: impl FnMut(&ParseData<T>) -> bool) {
+        self.trees.retain(keep);
+    }
+
+    /// Picks the tree `prefer` ranks highest, the earliest found on a tie.
+    pub fn resolve(
+        self,
+        mut prefer: impl FnMut(&ParseData<T>, &ParseData<T>) -> Ordering,
+    ) -> Option<ParseData<T>> {
+        self.trees
+            .into_iter()
+            .reduce(|best, tree| match prefer(&tree, &best) {
+                Ordering::Greater => tree,
+                _ => best,
+            })
+    }
+}
+
+/// Parses all of `tokens` every way `parser` can, for grammars that are ambiguous on purpose:
+/// each Choice explores all its alternatives rather than stopping at the first that works.
+///
+/// This enumerates trees rather than building a shared packed forest: each tree comes from a
+/// whole parse of its own and shares nothing with the others, and repeats are dropped, so the
+/// work grows with the number of ways the input parses. The search stops after `limit` trees,
+/// or after 4096 parses however many trees that found; [`Forest::truncated`] tells which, and
+/// a `limit` of 0 gives an empty forest without parsing. Errors are those of the ordinary
+/// parse, when no tree at all is found.
+pub fn parse_forest<T: Clone + PartialEq>(
+    parser: &dyn Parser<T>,
+    tokens: &dyn TokenInput<T>,
+    limit: usize,
+) -> Result<Forest<T>, Vec<ParseError>> {
+    let mut trees: Vec<ParseData<T>> = vec![];
+    let mut errors = None;
+    let mut pending = vec![vec![]];
+    let mut runs = 0;
+    let mut truncated = None;
+
+    while let Some(script) = pending.pop() {
+        if trees.len() >= limit {
+            truncated = Some(Truncation::Limit);
+            break;
+        }
+        if runs >= MAX_RUNS {
+            truncated = Some(Truncation::Runs);
+            break;
+        }
+        runs += 1;
+        let forks = Forks::new(script);
+        match parser.parse_all_in(&Context::new(tokens).with_forks(&forks)) {
+            Ok(tree) => {
+                if !trees.iter().any(|t| same(t, &tree)) {
+                    trees.push(tree);
+                }
+            }
+            Err(e) => {
+                errors.get_or_insert(e);
+            }
+        }
+
+        // every alternative after the one taken at each Choice reached past the script, later
+        // Choices first so the stack hands back nearby variations first
+        let Forks { script, taken } = forks;
+        let taken = taken.into_inner();
+        for j in (script.len()..taken.len()).rev() {
+            let (alternative, of) = taken[j];
+            if alternative == UNTAKEN {
+                continue;
+            }
+            for next in (alternative + 1..of).rev() {
+                let mut fork: Vec<usize> = taken[..j].iter().map(|(a, _)| *a).collect();
+                fork.push(next);
+                pending.push(fork);
+            }
+        }
+    }
+
+    if trees.is_empty() && limit > 0 {
+        return Err(errors.unwrap_or_default());
+    }
+    Ok(Forest { trees, truncated })
+}
+
+// The alternative each Choice takes, counting Choices in the order a parse reaches them.
+// Choices reached past the script try their alternatives as usual.
+pub(crate) struct Forks {
+    script: Vec<usize>,
+    // the alternative taken at each Choice reached, or UNTAKEN, and how many it had
+    taken: RefCell<Vec<(usize, usize)>>,
+}
+
+impl Forks {
+    const fn new(script: Vec<usize>) -> Self {
+        Self {
+            script,
+            taken: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Registers a Choice of `of` alternatives, giving back its number and the alternative it
+    // has to take, if scripted.
+    pub(crate) fn visit(&self, of: usize) -> (usize, Option<usize>) {
+        let mut taken = self.taken.borrow_mut();
+        let visit = taken.len();
+        taken.push((UNTAKEN, of));
+        (visit, self.script.get(visit).copied())
+    }
+
+    pub(crate) fn took(&self, visit: usize, alternative: usize) {
+        self.taken.borrow_mut()[visit].0 = alternative;
+    }
+}
@@ -0,0 +1,144 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::token::Token;
+
+/// A pretty-printing document in the style of Wadler's "prettier printer". Groups are laid out
+/// flat when they fit in the remaining width and broken at their lines otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Doc {
+    Text(String),
+    /// A space when flat, a newline when broken.
+    Line,
+    /// Nothing when flat, a newline when broken.
+    SoftLine,
+    /// Always a newline; forces every enclosing group to break.
+    HardLine,
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn indent(doc: Doc) -> Self {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Self {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    /// `docs` separated by `sep`.
+    pub fn join(docs: impl IntoIterator<Item = Doc>, sep: Doc) -> Self {
+        let mut out = vec![];
+        for doc in docs {
+            if !out.is_empty() {
+                out.push(sep.clone());
+            }
+            out.push(doc);
+        }
+        Doc::Concat(out)
+    }
+
+    /// A token's text surrounded by the comments among its trivia. `text` returns `None` for
+    /// trivia that should be dropped, such as whitespace. Comments are assumed to run to the end
+    /// of their line, so each is followed by a hard line.
+    pub fn token<T>(token: &Token<T>, text: impl Fn(&Token<T>) -> Option<String>) -> Self {
+        let mut out = vec![];
+        for comment in token.leading().iter().filter_map(&text) {
+            out.extend([Doc::Text(comment), Doc::HardLine]);
+        }
+        out.extend(text(token).map(Doc::Text));
+        for comment in token.trailing().iter().filter_map(&text) {
+            out.extend([Doc::text(" "), Doc::Text(comment), Doc::HardLine]);
+        }
+        Doc::Concat(out)
+    }
+
+    fn has_hard_line(&self) -> bool {
+        match self {
+            Doc::HardLine => true,
+            Doc::Indent(d) | Doc::Group(d) => d.has_hard_line(),
+            Doc::Concat(l) => l.iter().any(Doc::has_hard_line),
+            _ => false,
+        }
+    }
+
+    pub fn render(&self, width: usize, indent: usize) -> String {
+        let mut out = String::new();
+        let mut col = 0;
+        let mut stack = vec![(0, false, self)];
+
+        while let Some((level, flat, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(s) => {
+                    out.push_str(s);
+                    col += s.chars().count();
+                }
+                Doc::Line | Doc::SoftLine if flat => {
+                    if matches!(doc, Doc::Line) {
+                        out.push(' ');
+                        col += 1;
+                    }
+                }
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    // trailing spaces left by a flat Line before a break are dropped
+                    while out.ends_with(' ') {
+                        out.pop();
+                    }
+                    out.push('\n');
+                    out.extend(core::iter::repeat_n(' ', level));
+                    col = level;
+                }
+                Doc::Indent(d) => stack.push((level + indent, flat, d)),
+                Doc::Group(d) => {
+                    let flat =
+                        flat || !d.has_hard_line() && fits(d, width.saturating_sub(col), &stack);
+                    stack.push((level, flat, d));
+                }
+                Doc::Concat(l) => stack.extend(l.iter().rev().map(|d| (level, flat, d))),
+            }
+        }
+        out
+    }
+}
+
+// Measures `doc` laid out flat plus whatever follows it up to the next possible line break.
+fn fits(doc: &Doc, mut remaining: usize, rest: &[(usize, bool, &Doc)]) -> bool {
+    let mut pending = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        let (flat, doc) = match pending.pop() {
+            Some(d) => d,
+            None => match rest.next() {
+                Some((_, flat, d)) => (*flat, *d),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => match remaining.checked_sub(s.chars().count()) {
+                Some(r) => remaining = r,
+                None => return false,
+            },
+            Doc::Line if flat => match remaining.checked_sub(1) {
+                Some(r) => remaining = r,
+                None => return false,
+            },
+            Doc::SoftLine if flat => (),
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Indent(d) | Doc::Group(d) => pending.push((flat, d)),
+            Doc::Concat(l) => pending.extend(l.iter().rev().map(|d| (flat, d))),
+        }
+    }
+}
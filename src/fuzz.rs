@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::token::{Span, Token};
+
+const MAX_WIDTH: usize = 16;
+
+impl<'a> Arbitrary<'a> for Span {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ln = u.int_in_range(1..=usize::MAX / 2)?;
+        let cs = u.int_in_range(1..=usize::MAX / 2)?;
+        let width = u.int_in_range(0..=MAX_WIDTH)?;
+        Ok(Span::new(ln, cs, cs + width))
+    }
+}
+
+impl<'a, T> Arbitrary<'a> for Token<T>
+where
+    T: Arbitrary<'a>,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Token::new(T::arbitrary(u)?, Span::arbitrary(u)?))
+    }
+}
+
+/// Builds a token stream whose spans run left to right over a few lines, the way a lexer would
+/// produce them, with token types drawn from `u`.
+pub fn token_stream<'a, T>(u: &mut Unstructured<'a>, max_len: usize) -> Result<Vec<Token<T>>>
+where
+    T: Arbitrary<'a>,
+{
+    let len = u.int_in_range(0..=max_len)?;
+    let mut tokens = Vec::with_capacity(len);
+    let (mut ln, mut col) = (1, 1);
+
+    for _ in 0..len {
+        if u.ratio(1, 8)? {
+            ln += 1;
+            col = 1;
+        }
+        let width = u.int_in_range(0..=MAX_WIDTH)?;
+        tokens.push(Token::new(
+            T::arbitrary(u)?,
+            Span::new(ln, col, col + width),
+        ));
+        col += width + 2;
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+
+use crate::parse::Parser;
+use crate::token::{Span, Token};
+
+const DEFAULT_MAX_DEPTH: usize = 8;
+const DEFAULT_MAX_REPEAT: usize = 4;
+
+/// Drives [`Parser::generate`]. Holds a small xorshift generator so the output is reproducible
+/// from a seed without pulling in an RNG crate.
+pub struct Generator {
+    state: u64,
+    depth: usize,
+    max_depth: usize,
+    max_repeat: usize,
+}
+
+impl Generator {
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A number in `0..n`, or 0 when `n` is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Past the depth limit combinators stop making optional choices and repeat as little as
+    /// they can, so generation bottoms out.
+    pub fn exhausted(&self) -> bool {
+        self.depth >= self.max_depth
+    }
+
+    pub fn repeat_count(&mut self, min: usize) -> usize {
+        if self.exhausted() {
+            return min;
+        }
+        min + self.below(self.max_repeat.max(min) - min + 1)
+    }
+
+    /// Runs `f` one level deeper, refusing outright once far past the limit so recursive
+    /// grammars cannot overflow the stack.
+    pub fn nested(&mut self, f: impl FnOnce(&mut Self) -> bool) -> bool {
+        if self.depth > self.max_depth * 2 {
+            return false;
+        }
+        self.depth += 1;
+        let ok = f(self);
+        self.depth -= 1;
+        ok
+    }
+
+    /// Generates one input for `parser` and lays it out as tokens on a single line.
+    pub fn tokens<T>(&mut self, parser: &dyn Parser<T>) -> Option<Vec<Token<T>>> {
+        let mut out = Vec::new();
+        if !parser.generate(self, &mut out) {
+            return None;
+        }
+        Some(
+            out.into_iter()
+                .enumerate()
+                .map(|(i, ty)| Token::new(ty, Span::new(1, i * 2 + 1, i * 2 + 1)))
+                .collect(),
+        )
+    }
+
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub const fn with_max_repeat(mut self, max_repeat: usize) -> Self {
+        self.max_repeat = max_repeat;
+        self
+    }
+
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            // xorshift never leaves zero
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_repeat: DEFAULT_MAX_REPEAT,
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::parse::{Choice, OfType, Parser, Predicate, Repeatable, Sequence};
+use alloc::boxed::Box;
+use alloc::string::String;
+
+use crate::parse::{OfType, Parser, Predicate, Repeatable};
+use crate::{choice, seq};
 
 #[derive(Clone, PartialEq)]
 pub enum TokenType {
@@ -16,36 +20,21 @@ pub enum TokenType {
     Str(String),
 }
 
+#[allow(dead_code)]
 fn create_grammar_token_parser() -> impl Parser<TokenType> {
-    let item = Sequence::from(
-        "item",
-        false,
-        vec![
-            Box::new(Predicate::from("id", false, |t| matches!(t, TokenType::Id(_)))),
-            Box::new(OfType::from("=", false, TokenType::Equals)),
-            Box::new(Predicate::from(
-                "value",
-                false,
-                |t| matches!(t, TokenType::Str(_)),
-            )),
-        ]
-    );
+    let item = seq!["item";
+        Predicate::from("id", false, |t| matches!(t, TokenType::Id(_))),
+        OfType::from("=", false, TokenType::Equals),
+        Predicate::from("value", false, |t| matches!(t, TokenType::Str(_))),
+    ];
 
-    Choice::from(
-        "document",
-        false,
-        vec![
-            Box::new(OfType::from("_", false, TokenType::Eoi)),
-            Box::new(Sequence::from(
-                "items",
-                false,
-                vec![
-                    Box::new(Repeatable::from("fields", true, Box::new(item))),
-                    Box::new(OfType::from("_", false, TokenType::Eoi)),
-                ],
-            )),
-        ]
-    )
+    choice!["document";
+        OfType::from("_", false, TokenType::Eoi),
+        seq!["items";
+            Repeatable::from("fields", true, Box::new(item)),
+            OfType::from("_", false, TokenType::Eoi),
+        ],
+    ]
 
     // TODO finish
 }
@@ -0,0 +1,141 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::input::TokenInput;
+use crate::parse::{Context, ParseData, ParseError, Parser};
+use crate::token::{Span, Token};
+use crate::trace::{Outcome, Step, TraceRecorder};
+
+/// Classifies every token in `data`, trivia included, and returns the classified spans in
+/// source order. Tokens `classify` returns `None` for are left out.
+pub fn highlight<T, C>(
+    data: &ParseData<T>,
+    classify: impl Fn(&Token<T>) -> Option<C>,
+) -> Vec<(Span, C)> {
+    let mut out = vec![];
+    collect(data, &classify, &mut out);
+    out.sort_by_key(|(span, _)| (span.ln(), span.cs()));
+    out
+}
+
+fn collect<T, C>(
+    data: &ParseData<T>,
+    classify: &impl Fn(&Token<T>) -> Option<C>,
+    out: &mut Vec<(Span, C)>,
+) {
+    let mut token = |t: &Token<T>| {
+        for t in t
+            .leading()
+            .iter()
+            .chain(core::iter::once(t))
+            .chain(t.trailing())
+        {
+            if let Some(class) = classify(t) {
+                out.push((t.span().clone(), class));
+            }
+        }
+    };
+
+    match data {
+        ParseData::Token(t) => token(t),
+        ParseData::TokenList(l) | ParseData::Error { skipped: l, .. } => l.iter().for_each(token),
+        ParseData::Nested(l) => l.iter().for_each(|d| collect(d, classify, out)),
+    }
+}
+
+/// Classifies by rule as well as by token: parses all of `tokens` with `parser`, and each token
+/// takes the class `rule` gives the innermost rule that matched it, so an identifier can read
+/// as a function name inside a `call` rule. Tokens no rule claims, and all trivia, are left to
+/// `token`. Spans come back in source order, as from [`highlight`].
+pub fn highlight_rules<T, C: Clone>(
+    parser: &dyn Parser<T>,
+    tokens: &dyn TokenInput<T>,
+    rule: impl Fn(&str) -> Option<C>,
+    token: impl Fn(&Token<T>) -> Option<C>,
+) -> Result<Vec<(Span, C)>, Vec<ParseError>> {
+    let recorder = TraceRecorder::new();
+    parser.parse_all_in(&Context::new(tokens).with_recorder(&recorder))?;
+
+    let mut claimed = BTreeMap::new();
+    for (start, end, class) in claims(&recorder.into_steps(), &rule) {
+        for offset in start..end {
+            claimed.insert(offset, class.clone());
+        }
+    }
+
+    let mut out = vec![];
+    for offset in 0..tokens.len() {
+        let Some(t) = tokens.get(offset) else {
+            break;
+        };
+        for trivia in t.leading().iter().chain(t.trailing()) {
+            if let Some(class) = token(trivia) {
+                out.push((trivia.span().clone(), class));
+            }
+        }
+        if let Some(class) = claimed.remove(&offset).or_else(|| token(t)) {
+            out.push((t.span().clone(), class));
+        }
+    }
+    out.sort_by_key(|(span, _)| (span.ln(), span.cs()));
+    Ok(out)
+}
+
+// The token ranges claimed by matched rules `rule` has a class for, outer rules before the
+// rules inside them. Attempts that failed, were skipped, or reach past the rule that tried
+// them, as when a repetition gives tokens back, are not part of the tree and claim nothing.
+fn claims<C>(steps: &[Step], rule: &impl Fn(&str) -> Option<C>) -> Vec<(usize, usize, C)> {
+    let mut stack: Vec<Vec<(usize, usize, C)>> = vec![vec![]];
+    for step in steps {
+        match step {
+            Step::Enter { .. } => stack.push(vec![]),
+            Step::Exit {
+                rule: name,
+                start,
+                end,
+                outcome,
+                ..
+            } => {
+                let inner = stack.pop().unwrap_or_default();
+                let Some(parent) = stack.last_mut() else {
+                    continue;
+                };
+                if *outcome != Outcome::Ok {
+                    continue;
+                }
+                parent.extend(rule(name).map(|class| (*start, *end, class)));
+                parent.extend(inner.into_iter().filter(|(s, e, _)| start <= s && e <= end));
+            }
+        }
+    }
+    stack.into_iter().flatten().collect()
+}
+
+/// Encodes classified spans, in source order, as LSP `SemanticTokens.data`: five integers per
+/// token holding the line and start delta, the length, the token type and the modifier bits.
+/// Lines and columns are shifted from roder's 1-based positions to LSP's 0-based ones.
+pub fn encode_semantic_tokens(tokens: &[(Span, u32, u32)]) -> Vec<u32> {
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut prev_ln, mut prev_cs) = (1, 1);
+
+    for (span, ty, modifiers) in tokens {
+        let delta_ln = span.ln() - prev_ln;
+        let delta_cs = if delta_ln == 0 {
+            span.cs() - prev_cs
+        } else {
+            span.cs() - 1
+        };
+        let len = span.ce() - span.cs() + 1;
+
+        data.extend([
+            delta_ln as u32,
+            delta_cs as u32,
+            len as u32,
+            *ty,
+            *modifiers,
+        ]);
+        (prev_ln, prev_cs) = (span.ln(), span.cs());
+    }
+    data
+}
@@ -0,0 +1,137 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Write};
+
+use crate::parse::ParseData;
+use crate::token::{Span, Token};
+
+const STYLE: &str = "body{font-family:sans-serif;display:flex;gap:2em}\
+pre{line-height:1.4}.tok.hl{background:#fd6}details{margin-left:1em}\
+summary,.leaf{cursor:default;font-family:monospace}.err{color:#c00}";
+
+const SCRIPT: &str = "for(const n of document.querySelectorAll('[data-from]')){\
+const t=[];for(let i=+n.dataset.from;i<=+n.dataset.to;i++)t.push(document.getElementById('t'+i));\
+n.addEventListener('mouseenter',e=>{e.stopPropagation();t.forEach(x=>x&&x.classList.add('hl'))});\
+n.addEventListener('mouseleave',()=>t.forEach(x=>x&&x.classList.remove('hl')));}";
+
+/// Renders a standalone HTML page with `source` on the left and a collapsible view of `data` on
+/// the right. Hovering a node highlights the source of the tokens under it. Columns in spans
+/// are taken as 1-based character positions within their line.
+pub fn render_html<T: Debug>(data: &ParseData<T>, source: &str, title: &str) -> String {
+    let mut spans = vec![];
+    let mut tree = String::new();
+    write_node(&mut tree, data, &mut spans);
+
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{STYLE}</style></head>\
+<body><pre>{}</pre><div>{tree}</div><script>{SCRIPT}</script></body></html>\n",
+        escape(title),
+        render_source(source, &spans),
+    )
+}
+
+fn write_token<T: Debug>(out: &mut String, token: &Token<T>, spans: &mut Vec<Span>) {
+    let id = spans.len();
+    spans.push(token.span().clone());
+    let _ = write!(
+        out,
+        "<div class=\"leaf\" data-from=\"{id}\" data-to=\"{id}\">{} <small>{}</small></div>",
+        escape(&format!("{:?}", token.ty())),
+        token.span()
+    );
+}
+
+fn write_node<T: Debug>(out: &mut String, data: &ParseData<T>, spans: &mut Vec<Span>) {
+    let from = spans.len();
+    let mut children = String::new();
+
+    let label = match data {
+        ParseData::Token(t) => return write_token(out, t, spans),
+        ParseData::TokenList(l) => {
+            l.iter().for_each(|t| write_token(&mut children, t, spans));
+            String::from("tokens")
+        }
+        ParseData::Error {
+            expected,
+            skipped,
+            message,
+            ..
+        } => {
+            skipped
+                .iter()
+                .for_each(|t| write_token(&mut children, t, spans));
+            match message {
+                Some(message) => format!("<span class=\"err\">error: {}</span>", escape(message)),
+                None => format!(
+                    "<span class=\"err\">error: expected {}</span>",
+                    escape(expected)
+                ),
+            }
+        }
+        ParseData::Nested(l) => {
+            l.iter().for_each(|d| write_node(&mut children, d, spans));
+            format!("({})", l.len())
+        }
+    };
+
+    let to = spans.len().max(from + 1) - 1;
+    let _ = write!(
+        out,
+        "<details open data-from=\"{from}\" data-to=\"{to}\"><summary>{label}</summary>{children}</details>"
+    );
+}
+
+fn render_source(source: &str, spans: &[Span]) -> String {
+    let mut out = String::new();
+    let mut by_line: Vec<(usize, usize, usize, usize)> = spans
+        .iter()
+        .enumerate()
+        .map(|(id, s)| (s.ln(), s.cs(), s.ce(), id))
+        .collect();
+    by_line.sort();
+
+    let mut marks = by_line.iter().peekable();
+    for (i, line) in source.lines().enumerate() {
+        let ln = i + 1;
+        let chars: Vec<char> = line.chars().collect();
+        let mut col = 1;
+
+        while let Some(&&(_, cs, ce, id)) = marks.peek().filter(|m| m.0 == ln) {
+            marks.next();
+            if cs < col || cs > chars.len() {
+                continue;
+            }
+            push_escaped(&mut out, &chars[col - 1..cs - 1]);
+            let _ = write!(out, "<span class=\"tok\" id=\"t{id}\">");
+            push_escaped(&mut out, &chars[cs - 1..ce.min(chars.len())]);
+            out.push_str("</span>");
+            col = ce.min(chars.len()) + 1;
+        }
+        while marks.peek().is_some_and(|m| m.0 == ln) {
+            marks.next();
+        }
+        push_escaped(&mut out, &chars[col - 1..]);
+        out.push('\n');
+    }
+    out
+}
+
+fn push_escaped(out: &mut String, chars: &[char]) {
+    for c in chars {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(*c),
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    push_escaped(&mut out, &s.chars().collect::<Vec<_>>());
+    out
+}
@@ -0,0 +1,143 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::input::TokenInput;
+use crate::parse::ParseData;
+use crate::token::Span;
+
+struct Entry<'d, T> {
+    start: (usize, usize),
+    end: (usize, usize),
+    depth: usize,
+    parent: Option<usize>,
+    node: &'d ParseData<T>,
+}
+
+/// The nodes of a tree sorted by where they start, so finding the node at a position is a
+/// binary search plus a walk up through its ancestors.
+pub struct NodeIndex<'d, T> {
+    entries: Vec<Entry<'d, T>>,
+}
+
+impl<'d, T> NodeIndex<'d, T> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The innermost node whose span holds the position.
+    pub fn node_at(&self, ln: usize, col: usize) -> Option<&'d ParseData<T>> {
+        let pos = (ln, col);
+
+        // the innermost node holding `pos` is the last node starting at or before it, or one of
+        // that node's ancestors, all of which start earlier still
+        let mut at = self
+            .entries
+            .partition_point(|e| e.start <= pos)
+            .checked_sub(1);
+        while let Some(i) = at {
+            let entry = &self.entries[i];
+            if pos <= entry.end {
+                return Some(entry.node);
+            }
+            at = entry.parent;
+        }
+        None
+    }
+
+    pub fn build(root: &'d ParseData<T>) -> Self {
+        let mut found = vec![];
+        collect(root, 0, None, &mut found);
+
+        // parents before children where they start together
+        let mut order: Vec<usize> = (0..found.len()).collect();
+        order.sort_by_key(|&i| (found[i].start, found[i].depth));
+        let mut rank = vec![0; found.len()];
+        for (new, &old) in order.iter().enumerate() {
+            rank[old] = new;
+        }
+
+        let mut found: Vec<Option<Entry<'d, T>>> = found.into_iter().map(Some).collect();
+        let entries = order
+            .iter()
+            .filter_map(|&old| found[old].take())
+            .map(|e| Entry {
+                parent: e.parent.map(|p| rank[p]),
+                ..e
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+pub fn first_span<T>(data: &ParseData<T>) -> Option<&Span> {
+    match data {
+        ParseData::Token(t) => Some(t.span()),
+        ParseData::TokenList(l) => l.first().map(|t| t.span()),
+        ParseData::Error { span, .. } => Some(span),
+        ParseData::Nested(l) => l.iter().find_map(first_span),
+    }
+}
+
+pub fn last_span<T>(data: &ParseData<T>) -> Option<&Span> {
+    match data {
+        ParseData::Token(t) => Some(t.span()),
+        ParseData::TokenList(l) => l.last().map(|t| t.span()),
+        ParseData::Error { span, skipped, .. } => skipped.last().map(|t| t.span()).or(Some(span)),
+        ParseData::Nested(l) => l.iter().rev().find_map(last_span),
+    }
+}
+
+/// Where the tokens under `data` sit in `tokens`, the buffer it was parsed from. Lets trees
+/// refer back into a shared buffer by index rather than through their own copies. Tokens are
+/// found by position, so `tokens` must be in source order.
+pub fn token_range<T>(data: &ParseData<T>, tokens: &dyn TokenInput<T>) -> Option<Range<usize>> {
+    let start = position(tokens, first_span(data)?)?;
+    let end = position(tokens, last_span(data)?)?;
+    Some(start..end + 1)
+}
+
+fn position<T>(tokens: &dyn TokenInput<T>, span: &Span) -> Option<usize> {
+    let key = (span.ln(), span.cs());
+    let (mut lo, mut hi) = (0, tokens.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let at = tokens.span_at(mid)?;
+        match (at.ln(), at.cs()).cmp(&key) {
+            core::cmp::Ordering::Less => lo = mid + 1,
+            core::cmp::Ordering::Greater => hi = mid,
+            core::cmp::Ordering::Equal => return Some(mid),
+        }
+    }
+    None
+}
+
+fn collect<'d, T>(
+    data: &'d ParseData<T>,
+    depth: usize,
+    parent: Option<usize>,
+    entries: &mut Vec<Entry<'d, T>>,
+) {
+    let (Some(first), Some(last)) = (first_span(data), last_span(data)) else {
+        return;
+    };
+
+    let at = entries.len();
+    entries.push(Entry {
+        start: (first.ln(), first.cs()),
+        end: (last.ln(), last.ce()),
+        depth,
+        parent,
+        node: data,
+    });
+
+    if let ParseData::Nested(l) = data {
+        for child in l {
+            collect(child, depth + 1, Some(at), entries);
+        }
+    }
+}
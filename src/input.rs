@@ -0,0 +1,248 @@
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::{OnceCell, RefCell};
+
+use crate::token::{Span, Token};
+
+pub trait TokenInput<T> {
+    fn get(&self, index: usize) -> Option<&Token<T>>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn span_at(&self, index: usize) -> Option<Span> {
+        self.get(index).map(|t| t.span().clone())
+    }
+
+    /// The span of the last token, or for input read lazily, of the last token read so far.
+    fn span_last(&self) -> Option<Span> {
+        self.len().checked_sub(1).and_then(|i| self.span_at(i))
+    }
+}
+
+impl<T> TokenInput<T> for [Token<T>] {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        <[Token<T>]>::get(self, index)
+    }
+
+    fn len(&self) -> usize {
+        <[Token<T>]>::len(self)
+    }
+}
+
+impl<T, const N: usize> TokenInput<T> for [Token<T>; N] {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        self.as_slice().get(index)
+    }
+
+    fn len(&self) -> usize {
+        N
+    }
+}
+
+impl<T> TokenInput<T> for &[Token<T>] {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        (**self).get(index)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+impl<T> TokenInput<T> for Vec<Token<T>> {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        self.as_slice().get(index)
+    }
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+}
+
+impl<T> TokenInput<T> for Rc<[Token<T>]> {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        (**self).get(index)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+impl<T> TokenInput<T> for Arc<[Token<T>]> {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        (**self).get(index)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+/// The first `len` tokens of another input.
+pub struct Truncated<'t, T> {
+    tokens: &'t dyn TokenInput<T>,
+    len: usize,
+}
+
+impl<'t, T> Truncated<'t, T> {
+    pub const fn new(tokens: &'t dyn TokenInput<T>, len: usize) -> Self {
+        Self { tokens, len }
+    }
+}
+
+impl<T> TokenInput<T> for Truncated<'_, T> {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        if index < self.len {
+            self.tokens.get(index)
+        } else {
+            None
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len.min(self.tokens.len())
+    }
+
+    fn span_last(&self) -> Option<Span> {
+        let last = self.len.checked_sub(1)?;
+        self.tokens
+            .span_at(last)
+            .or_else(|| self.tokens.span_last())
+    }
+}
+
+/// Another input with the token at `at` replaced by `tokens`, read through rather than copied.
+pub struct Spliced<'t, T> {
+    tokens: &'t dyn TokenInput<T>,
+    at: usize,
+    inserted: Vec<Token<T>>,
+}
+
+impl<'t, T> Spliced<'t, T> {
+    pub const fn new(tokens: &'t dyn TokenInput<T>, at: usize, inserted: Vec<Token<T>>) -> Self {
+        Self {
+            tokens,
+            at,
+            inserted,
+        }
+    }
+}
+
+impl<T> TokenInput<T> for Spliced<'_, T> {
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        if index < self.at {
+            self.tokens.get(index)
+        } else if index < self.at + self.inserted.len() {
+            self.inserted.get(index - self.at)
+        } else {
+            self.tokens.get(index + 1 - self.inserted.len())
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.tokens.len() {
+            0 => self.inserted.len(),
+            len if len <= self.at => len,
+            len => len - 1 + self.inserted.len(),
+        }
+    }
+
+    fn span_last(&self) -> Option<Span> {
+        if self.tokens.get(self.at + 1).is_some() {
+            return self.tokens.span_last();
+        }
+        match self.inserted.last() {
+            Some(last) => Some(last.span().clone()),
+            None => self.at.checked_sub(1).and_then(|i| self.tokens.span_at(i)),
+        }
+    }
+}
+
+const FIRST_CHUNK: usize = 64;
+
+struct Chunk<T> {
+    tokens: Vec<Token<T>>,
+    next: OnceCell<Box<Chunk<T>>>,
+}
+
+// Chunks double in size and are never moved once filled, so `get` can hand out references.
+pub struct BufferedTokens<T, I> {
+    source: RefCell<I>,
+    head: OnceCell<Box<Chunk<T>>>,
+}
+
+impl<T, I> BufferedTokens<T, I>
+where
+    I: Iterator<Item = Token<T>>,
+{
+    fn fill(&self, capacity: usize) -> Box<Chunk<T>> {
+        let tokens = self.source.borrow_mut().by_ref().take(capacity).collect();
+        Box::new(Chunk {
+            tokens,
+            next: OnceCell::new(),
+        })
+    }
+
+    pub fn new(source: I) -> Self {
+        Self {
+            source: RefCell::new(source),
+            head: OnceCell::new(),
+        }
+    }
+}
+
+impl<T, I> TokenInput<T> for BufferedTokens<T, I>
+where
+    I: Iterator<Item = Token<T>>,
+{
+    fn get(&self, index: usize) -> Option<&Token<T>> {
+        let mut capacity = FIRST_CHUNK;
+        let mut chunk = self.head.get_or_init(|| self.fill(capacity));
+        let mut index = index;
+
+        loop {
+            if index < chunk.tokens.len() {
+                return chunk.tokens.get(index);
+            }
+            if chunk.tokens.len() < capacity {
+                return None;
+            }
+            index -= capacity;
+            capacity *= 2;
+            chunk = chunk.next.get_or_init(|| self.fill(capacity));
+        }
+    }
+
+    fn len(&self) -> usize {
+        let mut capacity = FIRST_CHUNK;
+        let mut chunk = self.head.get_or_init(|| self.fill(capacity));
+        let mut len = 0;
+
+        loop {
+            len += chunk.tokens.len();
+            if chunk.tokens.len() < capacity {
+                return len;
+            }
+            capacity *= 2;
+            chunk = chunk.next.get_or_init(|| self.fill(capacity));
+        }
+    }
+
+    // Errors point here at every failed Choice, so this must not read the rest of the source.
+    fn span_last(&self) -> Option<Span> {
+        let mut chunk = self.head.get_or_init(|| self.fill(FIRST_CHUNK));
+        let mut last = chunk.tokens.last();
+        while let Some(next) = chunk.next.get() {
+            chunk = next;
+            last = chunk.tokens.last().or(last);
+        }
+        last.map(|t| t.span().clone())
+    }
+}
@@ -0,0 +1,33 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::Serialize;
+
+use crate::parse::{ParseData, ParseError};
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Outcome<'a, T> {
+    Tree { tree: &'a ParseData<T> },
+    Errors { errors: &'a [ParseError] },
+}
+
+/// A parse result as JSON: `{"tree": …}` when it parsed and `{"errors": […]}` when it didn't,
+/// for tools on the other side of a process or language boundary.
+pub fn result_to_json<T: Serialize>(
+    result: &Result<ParseData<T>, Vec<ParseError>>,
+) -> serde_json::Result<String> {
+    let outcome = match result {
+        Ok(tree) => Outcome::Tree { tree },
+        Err(errors) => Outcome::Errors { errors },
+    };
+    serde_json::to_string(&outcome)
+}
+
+pub fn tree_to_json<T: Serialize>(tree: &ParseData<T>) -> serde_json::Result<String> {
+    serde_json::to_string(tree)
+}
+
+pub fn errors_to_json(errors: &[ParseError]) -> serde_json::Result<String> {
+    serde_json::to_string(errors)
+}
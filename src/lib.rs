@@ -1,3 +1,66 @@
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod macros;
+
+pub mod backtrack;
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod budget;
+pub mod cache;
+pub mod column;
+pub mod complete;
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod corpus;
+pub mod coverage;
+pub mod describe;
+pub mod diff;
+pub mod edit;
+pub mod escape;
+pub mod explain;
+pub mod factor;
+pub mod filter;
+pub mod first;
+pub mod forest;
+pub mod format;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod generate;
 pub mod grammar;
+pub mod highlight;
+pub mod html;
+pub mod index;
+pub mod input;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod literal;
+pub mod operator;
 pub mod parse;
+pub mod prelude;
+mod progress;
+pub mod recovery;
+pub mod relex;
+pub mod rewrite;
+pub mod roundtrip;
+pub mod rules;
+pub mod scratch;
+pub mod session;
+pub mod snapshot;
+pub mod snippet;
+#[cfg(feature = "proptest")]
+pub mod strategy;
 pub mod token;
+pub mod trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::boxed::Box;
+    pub use alloc::vec;
+}
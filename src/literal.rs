@@ -0,0 +1,194 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::escape::Escapes;
+use crate::parse::{Context, ParseError};
+use crate::token::{Span, Token};
+
+/// The value of a number or character literal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Literal {
+    Int(u128),
+    Float(f64),
+    Char(char),
+}
+
+/// Decodes an integer literal: decimal, or hex, octal or binary after `0x`, `0o` or `0b`, with
+/// `_` allowed between digits. `span` is where `text` sits in the source, columns counting
+/// characters, and errors span just the offending digit, or the whole literal for overflow.
+pub fn int(text: &str, span: &Span) -> Result<u128, ParseError> {
+    let (radix, prefix) = match text.get(..2) {
+        Some("0x") => (16, 2),
+        Some("0o") => (8, 2),
+        Some("0b") => (2, 2),
+        _ => (10, 0),
+    };
+    let mut value: Option<u128> = None;
+    for (i, c) in text[prefix..].chars().enumerate() {
+        if c == '_' {
+            continue;
+        }
+        let Some(digit) = c.to_digit(radix) else {
+            let col = span.cs() + prefix + i;
+            return Err(error(
+                "integer literal",
+                Span::new(span.ln(), col, col),
+                format!("Invalid digit {c:?} in a base {radix} literal"),
+            ));
+        };
+        value = Some(
+            value
+                .unwrap_or(0)
+                .checked_mul(u128::from(radix))
+                .and_then(|v| v.checked_add(u128::from(digit)))
+                .ok_or_else(|| {
+                    error(
+                        "integer literal",
+                        span.clone(),
+                        "Integer literal is too large".to_string(),
+                    )
+                })?,
+        );
+    }
+    value.ok_or_else(|| {
+        error(
+            "integer literal",
+            span.clone(),
+            "Expected digits in integer literal".to_string(),
+        )
+    })
+}
+
+/// Like [`int`], also checking that the value fits in `N`.
+pub fn int_as<N: TryFrom<u128>>(text: &str, span: &Span) -> Result<N, ParseError> {
+    let value = int(text, span)?;
+    N::try_from(value).map_err(|_| {
+        error(
+            "integer literal",
+            span.clone(),
+            format!(
+                "Integer literal {value} does not fit in {}",
+                core::any::type_name::<N>()
+            ),
+        )
+    })
+}
+
+/// Decodes a float literal: digits, then optionally a fraction after `.` and an exponent after
+/// `e` or `E`, with `_` allowed between digits. Spans work as in [`int`].
+pub fn float(text: &str, span: &Span) -> Result<f64, ParseError> {
+    let at = |i: usize, message: String| {
+        let col = span.cs() + i;
+        error("float literal", Span::new(span.ln(), col, col), message)
+    };
+    let mut part = Part::Whole;
+    let mut digits = 0;
+    let mut prev = None;
+    for (i, c) in text.chars().enumerate() {
+        match (c, part) {
+            ('0'..='9', _) => digits += 1,
+            ('_', _) if digits > 0 => (),
+            ('.', Part::Whole) if digits > 0 => part = Part::Fraction,
+            ('e' | 'E', Part::Whole | Part::Fraction) if digits > 0 => {
+                part = Part::Exponent;
+                digits = 0;
+            }
+            ('+' | '-', Part::Exponent) if matches!(prev, Some('e' | 'E')) => (),
+            _ => return Err(at(i, format!("Unexpected {c:?} in float literal"))),
+        }
+        prev = Some(c);
+    }
+    if digits == 0 {
+        let end = text.chars().count();
+        return Err(at(end.saturating_sub(1), "Expected digits".to_string()));
+    }
+
+    let value: f64 = text
+        .chars()
+        .filter(|c| *c != '_')
+        .collect::<String>()
+        .parse()
+        .map_err(|_| {
+            error(
+                "float literal",
+                span.clone(),
+                "Invalid float literal".to_string(),
+            )
+        })?;
+    if value.is_infinite() {
+        return Err(error(
+            "float literal",
+            span.clone(),
+            "Float literal is out of range".to_string(),
+        ));
+    }
+    Ok(value)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Part {
+    Whole,
+    Fraction,
+    Exponent,
+}
+
+/// Decodes a character literal, quotes included, with `escapes` for what may follow a `\`.
+pub fn character(text: &str, span: &Span, escapes: &Escapes) -> Result<char, ParseError> {
+    let inner = text
+        .strip_prefix('\'')
+        .and_then(|t| t.strip_suffix('\''))
+        .filter(|_| text.len() >= 2)
+        .ok_or_else(|| {
+            error(
+                "character literal",
+                span.clone(),
+                "Expected a character in single quotes".to_string(),
+            )
+        })?;
+    let decoded = escapes
+        .decode(inner, span.ln(), span.cs() + 1)
+        .map_err(|mut errors| errors.remove(0))?;
+    let mut chars = decoded.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        (None, _) => Err(error(
+            "character literal",
+            span.clone(),
+            "Empty character literal".to_string(),
+        )),
+        _ => Err(error(
+            "character literal",
+            span.clone(),
+            "Character literal holds more than one character".to_string(),
+        )),
+    }
+}
+
+/// Decodes `text` as whichever literal it looks like: a character if it starts with `'`, a
+/// float if it is decimal with a `.` or an exponent, and an integer otherwise.
+pub fn literal(text: &str, span: &Span) -> Result<Literal, ParseError> {
+    if text.starts_with('\'') {
+        return character(text, span, &Escapes::default()).map(Literal::Char);
+    }
+    let radix_prefix = matches!(text.get(..2), Some("0x" | "0o" | "0b"));
+    if !radix_prefix && text.contains(['.', 'e', 'E']) {
+        float(text, span).map(Literal::Float)
+    } else {
+        int(text, span).map(Literal::Int)
+    }
+}
+
+/// The token at `index` with its [`literal`] value, read from the source attached to `ctx`.
+/// `None` past the end of input or without a source.
+pub fn token_literal<'c, T>(
+    ctx: &'c Context<T>,
+    index: usize,
+) -> Option<(&'c Token<T>, Result<Literal, ParseError>)> {
+    let token = ctx.get(index)?;
+    let text = ctx.token_text(index)?;
+    Some((token, literal(text, token.span())))
+}
+
+fn error(expected: &str, span: Span, message: String) -> ParseError {
+    ParseError::from(expected.to_string(), span).with_message(message)
+}
@@ -0,0 +1,88 @@
+#[macro_export]
+macro_rules! seq {
+    ($pty:expr; $($p:expr),* $(,)?) => {
+        $crate::parse::Sequence::from(
+            $pty,
+            false,
+            $crate::__private::vec![$($crate::__private::Box::new($p) as $crate::__private::Box<dyn $crate::parse::Parser<_>>),*],
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! choice {
+    ($pty:expr; $($p:expr),* $(,)?) => {
+        $crate::parse::Choice::from(
+            $pty,
+            false,
+            $crate::__private::vec![$($crate::__private::Box::new($p) as $crate::__private::Box<dyn $crate::parse::Parser<_>>),*],
+        )
+    };
+}
+
+/// Asserts that a parser's snapshot of `tokens` equals `expected`. Spans are elided unless the
+/// `spans` flag comes before the expected text.
+#[macro_export]
+macro_rules! assert_parse_snapshot {
+    ($parser:expr, $tokens:expr, spans, $expected:expr $(,)?) => {
+        ::core::assert_eq!(
+            $crate::snapshot::parse_snapshot(&$parser, &$tokens, true),
+            $expected
+        )
+    };
+    ($parser:expr, $tokens:expr, $expected:expr $(,)?) => {
+        ::core::assert_eq!(
+            $crate::snapshot::parse_snapshot(&$parser, &$tokens, false),
+            $expected
+        )
+    };
+}
+
+/// Asserts that a parser matches all of `tokens`, and optionally that the tree matches a
+/// pattern, as in `matches!`. Failures print the errors or the tree.
+#[macro_export]
+macro_rules! assert_parses {
+    ($parser:expr, $tokens:expr $(,)?) => {
+        $crate::assert_parses!($parser, $tokens, _)
+    };
+    ($parser:expr, $tokens:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $crate::parse::Parser::parse_all(&$parser, &$tokens) {
+            ::core::result::Result::Ok(tree) => ::core::assert!(
+                ::core::matches!(&tree, $pattern $(if $guard)?),
+                "tree does not match `{}`:\n{}",
+                ::core::stringify!($pattern $(if $guard)?),
+                $crate::snapshot::render(&tree, true),
+            ),
+            ::core::result::Result::Err(errors) => ::core::panic!(
+                "expected the parse to succeed:\n{}",
+                $crate::snapshot::render_errors(&errors),
+            ),
+        }
+    };
+}
+
+/// Asserts that a parser fails on `tokens`, and optionally that the rendered errors (expected
+/// rule, span and message, one `error:` line each) contain `needle`.
+#[macro_export]
+macro_rules! assert_fails {
+    ($parser:expr, $tokens:expr $(,)?) => {
+        $crate::assert_fails!($parser, $tokens, "")
+    };
+    ($parser:expr, $tokens:expr, $needle:expr $(,)?) => {
+        match $crate::parse::Parser::parse_all(&$parser, &$tokens) {
+            ::core::result::Result::Ok(tree) => ::core::panic!(
+                "expected the parse to fail, got:\n{}",
+                $crate::snapshot::render(&tree, true),
+            ),
+            ::core::result::Result::Err(errors) => {
+                let rendered = $crate::snapshot::render_errors(&errors);
+                ::core::assert!(
+                    rendered.contains($needle),
+                    "errors do not mention {:?}:\n{}",
+                    $needle,
+                    rendered,
+                );
+            }
+        }
+    };
+}
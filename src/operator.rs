@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fixity {
+    Prefix,
+    InfixLeft,
+    InfixRight,
+}
+
+/// The operators an [`Expression`](crate::parse::Expression) knows about, with higher
+/// precedences binding tighter. Attached to a [`Context`](crate::parse::Context) it can grow
+/// while the parse is running, for languages that declare their own operators, and the
+/// declarations apply to everything parsed after them.
+pub struct OperatorTable<T> {
+    // a log of declarations, latest last, so rewinding to a checkpoint is a truncation
+    operators: RefCell<Vec<(T, Fixity, u16)>>,
+}
+
+impl<T: PartialEq> OperatorTable<T> {
+    /// Adds `ty` as an operator, overriding an earlier declaration of it with the same kind of
+    /// fixity, prefix or infix.
+    pub fn insert(&self, ty: T, fixity: Fixity, precedence: u16) {
+        self.operators.borrow_mut().push((ty, fixity, precedence));
+    }
+
+    pub fn prefix(&self, ty: &T) -> Option<u16> {
+        self.find(ty, true).map(|(_, p)| p)
+    }
+
+    pub fn infix(&self, ty: &T) -> Option<(Fixity, u16)> {
+        self.find(ty, false)
+    }
+
+    fn find(&self, ty: &T, prefix: bool) -> Option<(Fixity, u16)> {
+        self.operators
+            .borrow()
+            .iter()
+            .rev()
+            .find(|(t, f, _)| t == ty && is_prefix(*f) == prefix)
+            .map(|(_, f, p)| (*f, *p))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.borrow().is_empty()
+    }
+}
+
+impl<T> OperatorTable<T> {
+    pub const fn new() -> Self {
+        Self {
+            operators: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn mark(&self) -> usize {
+        self.operators.borrow().len()
+    }
+
+    pub(crate) fn rewind(&self, mark: usize) {
+        self.operators.borrow_mut().truncate(mark);
+    }
+}
+
+impl<T> Default for OperatorTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_prefix(fixity: Fixity) -> bool {
+    fixity == Fixity::Prefix
+}
@@ -1,7 +1,52 @@
-use crate::token::{Span, Token};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::rc::{Rc, Weak};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{Cell, OnceCell, RefCell};
+use core::fmt::{Debug, Display, Formatter};
+
+use crate::budget::{Budget, CancellationToken};
+use crate::column::ColumnPolicy;
+use crate::complete::{Expected, ExpectedSet};
+use crate::coverage::Coverage;
+use crate::first::{Conflict, TokenSet};
+use crate::forest::Forks;
+use crate::generate::Generator;
+use crate::index::first_span;
+use crate::input::{Spliced, TokenInput, Truncated};
+use crate::operator::{Fixity, OperatorTable};
+use crate::progress::Progress;
+use crate::recovery::{Recovery, RecoveryStrategy};
+use crate::scratch::Scratch;
+use crate::snippet::SnippetPart;
+use crate::token::{HasKind, Provenance, Span, Token};
+use crate::trace::{Outcome, TraceRecorder};
 
 pub struct Context<'t, T> {
-    tokens: &'t [Token<T>],
+    tokens: &'t dyn TokenInput<T>,
+    recorder: Option<&'t TraceRecorder>,
+    coverage: Option<&'t Coverage>,
+    expected: Option<&'t ExpectedSet<T>>,
+    budget: Budget,
+    progress: Option<Progress<'t>>,
+    source: Option<&'t str>,
+    columns: ColumnPolicy,
+    skippable: Option<fn(&T) -> bool>,
+    path: Option<RefCell<Vec<Arc<str>>>>,
+    operators: Option<&'t OperatorTable<T>>,
+    scratch: Option<&'t Scratch<T>>,
+    forks: Option<&'t Forks>,
+    // the context this one parses a rewritten stream for, whose budget and rule path it shares
+    parent: Option<&'t Context<'t, T>>,
+    // the furthest offset a rule was tried at
+    furthest: Cell<usize>,
+    // what the first rule to run out of input expected, and where
+    ran_out: RefCell<Option<(String, Span)>>,
 }
 
 impl<'t, T> Context<'t, T> {
@@ -19,398 +64,3431 @@ impl<'t, T> Context<'t, T> {
             if optional {
                 ParseResult::None
             } else {
-                ParseResult::Err(ParseError::new(
-                    pty.to_string(),
-                    self.span_last(),
-                    "Unexpected end of input",
-                ))
+                ParseResult::Err(
+                    ParseError::new(pty.to_string(), self.span_last(), "Unexpected end of input")
+                        .with_kind(ErrorKind::UnexpectedEof),
+                )
             }
         })
     }
 
     pub fn span_last(&self) -> Span {
-        self.get(self.tokens.len() - 1)
-            .map(|t| t.span().clone())
-            .unwrap_or_default()
+        self.tokens.span_last().unwrap_or_default()
+    }
+
+    pub fn span_at(&self, index: usize) -> Option<Span> {
+        self.tokens.span_at(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn recorder(&self) -> Option<&'t TraceRecorder> {
+        self.recorder
+    }
+
+    pub const fn with_recorder(mut self, recorder: &'t TraceRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn coverage(&self) -> Option<&'t Coverage> {
+        self.coverage
+    }
+
+    pub const fn with_coverage(mut self, coverage: &'t Coverage) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    pub const fn with_expected(mut self, expected: &'t ExpectedSet<T>) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    fn expect(&self, offset: usize, expected: impl FnOnce() -> Expected<T>) {
+        if let Some(set) = self.expected {
+            // the rule recording this entered last
+            let step = self.recorder.map_or(0, |r| r.len().saturating_sub(1));
+            set.record(offset, step, expected);
+        }
+    }
+
+    /// Fails the parse once `steps` rule attempts have been made.
+    pub fn with_step_budget(mut self, steps: usize) -> Self {
+        self.budget.set_steps(steps);
+        self
+    }
+
+    /// Fails the parse once `deadline` has passed. The clock is read every few hundred rule
+    /// attempts, so the parse may overrun slightly.
+    #[cfg(feature = "std")]
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.budget.set_deadline(deadline);
+        self
+    }
+
+    pub fn steps_taken(&self) -> usize {
+        self.budget().steps_taken()
+    }
+
+    /// Checks `cancel` on every rule attempt and fails the parse once it is raised.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.budget.set_cancel(cancel);
+        self
+    }
+
+    /// Calls `callback` with the furthest offset matched so far each time it passes another
+    /// `every` tokens, for progress bars on long inputs.
+    pub fn with_progress(mut self, every: usize, callback: &'t dyn Fn(usize)) -> Self {
+        self.progress = Some(Progress::new(every, callback));
+        self
+    }
+
+    /// Keeps the text the tokens were lexed from, so diagnostics can quote it.
+    pub const fn with_source(mut self, source: &'t str) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn source(&self) -> Option<&'t str> {
+        self.source
+    }
+
+    /// How the lexer counted span columns in the source; characters unless set.
+    pub const fn with_columns(mut self, columns: ColumnPolicy) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// The source text of the token at `index`.
+    pub fn token_text(&self, index: usize) -> Option<&'t str> {
+        self.get(index)?
+            .span()
+            .text_with(self.source?, self.columns)
+    }
+
+    /// Renders `error` followed by the offending source line with the span underlined, or just
+    /// the error when no source is attached.
+    pub fn render_error(&self, error: &ParseError) -> String {
+        let span = error.span();
+        let line = self
+            .source
+            .and_then(|s| s.lines().nth(span.ln().saturating_sub(1)));
+        let mut out = match line {
+            Some(line) => {
+                let gutter = span.ln().to_string();
+                let pad = " ".repeat(gutter.len());
+                // carets go under what the terminal shows, whatever the columns count
+                let width = |s: &str| ColumnPolicy::Width.column(s, s.len()) - 1;
+                let start = self.columns.byte_offset(line, span.cs());
+                let end = self.columns.byte_offset(line, span.ce() + 1);
+                let (lead, len) = match (start, end) {
+                    (Some(a), Some(b)) if a <= b => (width(&line[..a]), width(&line[a..b])),
+                    _ => (
+                        span.cs().saturating_sub(1),
+                        span.ce().saturating_sub(span.cs()) + 1,
+                    ),
+                };
+                let lead = " ".repeat(lead);
+                let marks = "^".repeat(len.max(1));
+                format!("{error}\n{pad} |\n{gutter} | {line}\n{pad} | {lead}{marks}")
+            }
+            None => error.to_string(),
+        };
+        for p in error.provenance().into_iter().flat_map(|p| p.chain()) {
+            out.push_str(&format!(
+                "\nnote: expanded from {} at {}",
+                p.reason(),
+                p.span()
+            ));
+        }
+        out
+    }
+
+    /// Lets the grammar ignore tokens `skippable` accepts, such as whitespace and comments,
+    /// while they stay in the stream. Sequences and repetitions step over them between items,
+    /// and whole-input parses at either end.
+    pub const fn with_skippable(mut self, skippable: fn(&T) -> bool) -> Self {
+        self.skippable = Some(skippable);
+        self
+    }
+
+    /// Tracks the chain of rules being parsed, so each `Parse` and the first error out of a rule
+    /// carry a dotted path such as `document.items.item.value`.
+    pub fn with_paths(mut self) -> Self {
+        self.path = Some(RefCell::new(Vec::new()));
+        self
+    }
+
+    /// The operators `Expression` parsers use, which `Declare` parsers can add to mid-parse.
+    pub const fn with_operators(mut self, operators: &'t OperatorTable<T>) -> Self {
+        self.operators = Some(operators);
+        self
+    }
+
+    pub fn operators(&self) -> Option<&'t OperatorTable<T>> {
+        self.operators
+    }
+
+    /// Builds nodes in buffers from `scratch`, which can be shared across parses.
+    pub const fn with_scratch(mut self, scratch: &'t Scratch<T>) -> Self {
+        self.scratch = Some(scratch);
+        self
+    }
+
+    // Steers every Choice down the alternatives `forks` scripts, for parse forests.
+    pub(crate) const fn with_forks(mut self, forks: &'t Forks) -> Self {
+        self.forks = Some(forks);
+        self
+    }
+
+    fn children(&self) -> Vec<ParseData<T>> {
+        self.scratch.map(Scratch::take).unwrap_or_default()
+    }
+
+    fn discard(&self, children: Vec<ParseData<T>>) {
+        if let Some(scratch) = self.scratch {
+            scratch.give(children);
+        }
+    }
+
+    /// Notes the parse state at `offset`, for a hand-written parser to come back to with
+    /// [`rewind`](Self::rewind) if a speculative attempt fails. Operators declared since are
+    /// forgotten again on rewinding, as the combinators that backtrack do.
+    pub fn checkpoint(&self, offset: usize) -> Checkpoint {
+        Checkpoint {
+            offset,
+            operators: self.operators.map_or(0, OperatorTable::mark),
+        }
+    }
+
+    /// Restores the state at `checkpoint` and gives back its offset to resume from.
+    pub fn rewind(&self, checkpoint: &Checkpoint) -> usize {
+        if let Some(operators) = self.operators {
+            operators.rewind(checkpoint.operators);
+        }
+        checkpoint.offset
+    }
+
+    /// The first offset from `offset` on that isn't a skippable token.
+    pub fn skip(&self, mut offset: usize) -> usize {
+        if let Some(skippable) = self.skippable {
+            while self.get(offset).is_some_and(|t| skippable(t.ty())) {
+                offset += 1;
+            }
+        }
+        offset
+    }
+
+    // The error a whole parse ends with. Optional and repeated rules that ran out of input
+    // matched less instead, so a syntax error here may only be the input ending early.
+    pub(crate) fn settle(&self, e: ParseError) -> ParseError {
+        match e.kind {
+            ErrorKind::Syntax => match self.ran_out.borrow().clone() {
+                Some((expected, span)) => {
+                    ParseError::new(expected, span, "Unexpected end of input")
+                        .with_kind(ErrorKind::UnexpectedEof)
+                }
+                None => e,
+            },
+            _ => e,
+        }
+    }
+
+    // The same tokens and settings for a parse of its own, with the budget's limits but none of
+    // it spent, and an empty rule path.
+    pub(crate) fn renewed(&self) -> Context<'t, T> {
+        Context {
+            recorder: self.recorder,
+            coverage: self.coverage,
+            budget: self.budget().renewed(),
+            source: self.source,
+            columns: self.columns,
+            skippable: self.skippable,
+            path: self.path().map(|_| RefCell::new(Vec::new())),
+            operators: self.operators,
+            scratch: self.scratch,
+            ..Context::new(self.tokens)
+        }
+    }
+
+    // The same settings over other tokens, for parsing a rewritten stream. The budget and
+    // rule path are shared, so limits, cancellation and paths span both streams.
+    fn over<'u>(&'u self, tokens: &'u dyn TokenInput<T>) -> Context<'u, T>
+    where
+        't: 'u,
+    {
+        Context {
+            recorder: self.recorder,
+            coverage: self.coverage,
+            source: self.source,
+            columns: self.columns,
+            skippable: self.skippable,
+            operators: self.operators,
+            scratch: self.scratch,
+            forks: self.forks,
+            parent: Some(self),
+            ..Context::new(tokens)
+        }
+    }
+
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget().aborted() == Some(ErrorKind::BudgetExceeded)
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.budget().aborted() == Some(ErrorKind::Cancelled)
+    }
+
+    /// The error to report instead of the parse result once the budget has run out or the
+    /// parse was cancelled. Choice, Recover and optional parsers may turn the failure into
+    /// something else on the way up, so callers of [`Parser::parse`] should check this first.
+    pub fn abort_error(&self) -> Option<ParseError> {
+        let budget = self.budget();
+        budget.aborted()?;
+        let span = budget
+            .deepest()
+            .and_then(|(_, offset)| self.span_at(offset))
+            .unwrap_or_else(|| self.span_last());
+        Some(budget.error(span))
+    }
+
+    pub const fn new(tokens: &'t dyn TokenInput<T>) -> Self {
+        Self {
+            tokens,
+            recorder: None,
+            coverage: None,
+            expected: None,
+            budget: Budget::new(),
+            progress: None,
+            source: None,
+            columns: ColumnPolicy::Chars,
+            skippable: None,
+            path: None,
+            operators: None,
+            scratch: None,
+            forks: None,
+            parent: None,
+            furthest: Cell::new(0),
+            ran_out: RefCell::new(None),
+        }
+    }
+
+    fn budget(&self) -> &Budget {
+        match self.parent {
+            Some(parent) => parent.budget(),
+            None => &self.budget,
+        }
     }
 
-    pub const fn new(tokens: &'t [Token<T>]) -> Self {
-        Self { tokens }
+    fn path(&self) -> Option<&RefCell<Vec<Arc<str>>>> {
+        match self.parent {
+            Some(parent) => parent.path(),
+            None => self.path.as_ref(),
+        }
+    }
+
+    // Notes a rule failing with `e`, remembering the first to run out of input.
+    fn fail(&self, e: &ParseError) {
+        let mut ran_out = self.ran_out.borrow_mut();
+        if e.is_incomplete() && ran_out.is_none() {
+            *ran_out = Some((e.expected().to_string(), e.span().clone()));
+        }
+    }
+}
+
+/// Parse state saved by [`Context::checkpoint`].
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    offset: usize,
+    operators: usize,
+}
+
+impl Checkpoint {
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorKind {
+    Syntax,
+    /// The input ended where more was required; a longer input might still parse.
+    UnexpectedEof,
+    BudgetExceeded,
+    Cancelled,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParseError {
+    kind: ErrorKind,
     expected: String,
     span: Span,
-    message: &'static str,
+    message: Cow<'static, str>,
+    // boxed thin so the window fits without pushing errors past 128 bytes
+    after: Option<Arc<String>>,
+    provenance: Option<Arc<Provenance>>,
+    path: Option<Arc<str>>,
+    window: Option<Arc<TokenWindow>>,
+    // narrow for the same reason, u32::MAX when unknown
+    offset: u32,
+}
+
+/// The tokens either side of where an error happened, written out as text, so a report still
+/// shows what the parser was looking at once the source is gone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenWindow {
+    /// The tokens just before the failure, nearest last.
+    pub before: Vec<String>,
+    /// The offending token and the ones after it; empty at the end of input.
+    pub after: Vec<String>,
+}
+
+impl Display for TokenWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for token in &self.before {
+            write!(f, "{token} ")?;
+        }
+        write!(f, "^")?;
+        for token in &self.after {
+            write!(f, " {token}")?;
+        }
+        Ok(())
+    }
 }
 
 impl ParseError {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Whether the error would go away with more input, as for a REPL line that needs a
+    /// continuation.
+    pub fn is_incomplete(&self) -> bool {
+        self.kind == ErrorKind::UnexpectedEof
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// Replaces what the error says was expected, say with a list from
+    /// [`ExpectedList`](crate::describe::ExpectedList) in place of the rule name.
+    pub fn with_expected(mut self, expected: String) -> Self {
+        self.expected = expected;
+        self
+    }
+
     pub fn span(&self) -> &Span {
         &self.span
     }
 
-    pub const fn from(expected: String, span: Span) -> Self {
-        Self::new(expected, span, "Syntax error")
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The rule that matched just before the failure, when it happened partway through a
+    /// sequence.
+    pub fn after(&self) -> Option<&str> {
+        self.after.as_deref().map(String::as_str)
+    }
+
+    pub fn with_after(mut self, after: String) -> Self {
+        self.after = Some(Arc::new(after));
+        self
+    }
+
+    /// Where the offending token came from, when preprocessing synthesized it.
+    pub fn provenance(&self) -> Option<&Arc<Provenance>> {
+        self.provenance.as_ref()
+    }
+
+    pub fn with_provenance(mut self, provenance: Arc<Provenance>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// The path of the innermost rule that failed, when the context tracks paths.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The offset of the token the innermost failing rule started at, in the tokens given to
+    /// the whole parse; unset on errors built by hand.
+    pub fn offset(&self) -> Option<usize> {
+        (self.offset != u32::MAX).then_some(self.offset as usize)
+    }
+
+    /// The tokens around the failure, when [`with_window`](Self::with_window) recorded them.
+    pub fn window(&self) -> Option<&TokenWindow> {
+        self.window.as_deref()
+    }
+
+    /// Records up to `radius` tokens of `tokens` either side of the failure, or of the end for
+    /// errors at the end of input, each written by `text`. The failure is the error's
+    /// [`offset`](Self::offset), or the first token at or past its span when it has none.
+    pub fn with_window<T>(
+        mut self,
+        tokens: &dyn TokenInput<T>,
+        radius: usize,
+        text: impl Fn(&Token<T>) -> String,
+    ) -> Self {
+        let failure = if self.is_incomplete() {
+            tokens.len()
+        } else if let Some(offset) = self.offset() {
+            offset.min(tokens.len())
+        } else {
+            // tokens are in source order, so search them for the span
+            let at = (self.span.ln(), self.span.cs());
+            let (mut low, mut high) = (0, tokens.len());
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match tokens.get(mid) {
+                    Some(t) if (t.span().ln(), t.span().cs()) < at => low = mid + 1,
+                    _ => high = mid,
+                }
+            }
+            low
+        };
+        let written = |range: core::ops::Range<usize>| -> Vec<String> {
+            range.filter_map(|i| tokens.get(i)).map(&text).collect()
+        };
+        self.window = Some(Arc::new(TokenWindow {
+            before: written(failure.saturating_sub(radius)..failure),
+            after: written(failure..(failure + radius).min(tokens.len())),
+        }));
+        self
+    }
+
+    pub fn with_message(mut self, message: String) -> Self {
+        self.message = Cow::Owned(message);
+        self
+    }
+
+    pub const fn from(expected: String, span: Span) -> Self {
+        Self::new(expected, span, "Syntax error")
+    }
+
+    pub const fn new(expected: String, span: Span, message: &'static str) -> Self {
+        Self {
+            kind: ErrorKind::Syntax,
+            expected,
+            span,
+            message: Cow::Borrowed(message),
+            after: None,
+            provenance: None,
+            path: None,
+            window: None,
+            offset: u32::MAX,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: expected {}", self.message, self.expected)?;
+        if let Some(after) = &self.after {
+            write!(f, " after {after}")?;
+        }
+        write!(f, " at {}", self.span)?;
+        if let Some(window) = &self.window {
+            write!(f, " near `{window}`")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Parse<T> {
+    type_parsed: Arc<str>,
+    data: ParseResult<T>,
+    start_offset: usize,
+    end_offset: usize,
+    path: Option<Arc<str>>,
+    alternative: Option<usize>,
+}
+
+impl<T> Parse<T> {
+    pub fn consumed(&self) -> usize {
+        self.end_offset - self.start_offset
+    }
+
+    pub fn size(&self) -> usize {
+        self.consumed()
+    }
+
+    pub fn type_parsed(&self) -> &str {
+        &self.type_parsed
+    }
+
+    /// The index of the alternative a `Choice` matched with, whose own name is in
+    /// `type_parsed`.
+    pub fn alternative(&self) -> Option<usize> {
+        self.alternative
+    }
+
+    /// Where this rule sat in the grammar, when the context tracks paths.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn data(&self) -> &ParseResult<T> {
+        &self.data
+    }
+
+    pub fn start_offset(&self) -> usize {
+        self.start_offset
+    }
+
+    pub fn end_offset(&self) -> usize {
+        self.end_offset
+    }
+
+    pub fn into_data(self) -> ParseResult<T> {
+        self.data
+    }
+
+    pub fn into_error(self) -> Option<ParseError> {
+        self.data.err()
+    }
+
+    pub fn into_result(self) -> Result<Option<ParseData<T>>, ParseError> {
+        self.data.into_result()
+    }
+
+    /// Figures for telemetry on how the parse over `ctx` went.
+    pub fn stats(&self, ctx: &Context<T>) -> ParseStats {
+        let mut stats = ParseStats {
+            consumed: self.consumed(),
+            total: ctx.len(),
+            steps: ctx.steps_taken(),
+            ..ParseStats::default()
+        };
+        match &self.data {
+            ParseResult::Ok(data) => {
+                stats.nodes = data.node_count();
+                stats.depth = data.depth();
+                stats.errors = data.errors().len();
+            }
+            ParseResult::Err(_) => stats.errors = 1,
+            ParseResult::None => (),
+        }
+        stats
+    }
+
+    pub fn expect(self, msg: &str) -> ParseData<T> {
+        match self.data {
+            ParseResult::Ok(data) => data,
+            ParseResult::Err(e) => panic!("{msg}: {} at {}", e.message(), e.span()),
+            ParseResult::None => panic!("{msg}: no {} parsed", self.type_parsed),
+        }
+    }
+
+    pub const fn new(
+        type_parsed: Arc<str>,
+        data: ParseResult<T>,
+        start_offset: usize,
+        end_offset: usize,
+    ) -> Self {
+        Self {
+            type_parsed,
+            data,
+            start_offset,
+            end_offset,
+            path: None,
+            alternative: None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseData<T> {
+    Nested(Vec<ParseData<T>>),
+    TokenList(Vec<Token<T>>),
+    Token(Token<T>),
+    Error {
+        expected: String,
+        span: Span,
+        skipped: Vec<Token<T>>,
+        /// What to tell the user in place of the generic message, from an
+        /// [`ErrorProduction`].
+        message: Option<Arc<str>>,
+    },
+}
+
+impl<T> ParseData<T> {
+    pub fn is_error(&self) -> bool {
+        matches!(self, ParseData::Error { .. })
+    }
+
+    pub fn has_errors(&self) -> bool {
+        match self {
+            ParseData::Error { .. } => true,
+            ParseData::Token(t) => t.provenance().is_some_and(|p| p.is_inserted()),
+            ParseData::Nested(l) => l.iter().any(|d| d.has_errors()),
+            _ => false,
+        }
+    }
+
+    /// Nodes in the tree, counting this one and every token.
+    pub fn node_count(&self) -> usize {
+        match self {
+            ParseData::Nested(l) => 1 + l.iter().map(|d| d.node_count()).sum::<usize>(),
+            ParseData::TokenList(l) => 1 + l.len(),
+            _ => 1,
+        }
+    }
+
+    /// Levels of nesting below this node; a lone token has depth 0.
+    pub fn depth(&self) -> usize {
+        match self {
+            ParseData::Nested(l) => 1 + l.iter().map(|d| d.depth()).max().unwrap_or(0),
+            ParseData::TokenList(l) if !l.is_empty() => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn first_token(&self) -> Option<&Token<T>> {
+        match self {
+            ParseData::Token(t) => Some(t),
+            ParseData::TokenList(l) => l.first(),
+            ParseData::Error { skipped, .. } => skipped.first(),
+            ParseData::Nested(l) => l.iter().find_map(|d| d.first_token()),
+        }
+    }
+
+    /// The doc comments written before this node: the leading trivia of its first token that
+    /// `is_doc` accepts, in source order. Needs trivia attached with
+    /// [`attach_trivia`](crate::token::attach_trivia) before parsing.
+    pub fn doc_comments(&self, is_doc: impl Fn(&Token<T>) -> bool) -> Vec<&Token<T>> {
+        self.first_token()
+            .map(|t| t.leading().iter().filter(|c| is_doc(c)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn errors(&self) -> Vec<ParseError> {
+        let mut errors = vec![];
+        self.collect_errors(&mut errors);
+        errors
+    }
+
+    fn collect_errors(&self, errors: &mut Vec<ParseError>) {
+        match self {
+            ParseData::Error {
+                expected,
+                span,
+                message,
+                ..
+            } => {
+                let error = ParseError::from(expected.clone(), span.clone());
+                errors.push(match message {
+                    Some(message) => error.with_message(message.to_string()),
+                    None => error,
+                })
+            }
+            ParseData::Token(t) => match t.provenance() {
+                Some(p) if p.is_inserted() => errors.push(
+                    ParseError::new(p.reason().to_string(), t.span().clone(), "Missing token")
+                        .with_provenance(p.clone()),
+                ),
+                _ => (),
+            },
+            ParseData::Nested(l) => l.iter().for_each(|d| d.collect_errors(errors)),
+            _ => (),
+        }
+    }
+}
+
+/// Size and shape of a finished parse, from [`Parse::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseStats {
+    pub nodes: usize,
+    pub depth: usize,
+    /// Tokens the parse consumed, out of the `total` in the input.
+    pub consumed: usize,
+    pub total: usize,
+    /// Error nodes left by recovery, or 1 for a failed parse.
+    pub errors: usize,
+    /// Rule attempts made, which grows far faster than the input on pathological backtracking.
+    pub steps: usize,
+}
+
+pub enum ParseResult<T> {
+    Ok(ParseData<T>),
+    Err(ParseError),
+    /// An optional construct was absent. Always consumes nothing; Sequence skips it,
+    /// Repeatable stops at it and Choice moves on to the next alternative.
+    None,
+}
+
+fn traced<T>(
+    ctx: &Context<T>,
+    pty: &Arc<str>,
+    offset: usize,
+    parse: impl FnOnce() -> Parse<T>,
+) -> Parse<T> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::trace_span!("parse", rule = &**pty, offset);
+    #[cfg(feature = "tracing")]
+    let _enter = span.enter();
+
+    if !ctx.budget().enter(pty, offset) {
+        let span = ctx.span_at(offset).unwrap_or_else(|| ctx.span_last());
+        return Parse::new(
+            pty.clone(),
+            ParseResult::Err(ctx.budget().error(span)),
+            offset,
+            offset,
+        );
+    }
+    if let Some(recorder) = ctx.recorder {
+        recorder.enter(pty, offset);
+    }
+    ctx.furthest.set(ctx.furthest.get().max(offset));
+    if let Some(path) = ctx.path() {
+        path.borrow_mut().push(pty.clone());
+    }
+
+    let mut parse = parse();
+    ctx.budget().exit();
+    if let Some(path) = ctx.path() {
+        let mut stack = path.borrow_mut();
+        let joined: Arc<str> = stack.join(".").into();
+        stack.pop();
+        if let ParseResult::Err(e) = &mut parse.data {
+            if e.path.is_none() {
+                e.path = Some(joined.clone());
+            }
+        }
+        parse.path = Some(joined);
+    }
+    if let (Some(progress), ParseResult::Ok(_)) = (&ctx.progress, &parse.data) {
+        progress.advance(parse.end_offset);
+    }
+    let outcome = Outcome::of(&parse.data);
+    if let ParseResult::Err(e) = &mut parse.data {
+        // offsets into a rewritten stream mean nothing to the caller, so the rule that parsed
+        // it in the caller's tokens sets it instead
+        if e.offset == u32::MAX && ctx.parent.is_none() {
+            e.offset = u32::try_from(offset).unwrap_or(u32::MAX);
+        }
+        ctx.fail(e);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(?outcome, end = parse.end_offset, "exit");
+
+    if let Some(recorder) = ctx.recorder {
+        recorder.exit(pty, parse.start_offset, parse.end_offset, outcome);
+    }
+    if let Some(coverage) = ctx.coverage {
+        coverage.rule(pty, outcome == Outcome::Ok);
+    }
+    parse
+}
+
+// Asks a tolerant rule's strategy what to do about `e`. Gives back the error node to put in the
+// tree and how many tokens it swallowed, or the error if there is no recovering from it.
+fn recover<T: Clone>(
+    strategy: Option<&Rc<dyn RecoveryStrategy<T>>>,
+    ctx: &Context<T>,
+    offset: usize,
+    e: ParseError,
+) -> Result<(ParseData<T>, usize), ParseError> {
+    let Some(strategy) = strategy else {
+        return Err(e);
+    };
+    if matches!(e.kind, ErrorKind::BudgetExceeded | ErrorKind::Cancelled) {
+        return Err(e);
+    }
+    let skip = match strategy.recover(&e, ctx, offset) {
+        Recovery::Skip(n) => n,
+        Recovery::Insert => match strategy.stand_in(&e) {
+            Some(ty) => return Ok((ParseData::Token(inserted(ctx, offset, ty, e)), 0)),
+            None => 0,
+        },
+        Recovery::Abort => return Err(e),
+    };
+    let skipped: Vec<_> = (offset..offset + skip)
+        .map_while(|i| ctx.get(i).cloned())
+        .collect();
+    if skip > 0 && skipped.is_empty() {
+        return Err(e);
+    }
+    let count = skipped.len();
+    let node = ParseData::Error {
+        expected: e.expected,
+        span: e.span,
+        skipped,
+        message: None,
+    };
+    Ok((node, count))
+}
+
+// A zero-width token just before the one at `offset`, or after the last one at the end.
+fn inserted<T>(ctx: &Context<T>, offset: usize, ty: T, e: ParseError) -> Token<T> {
+    let span = match ctx.span_at(offset) {
+        Some(s) => Span::new(s.ln(), s.cs(), s.cs().saturating_sub(1)),
+        None => {
+            let s = ctx.span_last();
+            Span::new(s.ln(), s.ce() + 1, s.ce())
+        }
+    };
+    let provenance = Provenance::inserted(span.clone(), e.expected);
+    Token::new(ty, span).with_provenance(Arc::new(provenance))
+}
+
+fn mismatch<T>(pty: &str, optional: bool, token: &Token<T>) -> ParseResult<T> {
+    if optional {
+        return ParseResult::None;
+    }
+    let error = ParseError::from(pty.to_string(), token.span().clone());
+    ParseResult::Err(match token.provenance() {
+        Some(p) => error.with_provenance(p.clone()),
+        None => error,
+    })
+}
+
+impl<T> ParseResult<T> {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ParseResult::Ok(_))
+    }
+
+    pub fn is_err(&self) -> bool {
+        matches!(self, ParseResult::Err(_))
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, ParseResult::None)
+    }
+
+    pub fn ok(self) -> Option<ParseData<T>> {
+        match self {
+            ParseResult::Ok(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn err(self) -> Option<ParseError> {
+        match self {
+            ParseResult::Err(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn map<U, F>(self, f: F) -> ParseResult<U>
+    where
+        F: FnOnce(ParseData<T>) -> ParseData<U>,
+    {
+        match self {
+            ParseResult::Ok(data) => ParseResult::Ok(f(data)),
+            ParseResult::Err(e) => ParseResult::Err(e),
+            ParseResult::None => ParseResult::None,
+        }
+    }
+
+    pub fn map_err<F>(self, f: F) -> ParseResult<T>
+    where
+        F: FnOnce(ParseError) -> ParseError,
+    {
+        match self {
+            ParseResult::Err(e) => ParseResult::Err(f(e)),
+            other => other,
+        }
+    }
+
+    pub fn into_result(self) -> Result<Option<ParseData<T>>, ParseError> {
+        match self {
+            ParseResult::Ok(data) => Ok(Some(data)),
+            ParseResult::Err(e) => Err(e),
+            ParseResult::None => Ok(None),
+        }
+    }
+}
+
+pub struct PrefixParse<T> {
+    pub data: ParseData<T>,
+    pub consumed: usize,
+    pub remaining: usize,
+}
+
+/// How a parser combines the parsers inside it, from [`Parser::structure`].
+pub enum Structure<'a, T> {
+    /// Nothing inside that can be looked at.
+    Opaque,
+    /// A single token, any that the first set allows.
+    Token,
+    /// Each in turn.
+    Sequence(Vec<&'a dyn Parser<T>>),
+    /// The first alternative that parses.
+    Choice(Vec<&'a dyn Parser<T>>),
+    /// One parser over and over.
+    Repeat(&'a dyn Parser<T>),
+    /// One parser, matched as it is.
+    Wraps(&'a dyn Parser<T>),
+    /// A handle to a parser that may be used in several places, such as a rule of a
+    /// [`RuleSet`](crate::rules::RuleSet).
+    Handle(&'a Shared<T>),
+    /// The rule of this name, referred to from inside itself or before it was built, so its
+    /// parser isn't at hand.
+    Reference(&'a str),
+}
+
+pub trait Parser<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T>;
+
+    /// The rule name this parser reports, for naming parsers built from it. `None` for parsers
+    /// without one.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Appends a random token sequence this parser accepts to `out`. Returns false when the
+    /// parser cannot invent input, as with predicates, which can only test a token. Lookaheads
+    /// produce nothing, so a following parser may still generate what they forbid.
+    fn generate(&self, _gen: &mut Generator, _out: &mut Vec<T>) -> bool {
+        false
+    }
+
+    /// Appends a template of what this parser matches to `out`, for
+    /// [`snippet`](crate::snippet::snippet). Returns false when the parser cannot describe
+    /// itself.
+    fn snippet(&self, _out: &mut Vec<SnippetPart<T>>) -> bool {
+        false
+    }
+
+    /// The tokens this parser can start with. Parsers that cannot say return
+    /// [`TokenSet::unknown`].
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::unknown()
+    }
+
+    /// For repetitions that can give items back, how many items to take at `offset`, in the
+    /// order an enclosing sequence should try them with [`parse_count`](Self::parse_count) until
+    /// what follows parses. `None` for parsers that match only one way.
+    fn counts(&self, _ctx: &Context<T>, _offset: usize) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Parses taking exactly `count` of the items [`counts`](Self::counts) offered.
+    fn parse_count(&self, ctx: &Context<T>, offset: usize, _count: usize) -> Parse<T> {
+        self.parse(ctx, offset)
+    }
+
+    /// How this parser combines the parsers inside it, for analyses that walk a grammar such
+    /// as [`worst_case`](crate::backtrack::worst_case). Parsers that don't say are
+    /// [`Structure::Opaque`].
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Opaque
+    }
+
+    fn shared(self) -> Shared<T>
+    where
+        Self: Sized + 'static,
+    {
+        Shared::new(Rc::new(self))
+    }
+
+    fn parse_partial(
+        &self,
+        tokens: &dyn TokenInput<T>,
+    ) -> Result<(ParseData<T>, usize), Vec<ParseError>> {
+        self.parse_partial_in(&Context::new(tokens))
+    }
+
+    fn parse_all(&self, tokens: &dyn TokenInput<T>) -> Result<ParseData<T>, Vec<ParseError>> {
+        self.parse_all_in(&Context::new(tokens))
+    }
+
+    /// Parses as much of `tokens` as possible and reports what is left, for grammars embedded
+    /// in a larger input. When the greedy parse fails partway, the input is cut before the
+    /// furthest token it got to and parsed once more, so unparseable input ends up in the
+    /// remainder rather than in an error. Errors are those of the greedy parse, returned when
+    /// the cut input doesn't parse either.
+    fn parse_prefix(&self, tokens: &dyn TokenInput<T>) -> Result<PrefixParse<T>, Vec<ParseError>> {
+        self.parse_prefix_in(&Context::new(tokens))
+    }
+
+    /// Like [`parse_prefix`](Parser::parse_prefix), over a configured context.
+    fn parse_prefix_in(&self, ctx: &Context<T>) -> Result<PrefixParse<T>, Vec<ParseError>> {
+        let total = ctx.len();
+        let errors = match self.parse_partial_in(ctx) {
+            Ok((data, end)) => {
+                return Ok(PrefixParse {
+                    data,
+                    consumed: end,
+                    remaining: total - end,
+                })
+            }
+            Err(e) => e,
+        };
+
+        let cut = ctx.furthest.get();
+        if ctx.abort_error().is_some() || cut >= total {
+            return Err(errors);
+        }
+        let truncated = Truncated::new(ctx.tokens, cut);
+        match self.parse_all_in(&ctx.over(&truncated)) {
+            Ok(data) => Ok(PrefixParse {
+                data,
+                consumed: cut,
+                remaining: total - cut,
+            }),
+            Err(_) => Err(errors),
+        }
+    }
+
+    /// Like [`parse_partial`](Parser::parse_partial), over a configured context.
+    fn parse_partial_in(&self, ctx: &Context<T>) -> Result<(ParseData<T>, usize), Vec<ParseError>> {
+        let parse = self.parse(ctx, ctx.skip(0));
+
+        if let Some(e) = ctx.abort_error() {
+            return Err(vec![e]);
+        }
+        if let Some(progress) = &ctx.progress {
+            progress.finish(parse.end_offset);
+        }
+        match parse.data {
+            ParseResult::Ok(data) => Ok((data, parse.end_offset)),
+            ParseResult::Err(e) => Err(vec![ctx.settle(e)]),
+            ParseResult::None => Ok((ParseData::Nested(vec![]), parse.end_offset)),
+        }
+    }
+
+    /// Like [`parse_all`](Parser::parse_all), over a configured context.
+    fn parse_all_in(&self, ctx: &Context<T>) -> Result<ParseData<T>, Vec<ParseError>> {
+        let (data, end) = self.parse_partial_in(ctx)?;
+
+        match ctx.span_at(ctx.skip(end)) {
+            Some(span) => Err(vec![ctx.settle(ParseError::new(
+                "end of input".to_string(),
+                span,
+                "Unexpected trailing input",
+            ))]),
+            None => Ok(data),
+        }
+    }
+}
+
+impl<T, P> Parser<T> for Box<P>
+where
+    P: Parser<T> + ?Sized,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        (**self).parse(ctx, offset)
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        (**self).generate(gen, out)
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        (**self).snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        (**self).first_set()
+    }
+
+    fn counts(&self, ctx: &Context<T>, offset: usize) -> Option<Vec<usize>> {
+        (**self).counts(ctx, offset)
+    }
+
+    fn parse_count(&self, ctx: &Context<T>, offset: usize, count: usize) -> Parse<T> {
+        (**self).parse_count(ctx, offset, count)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        (**self).structure()
+    }
+}
+
+pub struct Shared<T> {
+    inner: Rc<dyn Parser<T>>,
+}
+
+impl<T> Shared<T> {
+    pub const fn new(inner: Rc<dyn Parser<T>>) -> Self {
+        Self { inner }
+    }
+
+    /// Whether both are handles to the same parser.
+    pub fn same(&self, other: &Shared<T>) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// The parser this is a handle to.
+    pub fn parser(&self) -> &dyn Parser<T> {
+        &*self.inner
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<T> Parser<T> for Shared<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        self.inner.parse(ctx, offset)
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        self.inner.generate(gen, out)
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.inner.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        self.inner.first_set()
+    }
+
+    fn counts(&self, ctx: &Context<T>, offset: usize) -> Option<Vec<usize>> {
+        self.inner.counts(ctx, offset)
+    }
+
+    fn parse_count(&self, ctx: &Context<T>, offset: usize, count: usize) -> Parse<T> {
+        self.inner.parse_count(ctx, offset, count)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Handle(self)
+    }
+
+    fn shared(self) -> Shared<T> {
+        self
+    }
+}
+
+pub struct OfType<T> {
+    pty: Arc<str>,
+    optional: bool,
+    ty: T,
+}
+
+impl<T> OfType<T> {
+    pub fn from(pty: &str, optional: bool, ty: T) -> Self {
+        Self::new(pty.into(), optional, ty)
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, ty: T) -> Self {
+        Self { pty, optional, ty }
+    }
+}
+
+impl<T> Parser<T> for OfType<T>
+where
+    T: PartialEq + Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Token(self.ty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+
+            if &self.ty == token.ty() {
+                return Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(ParseData::Token(token.clone())),
+                    offset,
+                    offset + 1,
+                );
+            }
+            Parse::new(
+                self.pty.clone(),
+                mismatch(&self.pty, self.optional, token),
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        if !(self.optional && gen.coin()) {
+            out.push(self.ty.clone());
+        }
+        true
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Token(self.ty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::token(self.ty.clone()).nullable(self.optional)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Token
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct Predicate<T> {
+    pty: Arc<str>,
+    optional: bool,
+    predicate: fn(&T) -> bool,
+}
+
+impl<T> Predicate<T> {
+    pub fn from(pty: &str, optional: bool, predicate: fn(&T) -> bool) -> Self {
+        Self::new(pty.into(), optional, predicate)
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, predicate: fn(&T) -> bool) -> Self {
+        Self {
+            pty,
+            optional,
+            predicate,
+        }
+    }
+}
+
+impl<T> Parser<T> for Predicate<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+
+            if (self.predicate)(token.ty()) {
+                return Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(ParseData::Token(token.clone())),
+                    offset,
+                    offset + 1,
+                );
+            }
+            Parse::new(
+                self.pty.clone(),
+                mismatch(&self.pty, self.optional, token),
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Token
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct OfKind<T: HasKind> {
+    pty: Arc<str>,
+    optional: bool,
+    kind: T::Kind,
+}
+
+impl<T: HasKind> OfKind<T> {
+    pub fn from(pty: &str, optional: bool, kind: T::Kind) -> Self {
+        Self::new(pty.into(), optional, kind)
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, kind: T::Kind) -> Self {
+        Self {
+            pty,
+            optional,
+            kind,
+        }
+    }
+}
+
+impl<T> Parser<T> for OfKind<T>
+where
+    T: HasKind + Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+
+            if token.ty().kind() == self.kind {
+                return Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(ParseData::Token(token.clone())),
+                    offset,
+                    offset + 1,
+                );
+            }
+            Parse::new(
+                self.pty.clone(),
+                mismatch(&self.pty, self.optional, token),
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Token
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct TokenPredicate<T> {
+    pty: Arc<str>,
+    optional: bool,
+    predicate: fn(&Token<T>) -> bool,
+}
+
+impl<T> TokenPredicate<T> {
+    pub fn from(pty: &str, optional: bool, predicate: fn(&Token<T>) -> bool) -> Self {
+        Self::new(pty.into(), optional, predicate)
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, predicate: fn(&Token<T>) -> bool) -> Self {
+        Self {
+            pty,
+            optional,
+            predicate,
+        }
+    }
+}
+
+impl<T> Parser<T> for TokenPredicate<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+
+            if (self.predicate)(token) {
+                return Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(ParseData::Token(token.clone())),
+                    offset,
+                    offset + 1,
+                );
+            }
+            Parse::new(
+                self.pty.clone(),
+                mismatch(&self.pty, self.optional, token),
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Token
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// Matches an identifier token whose source text is `word` or one of its aliases, ignoring
+/// ASCII case, and puts it in the tree retyped as `canonical`. Needs a source attached to the
+/// context to read token text from.
+pub struct Keyword<T> {
+    pty: Arc<str>,
+    optional: bool,
+    ident: fn(&T) -> bool,
+    spellings: Vec<Arc<str>>,
+    canonical: T,
+}
+
+impl<T> Keyword<T> {
+    pub fn from(
+        pty: &str,
+        optional: bool,
+        ident: fn(&T) -> bool,
+        word: &str,
+        canonical: T,
+    ) -> Self {
+        Self::new(pty.into(), optional, ident, word.into(), canonical)
+    }
+
+    pub fn new(
+        pty: Arc<str>,
+        optional: bool,
+        ident: fn(&T) -> bool,
+        word: Arc<str>,
+        canonical: T,
+    ) -> Self {
+        Self {
+            pty,
+            optional,
+            ident,
+            spellings: vec![word],
+            canonical,
+        }
+    }
+
+    /// Accepts `alias` as another spelling, such as `elif` for `elsif`.
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.spellings.push(alias.into());
+        self
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.spellings.iter().any(|s| s.eq_ignore_ascii_case(text))
+    }
+}
+
+impl<T> Parser<T> for Keyword<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+
+            if (self.ident)(token.ty()) && ctx.token_text(offset).is_some_and(|t| self.matches(t)) {
+                return Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(ParseData::Token(
+                        token.clone().with_ty(self.canonical.clone()),
+                    )),
+                    offset,
+                    offset + 1,
+                );
+            }
+            Parse::new(
+                self.pty.clone(),
+                mismatch(&self.pty, self.optional, token),
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Token(self.canonical.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Token
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// Parses the inside of a composite token, such as a string with interpolated expressions.
+/// `expand` lexes the token's contents into tokens positioned relative to the token itself, with
+/// its first character at 1:1, or returns `None` for tokens it doesn't apply to. The spans are
+/// moved into place and `inner` must match the whole expansion; its tree becomes this parser's
+/// result. The expansion is parsed with this parse's settings, budget and rule path.
+pub struct Embedded<T> {
+    pty: Arc<str>,
+    optional: bool,
+    expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+    inner: Box<dyn Parser<T>>,
+}
+
+impl<T> Embedded<T> {
+    pub fn from(
+        pty: &str,
+        optional: bool,
+        expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+        inner: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self::new(pty.into(), optional, expand, inner)
+    }
+
+    pub const fn new(
+        pty: Arc<str>,
+        optional: bool,
+        expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+        inner: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self {
+            pty,
+            optional,
+            expand,
+            inner,
+        }
+    }
+}
+
+impl<T> Parser<T> for Embedded<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+            let Some(mut tokens) = (self.expand)(token) else {
+                return Parse::new(
+                    self.pty.clone(),
+                    mismatch(&self.pty, self.optional, token),
+                    offset,
+                    offset,
+                );
+            };
+
+            for t in &mut tokens {
+                t.relocate(token.span());
+            }
+            let data = match self.inner.parse_all_in(&ctx.over(&tokens)) {
+                Ok(data) => ParseResult::Ok(data),
+                // more input can't finish a token that is already whole
+                Err(mut errors) => match errors.swap_remove(0) {
+                    e if e.is_incomplete() => ParseResult::Err(e.with_kind(ErrorKind::Syntax)),
+                    e => ParseResult::Err(e),
+                },
+            };
+            let end = if data.is_ok() { offset + 1 } else { offset };
+            Parse::new(self.pty.clone(), data, offset, end)
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// Expands a macro invocation in place: `expand` gives the tokens a macro token stands for, or
+/// `None` for tokens that aren't macros. They are spliced into the stream in its stead, marked
+/// as coming from it, and `rest` carries on from the first of them through the tokens after
+/// the invocation. `rest` has to get past the whole expansion, since the rules outside can
+/// only resume in the original stream. The spliced stream is a view over the original, so only
+/// the expansion is copied, and is parsed in a context of its own, which keeps the source,
+/// recorder, coverage, skippable tokens and operators and shares the budget, cancellation and
+/// rule path.
+pub struct Splice<T> {
+    pty: Arc<str>,
+    optional: bool,
+    expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+    rest: Box<dyn Parser<T>>,
+}
+
+impl<T> Splice<T> {
+    pub fn from(
+        pty: &str,
+        optional: bool,
+        expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+        rest: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self::new(pty.into(), optional, expand, rest)
+    }
+
+    pub const fn new(
+        pty: Arc<str>,
+        optional: bool,
+        expand: fn(&Token<T>) -> Option<Vec<Token<T>>>,
+        rest: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self {
+            pty,
+            optional,
+            expand,
+            rest,
+        }
+    }
+}
+
+// Macros expanding into themselves would otherwise recurse until the stack runs out.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+impl<T> Parser<T> for Splice<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            ctx.expect(offset, || Expected::Rule(self.pty.clone()));
+            let token = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+            let Some(expansion) = (self.expand)(token) else {
+                return Parse::new(
+                    self.pty.clone(),
+                    mismatch(&self.pty, self.optional, token),
+                    offset,
+                    offset,
+                );
+            };
+
+            let mut provenance = Provenance::new(token.span().clone(), "expanded from macro");
+            if let Some(parent) = token.provenance() {
+                if parent.chain().count() >= MAX_EXPANSION_DEPTH {
+                    let error = ParseError::new(
+                        self.pty.to_string(),
+                        token.span().clone(),
+                        "Macro expansion too deep",
+                    );
+                    return Parse::new(self.pty.clone(), ParseResult::Err(error), offset, offset);
+                }
+                provenance = provenance.with_parent(parent.clone());
+            }
+            let provenance = Arc::new(provenance);
+
+            let len = expansion.len();
+            let expansion = expansion
+                .into_iter()
+                .map(|t| t.with_provenance(provenance.clone()))
+                .collect();
+            let spliced = Spliced::new(ctx.tokens, offset, expansion);
+
+            let parse = self.rest.parse(&ctx.over(&spliced), offset);
+            let end = offset + parse.consumed();
+            match parse.data {
+                ParseResult::Ok(data) if end >= offset + len => Parse::new(
+                    self.pty.clone(),
+                    ParseResult::Ok(data),
+                    offset,
+                    end + 1 - len,
+                ),
+                ParseResult::Ok(_) => {
+                    let span = spliced.span_at(end).unwrap_or_default();
+                    let error = ParseError::new(
+                        self.pty.to_string(),
+                        span,
+                        "Macro expansion only partly used",
+                    )
+                    .with_provenance(provenance);
+                    Parse::new(self.pty.clone(), ParseResult::Err(error), offset, offset)
+                }
+                data => Parse::new(self.pty.clone(), data, offset, offset),
+            }
+        })
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::rule(self.pty.clone()).nullable(self.optional)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct Sequence<T> {
+    pty: Arc<str>,
+    optional: bool,
+    inner: Vec<Box<dyn Parser<T>>>,
+    recovery: Option<Rc<dyn RecoveryStrategy<T>>>,
+}
+
+impl<T> Sequence<T> {
+    pub fn from(pty: &str, optional: bool, sequence: Vec<Box<dyn Parser<T>>>) -> Self {
+        Self::new(pty.into(), optional, sequence)
+    }
+
+    /// Makes the sequence tolerant: a failing item is handed to `strategy` instead of failing
+    /// the whole sequence.
+    pub fn with_recovery(mut self, strategy: Rc<dyn RecoveryStrategy<T>>) -> Self {
+        self.recovery = Some(strategy);
+        self
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
+        Self {
+            pty,
+            optional,
+            inner,
+            recovery: None,
+        }
+    }
+}
+
+impl<T> Sequence<T>
+where
+    T: Clone,
+{
+    // The items from `first` on, as a sequence of their own starting at `offset`.
+    fn items_from(
+        &self,
+        ctx: &Context<T>,
+        offset: usize,
+        first: usize,
+        optional: bool,
+    ) -> Parse<T> {
+        let checkpoint = ctx.checkpoint(offset);
+        let mut offs = offset;
+        let mut expr = ctx.children();
+        let mut prev: Option<Arc<str>> = None;
+
+        let mut i = first;
+        while let Some(item) = self.inner.get(i) {
+            let at = ctx.skip(offs);
+            if let Some(counts) = item.counts(ctx, at) {
+                if let Some((nodes, end)) = self.give_back(ctx, offs, i, &counts) {
+                    expr.extend(nodes);
+                    offs = end;
+                    break;
+                }
+            }
+            let parse = item.parse(ctx, at);
+            let consumed = parse.consumed();
+
+            match parse.data {
+                ParseResult::Ok(d) => {
+                    if consumed > 0 {
+                        offs = at + consumed;
+                    }
+                    expr.push(d);
+                    prev = Some(parse.type_parsed);
+                }
+                ParseResult::Err(mut e) => {
+                    if e.after.is_none() {
+                        e.after = prev.as_deref().map(|p| Arc::new(p.into()));
+                    }
+                    match recover(self.recovery.as_ref(), ctx, at, e) {
+                        Ok((node, 0)) => expr.push(node),
+                        Ok((node, skipped)) => {
+                            offs = at + skipped;
+                            expr.push(node);
+                            continue;
+                        }
+                        Err(e) => {
+                            ctx.discard(expr);
+                            ctx.rewind(&checkpoint);
+                            if optional {
+                                return Parse::new(
+                                    self.pty.clone(),
+                                    ParseResult::None,
+                                    offset,
+                                    offset,
+                                );
+                            }
+                            return Parse::new(
+                                self.pty.clone(),
+                                ParseResult::Err(e),
+                                offset,
+                                offset,
+                            );
+                        }
+                    }
+                }
+                _ => (),
+            }
+            i += 1;
+        }
+        Parse::new(
+            self.pty.clone(),
+            ParseResult::Ok(ParseData::Nested(expr)),
+            offset,
+            offs,
+        )
+    }
+
+    // Tries the counts the repetition at item `i` offers until the items after it parse without
+    // errors, giving the nodes from the repetition on and where they end. `None` sends the
+    // sequence down its usual path, so failures read as they would without giving back.
+    fn give_back(
+        &self,
+        ctx: &Context<T>,
+        offs: usize,
+        i: usize,
+        counts: &[usize],
+    ) -> Option<(Vec<ParseData<T>>, usize)> {
+        let at = ctx.skip(offs);
+        let checkpoint = ctx.checkpoint(at);
+        for count in counts {
+            let parse = self.inner[i].parse_count(ctx, at, *count);
+            let end = match parse.consumed() {
+                0 => offs,
+                consumed => at + consumed,
+            };
+            let head = match parse.data {
+                ParseResult::Ok(d) => Some(d),
+                ParseResult::None => None,
+                ParseResult::Err(_) => {
+                    ctx.rewind(&checkpoint);
+                    continue;
+                }
+            };
+            let rest = self.items_from(ctx, end, i + 1, false);
+            let rest_end = end + rest.consumed();
+            match rest.data {
+                ParseResult::Ok(ParseData::Nested(tail))
+                    if !tail.iter().any(ParseData::has_errors) =>
+                {
+                    let mut nodes: Vec<ParseData<T>> = head.into_iter().collect();
+                    nodes.extend(tail);
+                    return Some((nodes, rest_end));
+                }
+                _ => {
+                    ctx.rewind(&checkpoint);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T> Parser<T> for Sequence<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            self.items_from(ctx, offset, 0, self.optional)
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        if self.optional && (gen.exhausted() || gen.coin()) {
+            return true;
+        }
+        let len = out.len();
+        let ok = gen.nested(|gen| self.inner.iter().all(|item| item.generate(gen, out)));
+        if !ok {
+            out.truncate(len);
+        }
+        ok || self.optional
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.inner.iter().all(|item| item.snippet(out))
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let mut set = TokenSet::empty();
+        for item in &self.inner {
+            let first = item.first_set();
+            let nullable = first.is_nullable();
+            set.extend(first);
+            if !nullable {
+                return set.nullable(self.optional);
+            }
+        }
+        set
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Sequence(self.inner.iter().map(|p| &**p as &dyn Parser<T>).collect())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// How many items a [`Repeatable`] takes when the parser after it in a sequence fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Greediness {
+    /// As many as it can, never giving any back.
+    #[default]
+    Possessive,
+    /// As many as it can, giving them back one at a time until the rest of the sequence
+    /// parses: tokens up to the last `end`.
+    Greedy,
+    /// As few as it can, taking one more at a time until the rest of the sequence parses:
+    /// tokens up to the first `end`.
+    Lazy,
+}
+
+pub struct Repeatable<T> {
+    pty: Arc<str>,
+    optional: bool,
+    inner: Box<dyn Parser<T>>,
+    recovery: Option<Rc<dyn RecoveryStrategy<T>>>,
+    greediness: Greediness,
+    // the parse `counts` made to see how many items there are, and where, for the call that
+    // follows it to hand back rather than parse again
+    probed: RefCell<Option<(usize, Parse<T>)>>,
+}
+
+impl<T> Repeatable<T> {
+    pub fn from(pty: &str, optional: bool, repeatable: Box<dyn Parser<T>>) -> Self {
+        Self::new(pty.into(), optional, repeatable)
+    }
+
+    /// Makes the repetition tolerant: a failing item is handed to `strategy` instead of ending
+    /// the repetition.
+    pub fn with_recovery(mut self, strategy: Rc<dyn RecoveryStrategy<T>>) -> Self {
+        self.recovery = Some(strategy);
+        self
+    }
+
+    /// Lets an enclosing sequence choose how many items this takes; possessive unless set.
+    /// Giving items back reparses the repetition for each count tried.
+    pub fn with_greediness(mut self, greediness: Greediness) -> Self {
+        self.greediness = greediness;
+        self
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, inner: Box<dyn Parser<T>>) -> Self {
+        Self {
+            pty,
+            optional,
+            inner,
+            recovery: None,
+            greediness: Greediness::Possessive,
+            probed: RefCell::new(None),
+        }
+    }
+}
+
+impl<T> Repeatable<T>
+where
+    T: Clone,
+{
+    // The probe from `counts` if it was made at `offset` and took `count` items, or any number
+    // for `None`. It is dropped either way, being only good for the call straight after.
+    fn reprobe(&self, offset: usize, count: Option<usize>) -> Option<Parse<T>> {
+        let (at, parse) = self.probed.borrow_mut().take()?;
+        let items = match &parse.data {
+            ParseResult::Ok(ParseData::Nested(items)) => items.len(),
+            _ => 0,
+        };
+        (at == offset && count.is_none_or(|count| count == items)).then_some(parse)
+    }
+
+    // Parses up to `limit` items, or as many as there are.
+    fn parse_upto(&self, ctx: &Context<T>, offset: usize, limit: Option<usize>) -> Parse<T> {
+        if let Some(parse) = self.reprobe(offset, limit) {
+            return parse;
+        }
+        traced(ctx, &self.pty, offset, || {
+            let mut expr = ctx.children();
+            let mut err = None;
+
+            let mut offs = offset;
+
+            while limit.is_none_or(|limit| expr.len() < limit) {
+                let at = ctx.skip(offs);
+                // an item that doesn't stand takes back what it declared
+                let checkpoint = ctx.checkpoint(at);
+                let parse = self.inner.parse(ctx, at);
+                let consumed = parse.consumed();
+
+                match parse.data {
+                    ParseResult::Ok(data) => {
+                        expr.push(data);
+                        if consumed == 0 {
+                            break;
+                        }
+                        offs = at + consumed;
+                    }
+                    ParseResult::Err(e) => match recover(self.recovery.as_ref(), ctx, at, e) {
+                        Ok((_, 0)) => {
+                            ctx.rewind(&checkpoint);
+                            break;
+                        }
+                        Ok((node, skipped)) => {
+                            offs = at + skipped;
+                            expr.push(node);
+                        }
+                        Err(e) => {
+                            ctx.rewind(&checkpoint);
+                            err = Some(e);
+                            break;
+                        }
+                    },
+                    ParseResult::None => {
+                        ctx.rewind(&checkpoint);
+                        break;
+                    }
+                }
+            }
+
+            let data = if expr.is_empty() {
+                ctx.discard(expr);
+                if self.optional {
+                    ParseResult::None
+                } else if let Some(err) = err {
+                    ParseResult::Err(err)
+                } else {
+                    ParseResult::Err(ParseError::from(
+                        self.pty.to_string(),
+                        ctx.span_at(offs).unwrap_or_else(|| ctx.span_last()),
+                    ))
+                }
+            } else {
+                ParseResult::Ok(ParseData::Nested(expr))
+            };
+            Parse::new(self.pty.clone(), data, offset, offs)
+        })
+    }
+}
+
+impl<T> Parser<T> for Repeatable<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        self.parse_upto(ctx, offset, None)
+    }
+
+    fn counts(&self, ctx: &Context<T>, offset: usize) -> Option<Vec<usize>> {
+        if self.greediness == Greediness::Possessive {
+            return None;
+        }
+        let checkpoint = ctx.checkpoint(offset);
+        let parse = self.parse(ctx, offset);
+        let most = match &parse.data {
+            ParseResult::Ok(ParseData::Nested(items)) => items.len(),
+            _ => 0,
+        };
+        let failed = matches!(parse.data, ParseResult::Err(_));
+        // the probe is the attempt the sequence makes first, unless its items declared
+        // operators, which parsing the count again has to declare afresh
+        if ctx.checkpoint(offset).operators == checkpoint.operators {
+            *self.probed.borrow_mut() = Some((offset, parse));
+        }
+        ctx.rewind(&checkpoint);
+        if failed {
+            return None;
+        }
+        let fewest = if self.optional { 0 } else { 1 };
+        Some(match self.greediness {
+            Greediness::Lazy => (fewest..=most).collect(),
+            _ => (fewest..=most).rev().collect(),
+        })
+    }
+
+    fn parse_count(&self, ctx: &Context<T>, offset: usize, count: usize) -> Parse<T> {
+        self.parse_upto(ctx, offset, Some(count))
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        let count = gen.repeat_count(if self.optional { 0 } else { 1 });
+        let len = out.len();
+        let ok = gen.nested(|gen| (0..count).all(|_| self.inner.generate(gen, out)));
+        if !ok {
+            out.truncate(len);
+        }
+        ok || self.optional
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.optional || self.inner.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let first = self.inner.first_set();
+        let nullable = self.optional || first.is_nullable();
+        first.nullable(nullable)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Repeat(&*self.inner)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct Recover<T> {
+    pty: Arc<str>,
+    inner: Box<dyn Parser<T>>,
+    sync: fn(&T) -> bool,
+}
+
+impl<T> Recover<T> {
+    pub fn from(pty: &str, inner: Box<dyn Parser<T>>, sync: fn(&T) -> bool) -> Self {
+        Self::new(pty.into(), inner, sync)
+    }
+
+    pub const fn new(pty: Arc<str>, inner: Box<dyn Parser<T>>, sync: fn(&T) -> bool) -> Self {
+        Self { pty, inner, sync }
+    }
+}
+
+impl<T> Parser<T> for Recover<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let parse = self.inner.parse(ctx, offset);
+
+            let ParseResult::Err(e) = parse.data else {
+                return parse;
+            };
+
+            let mut offs = offset;
+            let mut skipped = vec![];
+            while let Some(token) = ctx.get(offs) {
+                if (self.sync)(token.ty()) {
+                    break;
+                }
+                skipped.push(token.clone());
+                offs += 1;
+            }
+
+            if skipped.is_empty() {
+                return Parse::new(self.pty.clone(), ParseResult::Err(e), offset, offset);
+            }
+
+            Parse::new(
+                self.pty.clone(),
+                ParseResult::Ok(ParseData::Error {
+                    expected: e.expected,
+                    span: e.span,
+                    skipped,
+                    message: None,
+                }),
+                offset,
+                offs,
+            )
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        self.inner.generate(gen, out)
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.inner.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        // on error any token up to the sync point is swallowed
+        self.inner.first_set().opened()
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// A yacc-style error rule, usually the last alternative of a Choice: matches everything up to
+/// the next token `sync` accepts, or the end of input, and puts it in the tree as an error node
+/// carrying the author's `message`. It fails without consuming anything when already at a sync
+/// token, so a repetition around it always makes progress.
+pub struct ErrorProduction<T> {
+    pty: Arc<str>,
+    sync: fn(&T) -> bool,
+    message: Arc<str>,
+}
+
+impl<T> ErrorProduction<T> {
+    pub fn from(pty: &str, sync: fn(&T) -> bool, message: &str) -> Self {
+        Self::new(pty.into(), sync, message.into())
+    }
+
+    pub const fn new(pty: Arc<str>, sync: fn(&T) -> bool, message: Arc<str>) -> Self {
+        Self { pty, sync, message }
+    }
+}
+
+impl<T> Parser<T> for ErrorProduction<T>
+where
+    T: Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let mut offs = offset;
+            let mut skipped = vec![];
+            while let Some(token) = ctx.get(offs) {
+                if (self.sync)(token.ty()) {
+                    break;
+                }
+                skipped.push(token.clone());
+                offs += 1;
+            }
+
+            let Some(first) = skipped.first() else {
+                let span = ctx.span_at(offset).unwrap_or_else(|| ctx.span_last());
+                let error = ParseError::from(self.pty.to_string(), span);
+                return Parse::new(self.pty.clone(), ParseResult::Err(error), offset, offset);
+            };
+            let span = first.span().clone();
+            Parse::new(
+                self.pty.clone(),
+                ParseResult::Ok(ParseData::Error {
+                    expected: self.pty.to_string(),
+                    span,
+                    skipped,
+                    message: Some(self.message.clone()),
+                }),
+                offset,
+                offs,
+            )
+        })
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// Repeated key-value entries, as in a config block. `key` names each parsed entry (or returns
+/// `None` for entries without one) and a key given twice fails the parse at the second entry,
+/// with the message pointing back at the first.
+pub struct KeyedSet<T> {
+    pty: Arc<str>,
+    optional: bool,
+    entry: Box<dyn Parser<T>>,
+    key: fn(&ParseData<T>) -> Option<String>,
+}
+
+impl<T> KeyedSet<T> {
+    pub fn from(
+        pty: &str,
+        optional: bool,
+        entry: Box<dyn Parser<T>>,
+        key: fn(&ParseData<T>) -> Option<String>,
+    ) -> Self {
+        Self::new(pty.into(), optional, entry, key)
+    }
+
+    /// The entries of a tree this set parsed, by key.
+    pub fn map<'d>(&self, data: &'d ParseData<T>) -> BTreeMap<String, &'d ParseData<T>> {
+        let ParseData::Nested(entries) = data else {
+            return BTreeMap::new();
+        };
+        entries
+            .iter()
+            .filter_map(|e| Some(((self.key)(e)?, e)))
+            .collect()
+    }
+
+    pub const fn new(
+        pty: Arc<str>,
+        optional: bool,
+        entry: Box<dyn Parser<T>>,
+        key: fn(&ParseData<T>) -> Option<String>,
+    ) -> Self {
+        Self {
+            pty,
+            optional,
+            entry,
+            key,
+        }
+    }
+}
+
+impl<T> Parser<T> for KeyedSet<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let mut entries = vec![];
+            let mut seen: BTreeMap<String, Span> = BTreeMap::new();
+            let mut err = None;
+            let mut offs = offset;
+
+            loop {
+                let at = ctx.skip(offs);
+                let parse = self.entry.parse(ctx, at);
+                let consumed = parse.consumed();
+                let data = match parse.data {
+                    ParseResult::Ok(data) => data,
+                    ParseResult::Err(e) => {
+                        err = Some(e);
+                        break;
+                    }
+                    ParseResult::None => break,
+                };
+
+                let span = first_span(&data)
+                    .cloned()
+                    .unwrap_or_else(|| ctx.span_last());
+                if let Some(key) = (self.key)(&data) {
+                    if let Some(first) = seen.get(&key) {
+                        let message = format!("Duplicate key {key}, first given at {first}");
+                        let e = ParseError::from(self.pty.to_string(), span).with_message(message);
+                        return Parse::new(self.pty.clone(), ParseResult::Err(e), offset, offset);
+                    }
+                    seen.insert(key, span);
+                }
+                entries.push(data);
+                if consumed == 0 {
+                    break;
+                }
+                offs = at + consumed;
+            }
+
+            let data = match err {
+                _ if !entries.is_empty() => ParseResult::Ok(ParseData::Nested(entries)),
+                _ if self.optional => ParseResult::None,
+                Some(err) => ParseResult::Err(err),
+                None => ParseResult::Err(ParseError::from(
+                    self.pty.to_string(),
+                    ctx.span_at(offs).unwrap_or_else(|| ctx.span_last()),
+                )),
+            };
+            Parse::new(self.pty.clone(), data, offset, offs)
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        // generated keys are rarely unique, so only ever emit one entry
+        if self.optional && gen.coin() {
+            return true;
+        }
+        let len = out.len();
+        let ok = self.entry.generate(gen, out);
+        if !ok {
+            out.truncate(len);
+        }
+        ok || self.optional
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.optional || self.entry.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let first = self.entry.first_set();
+        let nullable = self.optional || first.is_nullable();
+        first.nullable(nullable)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct Balanced<T> {
+    pty: Arc<str>,
+    optional: bool,
+    pairs: Vec<(T, T)>,
+    max_depth: Option<usize>,
+}
+
+impl<T> Balanced<T> {
+    pub fn from(pty: &str, optional: bool, pairs: Vec<(T, T)>) -> Self {
+        Self::new(pty.into(), optional, pairs)
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, pairs: Vec<(T, T)>) -> Self {
+        Self {
+            pty,
+            optional,
+            pairs,
+            max_depth: None,
+        }
+    }
+}
+
+impl<T: PartialEq> Balanced<T> {
+    fn opener(&self, ty: &T) -> Option<usize> {
+        self.pairs.iter().position(|(open, _)| open == ty)
+    }
+
+    fn closer(&self, ty: &T) -> Option<usize> {
+        self.pairs.iter().position(|(_, close)| close == ty)
+    }
+}
+
+impl<T> Parser<T> for Balanced<T>
+where
+    T: PartialEq + Clone + Display,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let fail = |span: Span, message: String| {
+                let e = ParseError::from(self.pty.to_string(), span).with_message(message);
+                Parse::new(self.pty.clone(), ParseResult::Err(e), offset, offset)
+            };
+            let eof = |parse: Parse<T>| Parse {
+                data: parse
+                    .data
+                    .map_err(|e| e.with_kind(ErrorKind::UnexpectedEof)),
+                ..parse
+            };
+
+            let first = match ctx.get_required(&self.pty, offset, self.optional) {
+                Ok(t) => t,
+                Err(e) => return Parse::new(self.pty.clone(), e, offset, offset),
+            };
+            let Some(pair) = self.opener(first.ty()) else {
+                if self.closer(first.ty()).is_some() && !self.optional {
+                    return fail(first.span().clone(), format!("Unexpected {}", first.ty()));
+                }
+                let data = mismatch(&self.pty, self.optional, first);
+                return Parse::new(self.pty.clone(), data, offset, offset);
+            };
+            if self.max_depth == Some(0) {
+                return fail(first.span().clone(), "Delimiters nested too deeply".into());
+            }
+
+            // indices into `pairs` plus the token that opened each level
+            let mut open: Vec<(usize, &Token<T>)> = vec![(pair, first)];
+            let mut tokens = vec![first.clone()];
+            let mut offs = offset + 1;
+
+            while !open.is_empty() {
+                let Some(token) = ctx.get(offs) else {
+                    let (_, opener) = open.last().expect("at least one level is open");
+                    return eof(fail(
+                        opener.span().clone(),
+                        format!("Unclosed {} opened at {}", opener.ty(), opener.span()),
+                    ));
+                };
+
+                // the innermost level's closer first, so a pair like `"` and `"` closes
+                let (pair, opener) = *open.last().expect("at least one level is open");
+                if self.pairs[pair].1 == *token.ty() {
+                    open.pop();
+                } else if let Some(pair) = self.opener(token.ty()) {
+                    if self.max_depth.is_some_and(|max| open.len() >= max) {
+                        return fail(token.span().clone(), "Delimiters nested too deeply".into());
+                    }
+                    open.push((pair, token));
+                } else if self.closer(token.ty()).is_some() {
+                    return fail(
+                        token.span().clone(),
+                        format!(
+                            "Unexpected {}, expected {} to close {} opened at {}",
+                            token.ty(),
+                            self.pairs[pair].1,
+                            opener.ty(),
+                            opener.span()
+                        ),
+                    );
+                }
+
+                tokens.push(token.clone());
+                offs += 1;
+            }
+
+            Parse::new(
+                self.pty.clone(),
+                ParseResult::Ok(ParseData::TokenList(tokens)),
+                offset,
+                offs,
+            )
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        if self.pairs.is_empty() {
+            return self.optional;
+        }
+        let (open, close) = &self.pairs[gen.below(self.pairs.len())];
+        out.extend([open.clone(), close.clone()]);
+        true
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if !self.optional {
+            out.push(SnippetPart::Placeholder(self.pty.clone()));
+        }
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let mut set = TokenSet::empty().nullable(self.optional);
+        for (open, _) in &self.pairs {
+            set.extend(TokenSet::token(open.clone()));
+        }
+        set
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+pub struct Not<T> {
+    pty: Arc<str>,
+    optional: bool,
+    inner: Box<dyn Parser<T>>,
+}
+
+impl<T> Not<T> {
+    pub fn from(pty: &str, optional: bool, inner: Box<dyn Parser<T>>) -> Self {
+        Self::new(pty.into(), optional, inner)
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, inner: Box<dyn Parser<T>>) -> Self {
+        Self {
+            pty,
+            optional,
+            inner,
+        }
+    }
+}
+
+impl<T> Parser<T> for Not<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let checkpoint = ctx.checkpoint(offset);
+            // more input only helps `inner` match, so what ran out in there can't finish it
+            let ran_out = ctx.ran_out.take();
+            let parse = self.inner.parse(ctx, offset);
+            ctx.rewind(&checkpoint);
+            *ctx.ran_out.borrow_mut() = ran_out;
+
+            Parse::new(
+                self.pty.clone(),
+                match parse.data {
+                    ParseResult::Ok(data) => {
+                        if self.optional {
+                            ParseResult::None
+                        } else {
+                            let span = first_span(&data)
+                                .cloned()
+                                .or_else(|| ctx.span_at(offset))
+                                .unwrap_or_else(|| ctx.span_last());
+                            ParseResult::Err(ParseError::from(self.pty.to_string(), span))
+                        }
+                    }
+                    ParseResult::Err(_) | ParseResult::None => ParseResult::None,
+                },
+                offset,
+                offset,
+            )
+        })
+    }
+
+    fn generate(&self, _gen: &mut Generator, _out: &mut Vec<T>) -> bool {
+        true
+    }
+
+    fn snippet(&self, _out: &mut Vec<SnippetPart<T>>) -> bool {
+        true
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::empty()
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// One or more `item`s with a `sep` between each pair. Only the items go into the tree, so a
+/// comma-separated list comes out as a flat list of its elements. A trailing separator is left
+/// unconsumed.
+pub struct Separated<T> {
+    pty: Arc<str>,
+    optional: bool,
+    item: Box<dyn Parser<T>>,
+    sep: Box<dyn Parser<T>>,
+}
+
+impl<T> Separated<T> {
+    pub fn from(
+        pty: &str,
+        optional: bool,
+        item: Box<dyn Parser<T>>,
+        sep: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self::new(pty.into(), optional, item, sep)
+    }
+
+    pub const fn new(
+        pty: Arc<str>,
+        optional: bool,
+        item: Box<dyn Parser<T>>,
+        sep: Box<dyn Parser<T>>,
+    ) -> Self {
+        Self {
+            pty,
+            optional,
+            item,
+            sep,
+        }
+    }
+}
+
+impl<T> Parser<T> for Separated<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            let first = self.item.parse(ctx, offset);
+            let mut offs = offset + first.consumed();
+            let mut items = match first.data {
+                ParseResult::Ok(data) => vec![data],
+                ParseResult::Err(e) if !self.optional => {
+                    return Parse::new(self.pty.clone(), ParseResult::Err(e), offset, offset);
+                }
+                _ if self.optional => {
+                    return Parse::new(self.pty.clone(), ParseResult::None, offset, offset);
+                }
+                _ => {
+                    let e = ParseError::from(
+                        self.pty.to_string(),
+                        ctx.span_at(offset).unwrap_or_else(|| ctx.span_last()),
+                    );
+                    return Parse::new(self.pty.clone(), ParseResult::Err(e), offset, offset);
+                }
+            };
+
+            loop {
+                let at = ctx.skip(offs);
+                let sep = self.sep.parse(ctx, at);
+                if !sep.data.is_ok() {
+                    break;
+                }
+                let item_at = ctx.skip(at + sep.consumed());
+                let item = self.item.parse(ctx, item_at);
+                let end = item_at + item.consumed();
+                let ParseResult::Ok(data) = item.data else {
+                    break;
+                };
+                items.push(data);
+                if end == offs {
+                    break;
+                }
+                offs = end;
+            }
+            Parse::new(
+                self.pty.clone(),
+                ParseResult::Ok(ParseData::Nested(items)),
+                offset,
+                offs,
+            )
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        if self.optional && gen.coin() {
+            return true;
+        }
+        let count = gen.repeat_count(1);
+        let len = out.len();
+        let ok = gen.nested(|gen| {
+            (0..count)
+                .all(|i| (i == 0 || self.sep.generate(gen, out)) && self.item.generate(gen, out))
+        });
+        if !ok {
+            out.truncate(len);
+        }
+        ok || self.optional
+    }
+
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.optional || self.item.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let first = self.item.first_set();
+        let nullable = self.optional || first.is_nullable();
+        first.nullable(nullable)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
+}
+
+/// A parser built the first time it is used, so a rule can refer to itself, or to a rule that
+/// refers back to it, through a constructor function.
+pub struct Deferred<T> {
+    pty: Arc<str>,
+    rule: Rule<T>,
+}
+
+pub(crate) type Slot<T> = Rc<OnceCell<Box<dyn Parser<T>>>>;
+
+// Where a Deferred finds its parser: built from the constructor on first use, or held in a slot
+// shared with the other references to the same rule. References from inside the rule's own
+// parser hold the slot weakly, or it would own itself and never be freed.
+enum Rule<T> {
+    Build(fn() -> Box<dyn Parser<T>>, OnceCell<Box<dyn Parser<T>>>),
+    Shared(Slot<T>),
+    Within(Weak<OnceCell<Box<dyn Parser<T>>>>),
+}
+
+impl<T> Deferred<T> {
+    pub fn from(pty: &str, build: fn() -> Box<dyn Parser<T>>) -> Self {
+        Self::new(pty.into(), build)
+    }
+
+    pub const fn new(pty: Arc<str>, build: fn() -> Box<dyn Parser<T>>) -> Self {
+        Self {
+            pty,
+            rule: Rule::Build(build, OnceCell::new()),
+        }
+    }
+
+    pub(crate) const fn shared(pty: Arc<str>, slot: Slot<T>) -> Self {
+        Self {
+            pty,
+            rule: Rule::Shared(slot),
+        }
     }
 
-    pub const fn new(expected: String, span: Span, message: &'static str) -> Self {
+    pub(crate) fn within(pty: Arc<str>, slot: &Slot<T>) -> Self {
         Self {
-            expected,
-            span,
-            message,
+            pty,
+            rule: Rule::Within(Rc::downgrade(slot)),
         }
     }
-}
-
-pub struct Parse<'t, T> {
-    type_parsed: &'t str,
-    data: ParseResult<T>,
-    start_offset: usize,
-    end_offset: usize,
-}
 
-impl<'t, T> Parse<'t, T> {
-    pub fn size(&self) -> usize {
-        self.end_offset - self.start_offset
+    fn inner<R>(&self, f: impl FnOnce(&dyn Parser<T>) -> R) -> R {
+        match &self.rule {
+            Rule::Build(build, built) => f(&**built.get_or_init(build)),
+            Rule::Shared(slot) => f(&**slot.get().expect("shared rule used while being built")),
+            Rule::Within(slot) => {
+                let slot = slot
+                    .upgrade()
+                    .expect("rule used after its grammar was dropped");
+                f(&**slot.get().expect("shared rule used while being built"))
+            }
+        }
     }
+}
 
-    pub fn type_parsed(&self) -> &'t str {
-        self.type_parsed
+impl<T> Parser<T> for Deferred<T> {
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        self.inner(|inner| inner.parse(ctx, offset))
     }
 
-    pub fn data(&self) -> &ParseResult<T> {
-        &self.data
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        self.inner(|inner| inner.generate(gen, out))
     }
 
-    pub fn start_offset(&self) -> usize {
-        self.start_offset
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        // expanding a recursive rule would never end
+        out.push(SnippetPart::Placeholder(self.pty.clone()));
+        true
     }
 
-    pub fn end_offset(&self) -> usize {
-        self.end_offset
+    fn first_set(&self) -> TokenSet<T> {
+        // building the inner parser here would never end for a recursive rule
+        TokenSet::rule(self.pty.clone())
     }
 
-    pub const fn new(
-        type_parsed: &'t str,
-        data: ParseResult<T>,
-        start_offset: usize,
-        end_offset: usize,
-    ) -> Self {
-        Self {
-            type_parsed,
-            data,
-            start_offset,
-            end_offset,
+    fn structure(&self) -> Structure<'_, T> {
+        // a reference back into the rule being walked would only walk it again
+        match &self.rule {
+            Rule::Build(build, built) => Structure::Wraps(&**built.get_or_init(build)),
+            Rule::Shared(slot) => slot
+                .get()
+                .map_or(Structure::Opaque, |p| Structure::Wraps(&**p)),
+            Rule::Within(_) => Structure::Reference(&self.pty),
         }
     }
-}
 
-pub enum ParseData<T> {
-    Nested(Vec<ParseData<T>>),
-    TokenList(Vec<Token<T>>),
-    Token(Token<T>),
-}
+    fn counts(&self, ctx: &Context<T>, offset: usize) -> Option<Vec<usize>> {
+        self.inner(|inner| inner.counts(ctx, offset))
+    }
 
-pub enum ParseResult<T> {
-    Ok(ParseData<T>),
-    Err(ParseError),
-    None,
-}
+    fn parse_count(&self, ctx: &Context<T>, offset: usize, count: usize) -> Parse<T> {
+        self.inner(|inner| inner.parse_count(ctx, offset, count))
+    }
 
-pub trait Parser<T> {
-    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T>;
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
+    }
 }
 
-pub struct OfType<T> {
-    pty: String,
-    optional: bool,
-    ty: T,
+/// Parses operands joined by the operators in the context's operator table, by precedence
+/// climbing. A prefix operator gives `[op, operand]` and an infix one `[lhs, op, rhs]`. Without
+/// a table it parses a lone operand.
+pub struct Expression<T> {
+    pty: Arc<str>,
+    operand: Box<dyn Parser<T>>,
 }
 
-impl<T> OfType<T> {
-    pub fn from(pty: &str, optional: bool, ty: T) -> Self {
-        Self::new(pty.to_string(), optional, ty)
+impl<T> Expression<T> {
+    pub fn from(pty: &str, operand: Box<dyn Parser<T>>) -> Self {
+        Self::new(pty.into(), operand)
     }
 
-    pub const fn new(pty: String, optional: bool, ty: T) -> Self {
-        Self { pty, optional, ty }
+    pub const fn new(pty: Arc<str>, operand: Box<dyn Parser<T>>) -> Self {
+        Self { pty, operand }
     }
 }
 
-impl<T> Parser<T> for OfType<T>
+impl<T> Expression<T>
 where
     T: PartialEq + Clone,
 {
-    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        let token = match ctx.get_required(&self.pty, offset, self.optional) {
-            Ok(t) => t,
-            Err(e) => return Parse::new(&self.pty, e, offset, offset),
+    // Left-associative operators bind their right side one notch tighter than their left, so
+    // an operator of the same precedence there ends the operand; right-associative ones the
+    // other way round.
+    fn climb(
+        &self,
+        ctx: &Context<T>,
+        offset: usize,
+        min: u32,
+    ) -> Result<(ParseData<T>, usize), ParseResult<T>> {
+        let table = ctx.operators;
+        let prefix = ctx
+            .get(offset)
+            .and_then(|t| Some((t, table?.prefix(t.ty())?)));
+
+        let (mut lhs, mut offs) = match prefix {
+            Some((op, precedence)) => {
+                let min = 2 * u32::from(precedence) + 1;
+                let (rhs, end) = self.operand_after(ctx, offset + 1, min)?;
+                (
+                    ParseData::Nested(vec![ParseData::Token(op.clone()), rhs]),
+                    end,
+                )
+            }
+            None => {
+                let parse = self.operand.parse(ctx, offset);
+                let end = offset + parse.consumed();
+                match parse.data {
+                    ParseResult::Ok(data) => (data, end),
+                    data => return Err(data),
+                }
+            }
         };
 
-        if &self.ty == token.ty() {
-            return Parse::new(
-                &self.pty,
-                ParseResult::Ok(ParseData::Token(token.clone())),
-                offset,
-                offset,
-            );
+        while let Some(table) = table {
+            let at = ctx.skip(offs);
+            let Some(op) = ctx.get(at) else { break };
+            let Some((fixity, precedence)) = table.infix(op.ty()) else {
+                break;
+            };
+            let (left, right) = match (fixity, 2 * u32::from(precedence)) {
+                (Fixity::InfixRight, p) => (p + 1, p),
+                (_, p) => (p, p + 1),
+            };
+            if left < min {
+                break;
+            }
+            let (rhs, end) = self.operand_after(ctx, at + 1, right)?;
+            lhs = ParseData::Nested(vec![lhs, ParseData::Token(op.clone()), rhs]);
+            offs = end;
         }
-        Parse::new(
-            &self.pty,
-            ParseResult::Err(ParseError::from(self.pty.to_string(), token.span().clone())),
-            offset,
-            offset,
-        )
+        Ok((lhs, offs))
     }
-}
 
-pub struct Predicate<T> {
-    pty: String,
-    optional: bool,
-    predicate: fn(&T) -> bool,
+    fn operand_after(
+        &self,
+        ctx: &Context<T>,
+        offset: usize,
+        min: u32,
+    ) -> Result<(ParseData<T>, usize), ParseResult<T>> {
+        let at = ctx.skip(offset);
+        match self.climb(ctx, at, min) {
+            Err(ParseResult::None) => Err(match ctx.get_required(&self.pty, at, false) {
+                Ok(token) => mismatch(&self.pty, false, token),
+                Err(e) => e,
+            }),
+            result => result,
+        }
+    }
 }
 
-impl<T> Predicate<T> {
-    pub fn from(pty: &str, optional: bool, predicate: fn(&T) -> bool) -> Self {
-        Self::new(pty.to_string(), optional ,predicate)
+impl<T> Parser<T> for Expression<T>
+where
+    T: PartialEq + Clone,
+{
+    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
+        traced(ctx, &self.pty, offset, || {
+            match self.climb(ctx, offset, 0) {
+                Ok((data, end)) => Parse::new(self.pty.clone(), ParseResult::Ok(data), offset, end),
+                Err(data) => Parse::new(self.pty.clone(), data, offset, offset),
+            }
+        })
     }
 
-    pub const fn new(pty: String, optional: bool, predicate: fn(&T) -> bool) -> Self {
-        Self {
-            pty,
-            optional,
-            predicate,
-        }
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        self.operand.generate(gen, out)
     }
-}
 
-impl<T> Parser<T> for Predicate<T>
-where T: Clone {
-    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        let token = match ctx.get_required(&self.pty, offset, self.optional) {
-            Ok(t) => t,
-            Err(e) => return Parse::new(&self.pty, e, offset, offset),
-        };
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.operand.snippet(out)
+    }
 
-        if (self.predicate)(token.ty()) {
-            return Parse::new(
-                &self.pty,
-                ParseResult::Ok(ParseData::Token(token.clone())),
-                offset,
-                offset,
-            );
-        }
-        Parse::new(
-            &self.pty,
-            ParseResult::Err(ParseError::from(self.pty.to_string(), token.span().clone())),
-            offset,
-            offset + 1,
-        )
+    fn first_set(&self) -> TokenSet<T> {
+        // prefix operators may be declared while parsing
+        TokenSet::rule(self.pty.clone())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
     }
 }
 
-pub struct Sequence<T> {
-    pty: String,
-    optional: bool,
-    inner: Vec<Box<dyn Parser<T>>>,
+/// Runs `declare` on what `inner` matched, so the grammar can add to the context's operator
+/// table as it reads fixity declarations. Combinators that backtrack take back declarations
+/// made by attempts that don't stand: Choice alternatives and Not, sequences that fail partway,
+/// repetition items that fail, and counts a repetition gives back.
+pub struct Declare<T> {
+    pty: Arc<str>,
+    inner: Box<dyn Parser<T>>,
+    declare: fn(&ParseData<T>, &OperatorTable<T>),
 }
 
-impl<T> Sequence<T> {
-    pub fn from(pty: &str, optional: bool, sequence: Vec<Box<dyn Parser<T>>>) -> Self {
-        Self::new(pty.to_string(), optional, sequence)
+impl<T> Declare<T> {
+    pub fn from(
+        pty: &str,
+        inner: Box<dyn Parser<T>>,
+        declare: fn(&ParseData<T>, &OperatorTable<T>),
+    ) -> Self {
+        Self::new(pty.into(), inner, declare)
     }
 
-    pub const fn new(pty: String, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
+    pub const fn new(
+        pty: Arc<str>,
+        inner: Box<dyn Parser<T>>,
+        declare: fn(&ParseData<T>, &OperatorTable<T>),
+    ) -> Self {
         Self {
             pty,
-            optional,
             inner,
+            declare,
         }
     }
 }
 
-impl<T> Parser<T> for Sequence<T> {
+impl<T> Parser<T> for Declare<T> {
     fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        let mut offs = offset;
-        let mut expr = vec![];
+        traced(ctx, &self.pty, offset, || {
+            let parse = self.inner.parse(ctx, offset);
+            if let (ParseResult::Ok(data), Some(table)) = (&parse.data, ctx.operators) {
+                (self.declare)(data, table);
+            }
+            let end = offset + parse.consumed();
+            Parse::new(self.pty.clone(), parse.data, offset, end)
+        })
+    }
 
-        for item in &self.inner {
-            let parse = item.parse(ctx, offs);
-            let size = parse.size();
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        self.inner.generate(gen, out)
+    }
 
-            match parse.data {
-                ParseResult::Ok(d) => {
-                    offs += size;
-                    expr.push(d);
-                }
-                ParseResult::Err(e) => {
-                    if self.optional {
-                        return Parse::new(&self.pty, ParseResult::None, offset, offs);
-                    }
-                    return Parse::new(&self.pty, ParseResult::Err(e), offset, offs);
-                }
-                _ => (),
-            }
-        }
-        Parse::new(
-            &self.pty,
-            ParseResult::Ok(ParseData::Nested(expr)),
-            offset,
-            offs,
-        )
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        self.inner.snippet(out)
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        self.inner.first_set()
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
     }
 }
 
-pub struct Repeatable<T> {
-    pty: String,
-    optional: bool,
-    inner: Box<dyn Parser<T>>,
+pub struct Empty {
+    pty: Arc<str>,
 }
 
-impl<T> Repeatable<T> {
-    pub fn from(pty: &str, optional: bool, repeatable: Box<dyn Parser<T>>) -> Self {
-        Self::new(pty.to_string(), optional, repeatable)
+impl Empty {
+    pub fn from(pty: &str) -> Self {
+        Self::new(pty.into())
     }
 
-    pub const fn new(pty: String, optional: bool, inner: Box<dyn Parser<T>>) -> Self {
-        Self {
-            pty,
-            optional,
-            inner,
-        }
+    pub const fn new(pty: Arc<str>) -> Self {
+        Self { pty }
     }
 }
 
-impl<T> Parser<T> for Repeatable<T> {
+impl<T> Parser<T> for Empty {
     fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        let mut expr = vec![];
-        let mut err = None;
+        traced(ctx, &self.pty, offset, || {
+            Parse::new(
+                self.pty.clone(),
+                ParseResult::Ok(ParseData::Nested(vec![])),
+                offset,
+                offset,
+            )
+        })
+    }
 
-        let mut offs = offset;
+    fn generate(&self, _gen: &mut Generator, _out: &mut Vec<T>) -> bool {
+        true
+    }
 
-        loop {
-            let parse = self.inner.parse(ctx, offs);
+    fn snippet(&self, _out: &mut Vec<SnippetPart<T>>) -> bool {
+        true
+    }
 
-            match parse.data {
-                ParseResult::Ok(data) => {
-                    match &data {
-                        ParseData::Nested(l) => offs += l.len(),
-                        ParseData::TokenList(l) => offs += l.len(),
-                        ParseData::Token(_) => offs += 1,
-                    }
-                    expr.push(data);
-                }
-                ParseResult::Err(e) => {
-                    err = Some(e);
-                    break;
-                }
-                ParseResult::None => {
-                    break;
-                }
-            }
-        }
+    fn first_set(&self) -> TokenSet<T> {
+        TokenSet::empty()
+    }
 
-        let data = if expr.is_empty() {
-            if self.optional {
-                ParseResult::None
-            } else if let Some(err) = err {
-                ParseResult::Err(err)
-            } else {
-                ParseResult::Err(ParseError::from(
-                    self.pty.clone(),
-                    ctx.tokens[offs].span().clone(),
-                ))
-            }
-        } else {
-            ParseResult::Ok(ParseData::Nested(expr))
-        };
-        Parse::new(&self.pty, data, offset, offs)
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
     }
 }
 
-pub struct Not<T> {
-    pty: String,
+pub struct Choice<T> {
+    pty: Arc<str>,
     optional: bool,
-    inner: Box<dyn Parser<T>>,
+    accept_none: bool,
+    inner: Vec<Box<dyn Parser<T>>>,
 }
 
-impl<T> Not<T> {
-    pub const fn new(pty: String, optional: bool, inner: Box<dyn Parser<T>>) -> Self {
+impl<T> Choice<T> {
+    pub fn from(pty: &str, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
+        Self::new(pty.into(), optional, inner)
+    }
+
+    pub const fn accepting_none(mut self) -> Self {
+        self.accept_none = true;
+        self
+    }
+
+    pub const fn new(pty: Arc<str>, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
         Self {
             pty,
             optional,
+            accept_none: false,
             inner,
         }
     }
-}
 
-impl<T> Parser<T> for Not<T> {
-    fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        fn get_data_span<T>(data: &ParseData<T>) -> Span {
-            match data {
-                ParseData::Token(s) => s.span().clone(),
-                ParseData::TokenList(l) => l.first().unwrap().span().clone(),
-                ParseData::Nested(l) => get_data_span(l.first().unwrap()),
+    /// Pairs of alternatives that can start with the same token or named class, earlier one
+    /// first. Such a pair makes the parse try the second only after the first has gone some way
+    /// and failed, or never when the first always wins: candidates for
+    /// [`left_factor`](crate::factor::left_factor). Alternatives whose first set is unknown are
+    /// left out.
+    pub fn overlapping(&self) -> Vec<(usize, usize)>
+    where
+        T: PartialEq,
+    {
+        let firsts: Vec<TokenSet<T>> = self.inner.iter().map(|p| p.first_set()).collect();
+        let mut pairs = vec![];
+        for (i, a) in firsts.iter().enumerate() {
+            for (j, b) in firsts.iter().enumerate().skip(i + 1) {
+                if a.items().iter().any(|e| b.items().contains(e)) {
+                    pairs.push((i, j));
+                }
             }
         }
+        pairs
+    }
 
-        let parse = self.inner.parse(ctx, offset);
-
-        return Parse::new(
-            &self.pty,
-            match parse.data {
-                ParseResult::Ok(data) => {
-                    if self.optional {
-                        ParseResult::None
-                    } else {
-                        let span = get_data_span(&data);
-                        ParseResult::Err(ParseError::from(self.pty.clone(), span))
-                    }
+    /// Where one token of lookahead can't pick an alternative, as an LL(1) parser would need:
+    /// pairs of alternatives with the tokens both can start with, and pairs that can both match
+    /// nothing. FOLLOW sets aren't computed, so a nullable alternative clashing with what comes
+    /// after the choice goes unreported.
+    pub fn conflicts(&self) -> Vec<Conflict<T>>
+    where
+        T: Clone + PartialEq,
+    {
+        let firsts: Vec<TokenSet<T>> = self.inner.iter().map(|p| p.first_set().dedup()).collect();
+        let mut conflicts = vec![];
+        for (i, a) in firsts.iter().enumerate() {
+            for (j, b) in firsts.iter().enumerate().skip(i + 1) {
+                let on: Vec<Expected<T>> = a
+                    .items()
+                    .iter()
+                    .filter(|e| b.items().contains(e))
+                    .cloned()
+                    .collect();
+                let nullable = a.is_nullable() && b.is_nullable();
+                if !on.is_empty() || nullable {
+                    conflicts.push(Conflict {
+                        first: i,
+                        second: j,
+                        on,
+                        nullable,
+                    });
                 }
-                ParseResult::Err(_) | ParseResult::None => ParseResult::None,
-            },
-            parse.start_offset,
-            parse.end_offset,
-        );
+            }
+        }
+        conflicts
     }
-}
 
-pub struct Choice<T> {
-    pty: String,
-    optional: bool,
-    inner: Vec<Box<dyn Parser<T>>>,
+    /// Alternatives that can never match in an ordinary parse, because every input they accept
+    /// starts with one an earlier alternative accepts, and the earlier one wins. Each comes with
+    /// the earlier alternatives covering it. Only alternatives that are fixed runs of single
+    /// tokens, or choices between single tokens, are taken to cover others, so shadowing by
+    /// anything larger goes unreported.
+    pub fn shadowed(&self) -> Vec<(usize, Vec<usize>)>
+    where
+        T: PartialEq,
+    {
+        let exact: Vec<Option<Vec<TokenSet<T>>>> = self.inner.iter().map(|p| exact(&**p)).collect();
+        let mut shadowed = vec![];
+        for (j, alternative) in self.inner.iter().enumerate() {
+            let earlier: Vec<(usize, &[TokenSet<T>])> = exact[..j]
+                .iter()
+                .enumerate()
+                .filter_map(|(i, e)| Some((i, e.as_deref()?)))
+                .collect();
+            let mut by = vec![];
+            if covered(&leading(&**alternative), &earlier, &mut by) {
+                by.sort_unstable();
+                by.dedup();
+                shadowed.push((j, by));
+            }
+        }
+        shadowed
+    }
 }
 
-impl<T> Choice<T> {
-    pub fn from(pty: &str, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
-        Self::new(pty.to_string(), optional, inner)
+// The tokens, position by position, of what `parser` matches when that is a fixed run of
+// single tokens: every combination of them, and nothing else.
+fn exact<T>(parser: &dyn Parser<T>) -> Option<Vec<TokenSet<T>>> {
+    let first = parser.first_set();
+    if first.is_nullable() || first.is_open() {
+        return None;
+    }
+    match parser.structure() {
+        Structure::Token => Some(vec![first]),
+        Structure::Sequence(items) => items.into_iter().try_fold(vec![], |mut run, item| {
+            run.extend(exact(item)?);
+            Some(run)
+        }),
+        Structure::Choice(alternatives) => alternatives
+            .into_iter()
+            .all(|a| exact(a).is_some_and(|run| run.len() == 1))
+            .then(|| vec![first]),
+        Structure::Wraps(inner) => exact(inner),
+        Structure::Handle(handle) => exact(handle.parser()),
+        Structure::Opaque | Structure::Repeat(_) | Structure::Reference(_) => None,
     }
+}
 
-    pub const fn new(pty: String, optional: bool, inner: Vec<Box<dyn Parser<T>>>) -> Self {
-        Self {
-            pty,
-            optional,
-            inner,
+// The tokens, position by position, that everything `parser` matches starts with, as far as
+// can be told.
+fn leading<T>(parser: &dyn Parser<T>) -> Vec<TokenSet<T>> {
+    let first = parser.first_set();
+    if first.is_nullable() || first.is_open() {
+        return vec![];
+    }
+    if let Some(run) = exact(parser) {
+        return run;
+    }
+    match parser.structure() {
+        Structure::Sequence(items) => {
+            let mut run = vec![];
+            for item in items {
+                match exact(item) {
+                    Some(tokens) => run.extend(tokens),
+                    None => {
+                        run.extend(leading(item));
+                        break;
+                    }
+                }
+            }
+            run
+        }
+        Structure::Repeat(inner) | Structure::Wraps(inner) => leading(inner),
+        Structure::Handle(handle) => leading(handle.parser()),
+        Structure::Opaque | Structure::Token | Structure::Choice(_) | Structure::Reference(_) => {
+            vec![first]
         }
     }
 }
 
+// Whether every input starting with `leading` also starts with one of the `runs`, noting in
+// `by` the ones it takes.
+fn covered<T: PartialEq>(
+    leading: &[TokenSet<T>],
+    runs: &[(usize, &[TokenSet<T>])],
+    by: &mut Vec<usize>,
+) -> bool {
+    let Some((first, rest)) = leading.split_first() else {
+        return false;
+    };
+    !first.items().is_empty()
+        && first.items().iter().all(|e| {
+            let going: Vec<(usize, &[TokenSet<T>])> = runs
+                .iter()
+                .filter(|(_, run)| run[0].items().contains(e))
+                .map(|(i, run)| (*i, &run[1..]))
+                .collect();
+            match going.iter().find(|(_, run)| run.is_empty()) {
+                Some((i, _)) => {
+                    by.push(*i);
+                    true
+                }
+                None => covered(rest, &going, by),
+            }
+        })
+}
+
 impl<T> Parser<T> for Choice<T> {
     fn parse(&self, ctx: &Context<T>, offset: usize) -> Parse<T> {
-        for choice in &self.inner {
-            let parse = choice.parse(ctx, offset);
+        traced(ctx, &self.pty, offset, || {
+            if let Some(coverage) = ctx.coverage {
+                coverage.alternatives(&self.pty, self.inner.len());
+            }
 
-            if let ParseResult::Ok(_) = parse.data {
+            let checkpoint = ctx.checkpoint(offset);
+            let fork = ctx
+                .forks
+                .map(|forks| (forks, forks.visit(self.inner.len())));
+            for (i, choice) in self.inner.iter().enumerate() {
+                if fork.is_some_and(|(_, (_, forced))| forced.is_some_and(|f| f != i)) {
+                    continue;
+                }
+                let mut parse = choice.parse(ctx, offset);
+
+                match &parse.data {
+                    ParseResult::Ok(_) => (),
+                    ParseResult::None if self.accept_none => (),
+                    _ => {
+                        ctx.rewind(&checkpoint);
+                        continue;
+                    }
+                }
+                if let Some(coverage) = ctx.coverage {
+                    coverage.alternative(&self.pty, i);
+                }
+                if let Some((forks, (visit, _))) = fork {
+                    forks.took(visit, i);
+                }
+                parse.alternative = Some(i);
                 return parse;
             }
+
+            let data = if self.optional {
+                ParseResult::None
+            } else {
+                let span = ctx.span_at(offset).unwrap_or_else(|| ctx.span_last());
+                ParseResult::Err(ParseError::from(self.pty.to_string(), span))
+            };
+            Parse::new(self.pty.clone(), data, offset, offset)
+        })
+    }
+
+    fn generate(&self, gen: &mut Generator, out: &mut Vec<T>) -> bool {
+        if self.optional && (gen.exhausted() || gen.coin()) {
+            return true;
         }
+        // Start at a random alternative and fall through to the others if it cannot generate.
+        let start = gen.below(self.inner.len());
+        let len = out.len();
+        let ok = gen.nested(|gen| {
+            (0..self.inner.len()).any(|i| {
+                out.truncate(len);
+                self.inner[(start + i) % self.inner.len()].generate(gen, out)
+            })
+        });
+        if !ok {
+            out.truncate(len);
+        }
+        ok || self.optional
+    }
 
-        return Parse::new(
-            &self.pty,
-            ParseResult::Err(ParseError::from(self.pty.clone(), ctx.span_last())),
-            offset,
-            offset,
-        );
+    fn snippet(&self, out: &mut Vec<SnippetPart<T>>) -> bool {
+        if self.optional {
+            return true;
+        }
+        let len = out.len();
+        self.inner.iter().any(|choice| {
+            out.truncate(len);
+            choice.snippet(out)
+        })
+    }
+
+    fn first_set(&self) -> TokenSet<T> {
+        let mut set = TokenSet::empty().nullable(self.optional);
+        let mut nullable = self.optional;
+        for choice in &self.inner {
+            let first = choice.first_set();
+            nullable |= first.is_nullable();
+            set.extend(first);
+        }
+        set.nullable(nullable)
+    }
+
+    fn structure(&self) -> Structure<'_, T> {
+        Structure::Choice(self.inner.iter().map(|p| &**p as &dyn Parser<T>).collect())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.pty)
     }
 }
@@ -0,0 +1,145 @@
+pub use crate::parse::{
+    Context, ErrorKind, Parse, ParseData, ParseError, ParseResult, Parser, Shared,
+};
+pub use crate::token::{HasKind, Span, Token};
+
+#[cfg(feature = "derive")]
+pub use crate::token::TokenKind;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::parse::{
+    Balanced, Choice, Empty, Not, OfKind, OfType, Predicate, Recover, Repeatable, Sequence,
+};
+
+/// Rules built here are named after what they match, so errors read "expected ;" and a
+/// sequence of `let` and a name is called "let name". Long names are cut short with "…".
+pub fn just<T>(ty: T) -> Box<dyn Parser<T>>
+where
+    T: PartialEq + Clone + Display + 'static,
+{
+    Box::new(OfType::from(&ty.to_string(), false, ty))
+}
+
+pub fn empty<T>() -> Box<dyn Parser<T>> {
+    Box::new(Empty::from("empty"))
+}
+
+/// A predicate can't be named after its argument, so it takes the name to report.
+pub fn pred<T>(name: &str, predicate: fn(&T) -> bool) -> Box<dyn Parser<T>>
+where
+    T: Clone + 'static,
+{
+    Box::new(Predicate::from(name, false, predicate))
+}
+
+pub fn kind<T>(kind: T::Kind) -> Box<dyn Parser<T>>
+where
+    T: HasKind + Clone + 'static,
+    T::Kind: Display + 'static,
+{
+    Box::new(OfKind::from(&kind.to_string(), false, kind))
+}
+
+pub fn seq<T>(inner: impl IntoIterator<Item = Box<dyn Parser<T>>>) -> Box<dyn Parser<T>>
+where
+    T: Clone + 'static,
+{
+    let inner: Vec<_> = inner.into_iter().collect();
+    let name = compose(&inner, " ", "sequence");
+    Box::new(Sequence::from(&name, false, inner))
+}
+
+pub fn choice<T: 'static>(
+    inner: impl IntoIterator<Item = Box<dyn Parser<T>>>,
+) -> Box<dyn Parser<T>> {
+    let inner: Vec<_> = inner.into_iter().collect();
+    let name = compose(&inner, " or ", "choice");
+    Box::new(Choice::from(&name, false, inner))
+}
+
+pub fn many<T>(inner: Box<dyn Parser<T>>) -> Box<dyn Parser<T>>
+where
+    T: Clone + 'static,
+{
+    let name = prefixed("many", &*inner);
+    Box::new(Repeatable::from(&name, false, inner))
+}
+
+pub fn not<T: 'static>(inner: Box<dyn Parser<T>>) -> Box<dyn Parser<T>> {
+    let name = prefixed("not", &*inner);
+    Box::new(Not::from(&name, false, inner))
+}
+
+pub fn recover<T>(inner: Box<dyn Parser<T>>, sync: fn(&T) -> bool) -> Box<dyn Parser<T>>
+where
+    T: Clone + 'static,
+{
+    let name = inner.name().unwrap_or("recover").to_string();
+    Box::new(Recover::from(&name, inner, sync))
+}
+
+/// Named after its pairs, so `balanced([("(", ")"), ("[", "]")])` is called "(…) or […]".
+pub fn balanced<T>(pairs: impl IntoIterator<Item = (T, T)>) -> Box<dyn Parser<T>>
+where
+    T: PartialEq + Clone + Display + 'static,
+{
+    let pairs: Vec<(T, T)> = pairs.into_iter().collect();
+    let names: Vec<String> = pairs
+        .iter()
+        .map(|(open, close)| format!("{open}…{close}"))
+        .collect();
+    let name = join(&names, " or ", "balanced");
+    Box::new(Balanced::from(&name, false, pairs))
+}
+
+// Past this many characters a built-up name ends in "…".
+const NAME_LIMIT: usize = 40;
+
+// Names a parser made of `parts` by joining theirs with `sep`, bracketing names of more than
+// one word. `fallback` when a part has no name.
+fn compose<T>(parts: &[Box<dyn Parser<T>>], sep: &str, fallback: &str) -> String {
+    let names: Option<Vec<String>> = parts.iter().map(|p| p.name().map(group)).collect();
+    match names {
+        Some(names) => join(&names, sep, fallback),
+        None => fallback.into(),
+    }
+}
+
+fn join(names: &[String], sep: &str, fallback: &str) -> String {
+    let mut name = String::new();
+    for (i, part) in names.iter().enumerate() {
+        if i > 0 {
+            name.push_str(sep);
+        }
+        if i > 0 && name.chars().count() + part.chars().count() > NAME_LIMIT {
+            name.push('…');
+            break;
+        }
+        name.push_str(part);
+    }
+    if name.is_empty() {
+        fallback.into()
+    } else {
+        name
+    }
+}
+
+fn prefixed<T>(word: &str, inner: &dyn Parser<T>) -> String {
+    match inner.name() {
+        Some(name) => format!("{word} {}", group(name)),
+        None => word.into(),
+    }
+}
+
+fn group(name: &str) -> String {
+    if name.contains(' ') {
+        format!("({name})")
+    } else {
+        name.into()
+    }
+}
@@ -0,0 +1,39 @@
+use core::cell::Cell;
+
+/// Calls back whenever the furthest offset any rule has matched up to crosses another multiple
+/// of `every` tokens.
+pub(crate) struct Progress<'t> {
+    callback: &'t dyn Fn(usize),
+    every: usize,
+    next: Cell<usize>,
+    last: Cell<usize>,
+}
+
+impl<'t> Progress<'t> {
+    pub(crate) fn advance(&self, offset: usize) {
+        if offset < self.next.get() {
+            return;
+        }
+        self.next.set((offset / self.every + 1) * self.every);
+        self.last.set(offset);
+        (self.callback)(offset);
+    }
+
+    /// Reports where the parse ended, unless that was the last thing reported.
+    pub(crate) fn finish(&self, offset: usize) {
+        if offset > self.last.get() {
+            self.last.set(offset);
+            (self.callback)(offset);
+        }
+    }
+
+    pub(crate) fn new(every: usize, callback: &'t dyn Fn(usize)) -> Self {
+        let every = every.max(1);
+        Self {
+            callback,
+            every,
+            next: Cell::new(every),
+            last: Cell::new(0),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::parse::{Context, ParseError};
+
+/// What a tolerant [`Sequence`](crate::parse::Sequence) or
+/// [`Repeatable`](crate::parse::Repeatable) should do about a failed item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// Wrap this many tokens in an error node and try the item again after them. Repeatable
+    /// moves on to its next repetition.
+    Skip(usize),
+    /// Put an error node, or the strategy's stand-in token, where the item should have been
+    /// and carry on as if it had matched. Repeatable takes this as the end of the repetition.
+    Insert,
+    /// Fail as if there were no strategy.
+    Abort,
+}
+
+/// Decides how a rule recovers from a syntax error, given the error and the tokens from
+/// `offset` on. Budget and cancellation errors are never offered to a strategy.
+pub trait RecoveryStrategy<T> {
+    fn recover(&self, error: &ParseError, ctx: &Context<T>, offset: usize) -> Recovery;
+
+    /// The type of a zero-width token to insert for [`Recovery::Insert`], instead of an error
+    /// node. The token is marked with [`Provenance::inserted`](crate::token::Provenance) and
+    /// still reported by [`ParseData::errors`](crate::parse::ParseData::errors).
+    fn stand_in(&self, _error: &ParseError) -> Option<T> {
+        None
+    }
+}
+
+impl<T, F> RecoveryStrategy<T> for F
+where
+    F: Fn(&ParseError, &Context<T>, usize) -> Recovery,
+{
+    fn recover(&self, error: &ParseError, ctx: &Context<T>, offset: usize) -> Recovery {
+        self(error, ctx, offset)
+    }
+}
+
+/// Panic-mode recovery: skips to the next token `sync` accepts, or inserts the missing item when
+/// already standing on one. A sync token that the failed item started with and got past is
+/// skipped too, so a repetition doesn't stop on the same broken construct.
+#[derive(Clone, Copy)]
+pub struct SkipTo<T> {
+    sync: fn(&T) -> bool,
+}
+
+impl<T> SkipTo<T> {
+    pub const fn new(sync: fn(&T) -> bool) -> Self {
+        Self { sync }
+    }
+}
+
+impl<T> RecoveryStrategy<T> for SkipTo<T> {
+    fn recover(&self, error: &ParseError, ctx: &Context<T>, offset: usize) -> Recovery {
+        let Some(first) = ctx.get(offset) else {
+            return Recovery::Abort;
+        };
+        let started = first.span() != error.span();
+        let skip = (offset + usize::from(started)..)
+            .map_while(|i| ctx.get(i))
+            .take_while(|t| !(self.sync)(t.ty()))
+            .count();
+        match skip + usize::from(started) {
+            0 => Recovery::Insert,
+            n => Recovery::Skip(n),
+        }
+    }
+}
+
+/// Inserts the token `missing` names for an error, such as a `;` or `)` the input left out,
+/// and gives up on errors it names nothing for.
+#[derive(Clone, Copy)]
+pub struct InsertMissing<T> {
+    missing: fn(&ParseError) -> Option<T>,
+}
+
+impl<T> InsertMissing<T> {
+    pub const fn new(missing: fn(&ParseError) -> Option<T>) -> Self {
+        Self { missing }
+    }
+}
+
+impl<T> RecoveryStrategy<T> for InsertMissing<T> {
+    fn recover(&self, error: &ParseError, _ctx: &Context<T>, _offset: usize) -> Recovery {
+        match (self.missing)(error) {
+            Some(_) => Recovery::Insert,
+            None => Recovery::Abort,
+        }
+    }
+
+    fn stand_in(&self, error: &ParseError) -> Option<T> {
+        (self.missing)(error)
+    }
+}
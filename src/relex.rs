@@ -0,0 +1,61 @@
+use alloc::vec::Vec;
+
+use crate::token::Token;
+
+/// The lines an edit touched, 1-based and inclusive: `start..=old_end` in the old text became
+/// `start..=new_end` in the new one. A `start` of 0 means from the beginning, like 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineEdit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+/// Brings `tokens` up to date with `source`, the text after `edit`, by re-lexing only the edited
+/// lines. `lex` is given the text of those lines and the number of the first one and must return
+/// tokens positioned in `source`. Tokens past the edit keep their columns and move by the change
+/// in line count.
+///
+/// Lines are lexed in isolation, so lexers that carry state from one line to the next (block
+/// comments, multi-line strings) need `edit` widened to cover the whole construct. Tokens should
+/// not have trivia attached yet; run [`attach_trivia`](crate::token::attach_trivia) afterwards.
+pub fn relex<T>(
+    tokens: Vec<Token<T>>,
+    source: &str,
+    edit: LineEdit,
+    lex: impl FnOnce(&str, usize) -> Vec<Token<T>>,
+) -> Vec<Token<T>> {
+    let delta = edit.new_end as isize - edit.old_end as isize;
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+
+    for mut token in tokens {
+        let ln = token.span().ln();
+        if ln < edit.start {
+            before.push(token);
+        } else if ln > edit.old_end {
+            token.shift_lines(delta);
+            after.push(token);
+        }
+    }
+
+    let damaged = line_range(source, edit.start, edit.new_end);
+    before.extend(lex(damaged, edit.start.max(1)));
+    before.extend(after);
+    before
+}
+
+fn line_range(source: &str, start: usize, end: usize) -> &str {
+    let mut offsets = source
+        .match_indices('\n')
+        .map(|(i, _)| i + 1)
+        .collect::<Vec<_>>();
+    offsets.insert(0, 0);
+
+    let from = match start.checked_sub(1) {
+        Some(line) => offsets.get(line).copied().unwrap_or(source.len()),
+        None => 0,
+    };
+    let to = offsets.get(end).copied().unwrap_or(source.len());
+    &source[from..to.max(from)]
+}
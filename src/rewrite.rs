@@ -0,0 +1,154 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+use crate::column::ColumnPolicy;
+use crate::index::{first_span, last_span};
+use crate::parse::ParseData;
+use crate::token::Span;
+
+/// Replaces the text from one position up to (not including) another, each a line and
+/// 1-based column. Insertions start and end at the same position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    start: (usize, usize),
+    end: (usize, usize),
+    text: String,
+}
+
+impl TextEdit {
+    pub fn replace(span: &Span, text: impl Into<String>) -> Self {
+        Self::between(span, span, text)
+    }
+
+    /// Replaces everything from the start of `first` to the end of `last`, which may lie on
+    /// later lines.
+    pub fn between(first: &Span, last: &Span, text: impl Into<String>) -> Self {
+        Self {
+            start: (first.ln(), first.cs()),
+            end: (last.ln(), last.ce() + 1),
+            text: text.into(),
+        }
+    }
+
+    pub fn delete(span: &Span) -> Self {
+        Self::replace(span, "")
+    }
+
+    pub fn insert_before(span: &Span, text: impl Into<String>) -> Self {
+        let at = (span.ln(), span.cs());
+        Self::at(at, text)
+    }
+
+    pub fn insert_after(span: &Span, text: impl Into<String>) -> Self {
+        let at = (span.ln(), span.ce() + 1);
+        Self::at(at, text)
+    }
+
+    /// Replaces the source of a whole node, or `None` for a node without tokens.
+    pub fn node<T>(data: &ParseData<T>, text: impl Into<String>) -> Option<Self> {
+        Some(Self::between(first_span(data)?, last_span(data)?, text))
+    }
+
+    fn at(at: (usize, usize), text: impl Into<String>) -> Self {
+        Self {
+            start: at,
+            end: at,
+            text: text.into(),
+        }
+    }
+
+    // Insertions only clash with edits they fall strictly inside.
+    fn overlaps(&self, other: &TextEdit) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RewriteError {
+    /// The edit overlaps the one added at this index.
+    Overlap(usize),
+    /// The edit points past the end of its line or of the source.
+    OutOfSource(usize),
+}
+
+impl Display for RewriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RewriteError::Overlap(i) => write!(f, "Edit overlaps edit {i}"),
+            RewriteError::OutOfSource(i) => write!(f, "Edit {i} lies outside the source"),
+        }
+    }
+}
+
+/// Collects edits to `source`, typically made from tree nodes by code fixes and formatters,
+/// and applies them all at once. Positions are resolved against the original text, so edits
+/// don't need to account for each other; overlapping ones are turned away as they are added.
+/// Insertions at the same position go in in the order they were added.
+pub struct SourceRewriter<'s> {
+    source: &'s str,
+    columns: ColumnPolicy,
+    edits: Vec<TextEdit>,
+}
+
+impl<'s> SourceRewriter<'s> {
+    pub const fn new(source: &'s str) -> Self {
+        Self {
+            source,
+            columns: ColumnPolicy::Chars,
+            edits: Vec::new(),
+        }
+    }
+
+    /// How the spans the edits come from count columns; characters unless set.
+    pub const fn with_columns(mut self, columns: ColumnPolicy) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn add(&mut self, edit: TextEdit) -> Result<(), RewriteError> {
+        if let Some(i) = self.edits.iter().position(|e| e.overlaps(&edit)) {
+            return Err(RewriteError::Overlap(i));
+        }
+        self.edits.push(edit);
+        Ok(())
+    }
+
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+
+    pub fn apply(&self) -> Result<String, RewriteError> {
+        let mut edits = self
+            .edits
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let start = self.offset(e.start).ok_or(RewriteError::OutOfSource(i))?;
+                let end = self.offset(e.end).ok_or(RewriteError::OutOfSource(i))?;
+                Ok((start, end.max(start), &e.text))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+        let mut out = String::with_capacity(self.source.len());
+        let mut at = 0;
+        for (start, end, text) in edits {
+            out.push_str(&self.source[at..start]);
+            out.push_str(text);
+            at = end;
+        }
+        out.push_str(&self.source[at..]);
+        Ok(out)
+    }
+
+    fn offset(&self, (ln, col): (usize, usize)) -> Option<usize> {
+        let mut line_start = 0;
+        for _ in 1..ln {
+            line_start += self.source[line_start..].find('\n')? + 1;
+        }
+        let rest = &self.source[line_start..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        Some(line_start + self.columns.byte_offset(line, col)?)
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::column::ColumnPolicy;
+use crate::input::TokenInput;
+use crate::parse::ParseData;
+use crate::token::{Span, Token};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// Source text no token accounts for.
+    Missing,
+    /// A token that starts before the one in front of it ends.
+    Overlap,
+    /// A token pointing past the end of its line or of the source.
+    OutOfSource,
+    /// A token that differs from the one at the same place in the token stream.
+    Mismatch,
+    /// Tokens in the stream that the tree never got to, or the other way round.
+    Length,
+}
+
+/// Where a tree first stops reproducing its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub kind: DivergenceKind,
+    pub span: Span,
+}
+
+/// Checks that the tokens of a lossless tree, trivia included, cover `source` exactly: in
+/// order, without gaps or overlaps, so printing them back gives the original bytes. Line breaks
+/// are implied by tokens moving to a later line, so no token needs to span one.
+pub fn verify_lossless<T>(
+    data: &ParseData<T>,
+    source: &str,
+    columns: ColumnPolicy,
+) -> Result<(), Divergence> {
+    let lines: Vec<&str> = source.lines().collect();
+    let (mut ln, mut at) = (1, 0);
+    let missing = |ln: usize, from: usize, line: &str| Divergence {
+        kind: DivergenceKind::Missing,
+        span: Span::new(
+            ln,
+            columns.column(line, from),
+            columns.column(line, line.len()) - 1,
+        ),
+    };
+
+    for token in leaves(data) {
+        let span = token.span();
+        let out_of_source = || Divergence {
+            kind: DivergenceKind::OutOfSource,
+            span: span.clone(),
+        };
+        let line = lines
+            .get(span.ln().wrapping_sub(1))
+            .ok_or_else(out_of_source)?;
+        let start = columns
+            .byte_offset(line, span.cs())
+            .ok_or_else(out_of_source)?;
+        let end = columns
+            .byte_offset(line, span.ce() + 1)
+            .ok_or_else(out_of_source)?;
+
+        if span.ln() > ln {
+            // the rest of this line and any lines skipped over must be empty
+            let current = lines.get(ln - 1).copied().unwrap_or("");
+            if at < current.len() {
+                return Err(missing(ln, at, current));
+            }
+            if let Some(skipped) = (ln + 1..span.ln()).find(|l| !lines[l - 1].is_empty()) {
+                return Err(missing(skipped, 0, lines[skipped - 1]));
+            }
+            (ln, at) = (span.ln(), 0);
+        }
+        if span.ln() < ln || start < at {
+            return Err(Divergence {
+                kind: DivergenceKind::Overlap,
+                span: span.clone(),
+            });
+        }
+        if start > at {
+            let mut gap = missing(ln, at, line);
+            gap.span = Span::new(ln, columns.column(line, at), span.cs() - 1);
+            return Err(gap);
+        }
+        at = end;
+    }
+
+    let current = lines.get(ln.wrapping_sub(1)).copied().unwrap_or("");
+    if at < current.len() {
+        return Err(missing(ln, at, current));
+    }
+    match (ln + 1..=lines.len()).find(|l| !lines[l - 1].is_empty()) {
+        Some(l) => Err(missing(l, 0, lines[l - 1])),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the tree holds exactly `tokens`, in order, for trees that drop trivia and so
+/// can't give the source back. Tokens are compared by type and span.
+pub fn verify_tokens<T: PartialEq>(
+    data: &ParseData<T>,
+    tokens: &dyn TokenInput<T>,
+) -> Result<(), Divergence> {
+    let mut count = 0;
+    for (i, token) in data_tokens(data).enumerate() {
+        count += 1;
+        match tokens.get(i) {
+            Some(t) if t.ty() == token.ty() && t.span() == token.span() => (),
+            Some(_) => {
+                return Err(Divergence {
+                    kind: DivergenceKind::Mismatch,
+                    span: token.span().clone(),
+                })
+            }
+            None => {
+                return Err(Divergence {
+                    kind: DivergenceKind::Length,
+                    span: token.span().clone(),
+                })
+            }
+        }
+    }
+    match tokens.span_at(count) {
+        Some(span) => Err(Divergence {
+            kind: DivergenceKind::Length,
+            span,
+        }),
+        None => Ok(()),
+    }
+}
+
+// The tokens of the tree in order, without their trivia.
+fn data_tokens<T>(data: &ParseData<T>) -> impl Iterator<Item = &Token<T>> {
+    let mut stack = vec![data];
+    let mut pending: Vec<&Token<T>> = vec![];
+    core::iter::from_fn(move || loop {
+        if let Some(token) = pending.pop() {
+            return Some(token);
+        }
+        match stack.pop()? {
+            ParseData::Token(t) => return Some(t),
+            ParseData::TokenList(l) | ParseData::Error { skipped: l, .. } => {
+                pending.extend(l.iter().rev())
+            }
+            ParseData::Nested(l) => stack.extend(l.iter().rev()),
+        }
+    })
+}
+
+// The tokens of the tree in order, each between its leading and trailing trivia.
+fn leaves<T>(data: &ParseData<T>) -> impl Iterator<Item = &Token<T>> {
+    data_tokens(data).flat_map(with_trivia)
+}
+
+fn with_trivia<T>(token: &Token<T>) -> Vec<&Token<T>> {
+    let mut out = vec![];
+    out.extend(token.leading().iter().flat_map(with_trivia));
+    out.push(token);
+    out.extend(token.trailing().iter().flat_map(with_trivia));
+    out
+}
@@ -0,0 +1,409 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::generate::Generator;
+use crate::input::TokenInput;
+use crate::parse::{Deferred, ParseData, ParseError, Parser, Shared, Slot, Structure};
+use crate::snippet::snippet;
+use crate::token::Span;
+
+// Inputs generated from each version of a rule for the other to parse, the same ones every
+// time so a diff is reproducible.
+const SAMPLES: usize = 32;
+const SEED: u64 = 0x5eed;
+
+/// Named rules shared between several entry points, so one grammar can parse a whole document
+/// in one place and a single expression in another.
+///
+/// Flags let one set serve several versions of a language: rules and alternatives gated on a
+/// flag are only built in when the set was created with that flag enabled.
+pub struct RuleSet<T> {
+    rules: BTreeMap<Arc<str>, Shared<T>>,
+    flags: BTreeSet<Arc<str>>,
+    // where references by name find their rule, filled in as rules are inserted
+    slots: RefCell<BTreeMap<Arc<str>, Slot<T>>>,
+}
+
+/// How one rule set differs from another, by rule name. A rule counts as changed when what it
+/// can start with (its FIRST set, in order) or its [`snippet`] changed, or when either version
+/// rejects one of a fixed run of inputs generated from the other. Rules that can't describe
+/// or generate themselves, such as predicates, are compared on what they do offer, so a change
+/// inside a predicate goes unnoticed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl RuleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// What [`RuleSet::analyze`] finds in a rule set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// The rules none of the entry points reaches, as [`RuleSet::unused`] gives them.
+    pub unreachable: Vec<String>,
+    /// Groups of rules that reach each other through their references, each in name order. A
+    /// rule referring to itself is a group of one.
+    pub cycles: Vec<Vec<String>>,
+    /// How deep each rule's matches go, by rule name.
+    pub depths: BTreeMap<String, Depth>,
+}
+
+/// How many rules a match of a rule passes through, one inside the next, counting the rule
+/// itself. Optional parts and repetitions count as matched once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Depth {
+    /// The fewest, or `None` when the rule can't match without recursing forever.
+    pub min: Option<usize>,
+    /// The most, or `None` when recursion leaves no limit.
+    pub max: Option<usize>,
+}
+
+// What a rule is made of, down to its references to other rules of the set.
+enum Shape {
+    Rule(Arc<str>),
+    // a sequence, or one parser in a repetition or wrapper: each part is matched
+    All(Vec<Shape>),
+    // a choice: one part is matched
+    Any(Vec<Shape>),
+}
+
+impl Shape {
+    fn rules(&self, out: &mut BTreeSet<Arc<str>>) {
+        match self {
+            Shape::Rule(name) => {
+                out.insert(name.clone());
+            }
+            Shape::All(parts) | Shape::Any(parts) => parts.iter().for_each(|p| p.rules(out)),
+        }
+    }
+
+    // The depth of a match, given that of each rule; `choose` settles a choice.
+    fn depth(
+        &self,
+        rule: &dyn Fn(&str) -> Option<usize>,
+        choose: fn(Vec<Option<usize>>) -> Option<usize>,
+    ) -> Option<usize> {
+        match self {
+            Shape::Rule(name) => rule(name),
+            Shape::All(parts) => parts
+                .iter()
+                .try_fold(0, |d, p| Some(d.max(p.depth(rule, choose)?))),
+            Shape::Any(parts) => choose(parts.iter().map(|p| p.depth(rule, choose)).collect()),
+        }
+    }
+}
+
+impl<T: 'static> RuleSet<T> {
+    pub fn diff(old: &Self, new: &Self) -> RuleDiff
+    where
+        T: PartialEq,
+    {
+        let mut diff = RuleDiff::default();
+        for (name, rule) in &new.rules {
+            match old.rules.get(name) {
+                None => diff.added.push(name.to_string()),
+                Some(was) if differs(was, rule) => diff.changed.push(name.to_string()),
+                Some(_) => (),
+            }
+        }
+        diff.removed = old
+            .names()
+            .filter(|n| !new.rules.contains_key(*n))
+            .map(String::from)
+            .collect();
+        diff
+    }
+
+    /// Registers `parser` under `name` and returns a handle for building larger rules from it.
+    pub fn insert(&mut self, name: &str, parser: impl Parser<T> + 'static) -> Shared<T> {
+        let shared = parser.shared();
+        self.rules.insert(name.into(), shared.clone());
+        let slot = self
+            .slots
+            .borrow_mut()
+            .entry(name.into())
+            .or_default()
+            .clone();
+        let _ = slot.set(Box::new(shared.clone()));
+        shared
+    }
+
+    /// Refers to the rule `name` before it is inserted, for rules that refer to themselves or
+    /// to each other. The reference parses as that rule for as long as this set is alive;
+    /// parsing it once the set is gone, or before the rule is inserted, panics.
+    pub fn reference(&self, name: &str) -> Box<dyn Parser<T>> {
+        let mut slots = self.slots.borrow_mut();
+        let slot = slots.entry(name.into()).or_default();
+        Box::new(Deferred::within(name.into(), slot))
+    }
+
+    /// Registers `parser` only if `flag` is enabled.
+    pub fn insert_if(
+        &mut self,
+        flag: &str,
+        name: &str,
+        parser: impl Parser<T> + 'static,
+    ) -> Option<Shared<T>> {
+        self.enabled(flag).then(|| self.insert(name, parser))
+    }
+
+    /// `parser` if `flag` is enabled, for gating one alternative of a choice:
+    /// `choice([a, b].into_iter().chain(rules.when("v2", c)))`.
+    pub fn when(&self, flag: &str, parser: Box<dyn Parser<T>>) -> Option<Box<dyn Parser<T>>> {
+        self.enabled(flag).then_some(parser)
+    }
+
+    pub fn enabled(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    pub fn with_flags<'f>(mut self, flags: impl IntoIterator<Item = &'f str>) -> Self {
+        self.flags.extend(flags.into_iter().map(Arc::from));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Shared<T>> {
+        self.rules.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.keys().map(|k| &**k)
+    }
+
+    /// The rules none of `starts` uses, directly or through other rules, in name order: ones
+    /// left behind by a change, or never wired in. A rule is used where its handle or a
+    /// [`reference`](Self::reference) to it appears in another; what a custom parser refers to
+    /// can't be seen.
+    pub fn unused(&self, starts: &[&str]) -> Vec<String> {
+        let refs = references(&self.shapes());
+        let mut used: BTreeSet<&str> = BTreeSet::new();
+        let mut pending: Vec<&str> = starts.to_vec();
+        while let Some(name) = pending.pop() {
+            if let Some(to) = refs.get(name) {
+                if used.insert(name) {
+                    pending.extend(to.iter().map(|r| &**r));
+                }
+            }
+        }
+        self.names()
+            .filter(|n| !used.contains(n))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Looks over the rules as a whole: which of them `starts` never reach, which refer to each
+    /// other in loops, and how deeply their matches nest. Like [`unused`](Self::unused), it
+    /// goes by the handles and references inside each rule.
+    pub fn analyze(&self, starts: &[&str]) -> Analysis {
+        let shapes = self.shapes();
+        let refs = references(&shapes);
+        // every rule each one leads to, itself included only through a loop
+        let reach: BTreeMap<&str, BTreeSet<&str>> = refs
+            .keys()
+            .map(|name| {
+                let mut seen = BTreeSet::new();
+                let mut pending: Vec<&str> = refs[name].iter().map(|r| &**r).collect();
+                while let Some(next) = pending.pop() {
+                    if seen.insert(next) {
+                        pending.extend(refs[next].iter().map(|r| &**r));
+                    }
+                }
+                (*name, seen)
+            })
+            .collect();
+        let looping = |name: &str| reach[name].contains(name);
+
+        let mut cycles: Vec<Vec<String>> = vec![];
+        for name in refs.keys().filter(|n| looping(n)) {
+            if cycles.iter().any(|c| c.iter().any(|m| m == name)) {
+                continue;
+            }
+            cycles.push(
+                reach[name]
+                    .iter()
+                    .filter(|m| reach[**m].contains(name))
+                    .map(|m| String::from(*m))
+                    .collect(),
+            );
+        }
+
+        // the fewest only shrink as more rules are found to finish, so repeat until none do
+        let mut min: BTreeMap<&str, Option<usize>> = refs.keys().map(|n| (*n, None)).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (name, shape) in &shapes {
+                let depth = shape
+                    .depth(&|r| min[r], |ds| ds.into_iter().flatten().min())
+                    .map(|d| d + 1);
+                if depth != min[name] {
+                    min.insert(name, depth);
+                    changed = true;
+                }
+            }
+        }
+
+        // the most are only bounded for rules no loop can be reached from, worked out from
+        // the rules they refer to up
+        let mut max: BTreeMap<&str, usize> = BTreeMap::new();
+        let bounded: Vec<&str> = refs
+            .keys()
+            .filter(|n| !looping(n) && !reach[**n].iter().any(|m| looping(m)))
+            .copied()
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let pending: Vec<&str> = bounded
+                .iter()
+                .filter(|n| !max.contains_key(**n))
+                .copied()
+                .collect();
+            for name in pending {
+                let depth = shapes[&name].depth(&|r| max.get(r).copied(), |ds| {
+                    ds.into_iter().try_fold(0, |most, d| Some(most.max(d?)))
+                });
+                if let Some(depth) = depth {
+                    max.insert(name, depth + 1);
+                    changed = true;
+                }
+            }
+        }
+
+        Analysis {
+            unreachable: self.unused(starts),
+            cycles,
+            depths: refs
+                .keys()
+                .map(|name| {
+                    let depth = Depth {
+                        min: min[name],
+                        max: max.get(name).copied(),
+                    };
+                    (String::from(*name), depth)
+                })
+                .collect(),
+        }
+    }
+
+    fn shapes(&self) -> BTreeMap<&str, Shape> {
+        self.rules
+            .iter()
+            .map(|(name, rule)| (&**name, self.shape(rule.parser(), &mut vec![])))
+            .collect()
+    }
+
+    // What `parser` is made of, looking through handles that aren't in the set. `within` holds
+    // the rules being walked that build themselves afresh for every reference, as a `Deferred`
+    // does, so each is only followed once.
+    fn shape<'a>(&self, parser: &'a dyn Parser<T>, within: &mut Vec<&'a str>) -> Shape {
+        match parser.structure() {
+            Structure::Handle(handle) => {
+                match self.rules.iter().find(|(_, rule)| rule.same(handle)) {
+                    Some((name, _)) => Shape::Rule(name.clone()),
+                    None => self.shape(handle.parser(), within),
+                }
+            }
+            Structure::Reference(name) if self.rules.contains_key(name) => Shape::Rule(name.into()),
+            Structure::Sequence(items) => {
+                Shape::All(items.into_iter().map(|p| self.shape(p, within)).collect())
+            }
+            Structure::Choice(alternatives) => Shape::Any(
+                alternatives
+                    .into_iter()
+                    .map(|p| self.shape(p, within))
+                    .collect(),
+            ),
+            Structure::Repeat(inner) => Shape::All(vec![self.shape(inner, within)]),
+            Structure::Wraps(inner) => {
+                let name = parser.name().unwrap_or_default();
+                if within.contains(&name) {
+                    return Shape::All(vec![]);
+                }
+                within.push(name);
+                let shape = self.shape(inner, within);
+                within.pop();
+                Shape::All(vec![shape])
+            }
+            Structure::Opaque | Structure::Token | Structure::Reference(_) => Shape::All(vec![]),
+        }
+    }
+
+    pub fn parse_all(
+        &self,
+        entry: &str,
+        tokens: &dyn TokenInput<T>,
+    ) -> Result<ParseData<T>, Vec<ParseError>> {
+        self.entry(entry)?.parse_all(tokens)
+    }
+
+    pub fn parse_partial(
+        &self,
+        entry: &str,
+        tokens: &dyn TokenInput<T>,
+    ) -> Result<(ParseData<T>, usize), Vec<ParseError>> {
+        self.entry(entry)?.parse_partial(tokens)
+    }
+
+    fn entry(&self, entry: &str) -> Result<&Shared<T>, Vec<ParseError>> {
+        self.get(entry).ok_or_else(|| {
+            vec![ParseError::new(
+                entry.to_string(),
+                Span::default(),
+                "Unknown entry point",
+            )]
+        })
+    }
+
+    pub const fn new() -> Self {
+        Self {
+            rules: BTreeMap::new(),
+            flags: BTreeSet::new(),
+            slots: RefCell::new(BTreeMap::new()),
+        }
+    }
+}
+
+// The rules of the set each rule refers to.
+fn references<'a>(shapes: &BTreeMap<&'a str, Shape>) -> BTreeMap<&'a str, BTreeSet<Arc<str>>> {
+    shapes
+        .iter()
+        .map(|(name, shape)| {
+            let mut refs = BTreeSet::new();
+            shape.rules(&mut refs);
+            (*name, refs)
+        })
+        .collect()
+}
+
+// Two versions of a rule differ in what they start with, in their snippets, or in an input
+// one generates that the other rejects.
+fn differs<T: PartialEq>(old: &Shared<T>, new: &Shared<T>) -> bool {
+    if old.first_set().dedup() != new.first_set().dedup() || snippet(old) != snippet(new) {
+        return true;
+    }
+    let mut gen = Generator::new(SEED);
+    (0..SAMPLES).any(|_| {
+        [(old, new), (new, old)].into_iter().any(|(from, by)| {
+            gen.tokens(from)
+                .is_some_and(|sample| by.parse_all(&sample).is_err())
+        })
+    })
+}
+
+impl<T: 'static> Default for RuleSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,55 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::parse::ParseData;
+
+// Past this many spare buffers, returned ones are dropped instead of kept.
+const MAX_POOLED: usize = 1024;
+
+/// Spare child buffers for Sequence and Repeatable to build nodes in, shared by every parse
+/// run through a [`Context`](crate::parse::Context) it is attached to. Buffers come back from
+/// attempts that fail and from trees handed to [`recycle`](Self::recycle), so a service parsing
+/// many small inputs stops allocating once the pool has warmed up.
+pub struct Scratch<T> {
+    children: RefCell<Vec<Vec<ParseData<T>>>>,
+}
+
+impl<T> Scratch<T> {
+    pub const fn new() -> Self {
+        Self {
+            children: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Takes a tree that is no longer needed apart, keeping its buffers for later parses.
+    pub fn recycle(&self, data: ParseData<T>) {
+        if let ParseData::Nested(children) = data {
+            self.give(children);
+        }
+    }
+
+    /// Spare buffers waiting to be reused.
+    pub fn pooled(&self) -> usize {
+        self.children.borrow().len()
+    }
+
+    pub(crate) fn take(&self) -> Vec<ParseData<T>> {
+        self.children.borrow_mut().pop().unwrap_or_default()
+    }
+
+    pub(crate) fn give(&self, mut children: Vec<ParseData<T>>) {
+        for child in children.drain(..) {
+            self.recycle(child);
+        }
+        let mut pool = self.children.borrow_mut();
+        if pool.len() < MAX_POOLED && children.capacity() > 0 {
+            pool.push(children);
+        }
+    }
+}
+
+impl<T> Default for Scratch<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
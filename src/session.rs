@@ -0,0 +1,157 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::parse::{ParseData, ParseError, Parser};
+use crate::token::{Lexer, Span, Token};
+
+/// Identifies one file of a [`ParseSession`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(usize);
+
+pub struct SourceFile<T> {
+    name: String,
+    text: String,
+    tree: Option<ParseData<T>>,
+    errors: Vec<ParseError>,
+    included_from: Option<(SourceId, Span)>,
+}
+
+impl<T> SourceFile<T> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The file's tree, if it parsed.
+    pub fn tree(&self) -> Option<&ParseData<T>> {
+        self.tree.as_ref()
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// The file and directive that first pulled this file in; `None` for the main file.
+    pub fn included_from(&self) -> Option<&(SourceId, Span)> {
+        self.included_from.as_ref()
+    }
+}
+
+/// A parse of a main file together with everything it includes, directly or through other
+/// includes. Each file is lexed with `lex` and parsed on its own; `includes` picks the include
+/// directives out of a parsed tree as (path, span of the directive) pairs. A file included
+/// twice is parsed once.
+pub struct ParseSession<T> {
+    files: Vec<SourceFile<T>>,
+    lex: Lexer<T>,
+    includes: fn(&ParseData<T>) -> Vec<(String, Span)>,
+}
+
+impl<T> ParseSession<T> {
+    /// Parses `text` as the file `name`, then every file it includes. `resolve` is given an
+    /// include path and the name of the including file and returns the included text, or
+    /// `None` to report the include as unresolved.
+    pub fn parse(
+        &mut self,
+        name: &str,
+        text: String,
+        parser: &dyn Parser<T>,
+        mut resolve: impl FnMut(&str, &str) -> Option<String>,
+    ) -> SourceId {
+        let main = self.load(name.to_string(), text, parser, None);
+        let mut pending = vec![main];
+
+        while let Some(id) = pending.pop() {
+            let Some(tree) = &self.files[id.0].tree else {
+                continue;
+            };
+            for (path, span) in (self.includes)(tree) {
+                if self.id(&path).is_some() {
+                    continue;
+                }
+                match resolve(&path, &self.files[id.0].name) {
+                    Some(text) => pending.push(self.load(path, text, parser, Some((id, span)))),
+                    None => {
+                        let error = ParseError::from(path.clone(), span)
+                            .with_message(format!("Cannot resolve include {path}"));
+                        self.files[id.0].errors.push(error);
+                    }
+                }
+            }
+        }
+        main
+    }
+
+    fn load(
+        &mut self,
+        name: String,
+        text: String,
+        parser: &dyn Parser<T>,
+        included_from: Option<(SourceId, Span)>,
+    ) -> SourceId {
+        let (tree, errors) = match parser.parse_all(&(self.lex)(&text)) {
+            Ok(tree) => (Some(tree), vec![]),
+            Err(errors) => (None, errors),
+        };
+        self.files.push(SourceFile {
+            name,
+            text,
+            tree,
+            errors,
+            included_from,
+        });
+        SourceId(self.files.len() - 1)
+    }
+
+    pub fn id(&self, name: &str) -> Option<SourceId> {
+        self.files.iter().position(|f| f.name == name).map(SourceId)
+    }
+
+    pub fn file(&self, id: SourceId) -> &SourceFile<T> {
+        &self.files[id.0]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (SourceId, &SourceFile<T>)> {
+        self.files.iter().enumerate().map(|(i, f)| (SourceId(i), f))
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.files.iter().any(|f| !f.errors.is_empty())
+    }
+
+    /// Every error in the session, each naming its file and followed by the chain of includes
+    /// that led to it.
+    pub fn render_errors(&self) -> Vec<String> {
+        let mut out = vec![];
+        for file in &self.files {
+            for error in &file.errors {
+                let mut rendered = format!("{}: {error}", file.name);
+                let mut from = file.included_from.as_ref();
+                while let Some((id, span)) = from {
+                    let parent = &self.files[id.0];
+                    rendered.push_str(&format!("\n  included from {} at {span}", parent.name));
+                    from = parent.included_from.as_ref();
+                }
+                out.push(rendered);
+            }
+        }
+        out
+    }
+
+    pub fn new(
+        lex: impl Fn(&str) -> Vec<Token<T>> + 'static,
+        includes: fn(&ParseData<T>) -> Vec<(String, Span)>,
+    ) -> Self {
+        Self {
+            files: Vec::new(),
+            lex: Box::new(lex),
+            includes,
+        }
+    }
+}
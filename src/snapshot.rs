@@ -0,0 +1,105 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{Debug, Write};
+
+use crate::input::TokenInput;
+use crate::parse::{ParseData, ParseError, Parser};
+use crate::token::Token;
+
+/// Renders a tree as an indented S-expression. Token types use their `Debug` form; spans are
+/// appended as `@ln:cs-ce` when `spans` is set. The output depends only on the tree, so it is
+/// suitable for golden files and snapshot tools.
+pub fn render<T: Debug>(data: &ParseData<T>, spans: bool) -> String {
+    let mut out = String::new();
+    write_node(&mut out, data, spans, 0);
+    out
+}
+
+/// Runs `parser` over all of `tokens` and renders either the tree or one `error:` line per
+/// error.
+pub fn parse_snapshot<T: Debug>(
+    parser: &dyn Parser<T>,
+    tokens: &dyn TokenInput<T>,
+    spans: bool,
+) -> String {
+    match parser.parse_all(tokens) {
+        Ok(data) => render(&data, spans),
+        Err(errors) => render_errors(&errors),
+    }
+}
+
+/// Renders one `error:` line per error, as [`parse_snapshot`] does for failed parses.
+pub fn render_errors(errors: &[ParseError]) -> String {
+    errors
+        .iter()
+        .map(|e| {
+            format!(
+                "error: expected {} at {}: {}\n",
+                e.expected(),
+                e.span(),
+                e.message()
+            )
+        })
+        .collect()
+}
+
+fn write_token<T: Debug>(out: &mut String, token: &Token<T>, spans: bool) {
+    let _ = write!(out, "{:?}", token.ty());
+    if spans {
+        let _ = write!(out, "@{}", token.span());
+    }
+}
+
+fn write_node<T: Debug>(out: &mut String, data: &ParseData<T>, spans: bool, depth: usize) {
+    match data {
+        ParseData::Token(t) => write_token(out, t, spans),
+        ParseData::TokenList(l) => {
+            out.push_str("(tokens");
+            for t in l {
+                out.push(' ');
+                write_token(out, t, spans);
+            }
+            out.push(')');
+        }
+        ParseData::Error {
+            expected,
+            span,
+            skipped,
+            message,
+        } => {
+            let _ = write!(out, "(error {expected:?}");
+            if let Some(message) = message {
+                let _ = write!(out, " {message:?}");
+            }
+            if spans {
+                let _ = write!(out, "@{span}");
+            }
+            for t in skipped {
+                out.push(' ');
+                write_token(out, t, spans);
+            }
+            out.push(')');
+        }
+        ParseData::Nested(l) if l.iter().all(|d| matches!(d, ParseData::Token(_))) => {
+            out.push('(');
+            for (i, d) in l.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_node(out, d, spans, depth);
+            }
+            out.push(')');
+        }
+        ParseData::Nested(l) => {
+            out.push('(');
+            for d in l {
+                out.push('\n');
+                out.extend(core::iter::repeat_n(' ', (depth + 1) * 2));
+                write_node(out, d, spans, depth + 1);
+            }
+            out.push('\n');
+            out.extend(core::iter::repeat_n(' ', depth * 2));
+            out.push(')');
+        }
+    }
+}
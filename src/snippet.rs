@@ -0,0 +1,67 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::parse::Parser;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnippetPart<T> {
+    /// A token the rule requires as is.
+    Token(T),
+    /// A slot for the user to fill in, named after the rule that matches it.
+    Placeholder(Arc<str>),
+}
+
+/// A template for what a rule matches, for editor completion: its fixed tokens, with
+/// placeholders for identifiers, values and the like. Optional parts are left out, repetitions
+/// appear once and choices take their first alternative.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snippet<T> {
+    parts: Vec<SnippetPart<T>>,
+}
+
+impl<T> Snippet<T> {
+    pub fn parts(&self) -> &[SnippetPart<T>] {
+        &self.parts
+    }
+
+    /// Spells the snippet out with `text` giving each token's text and `slot` wrapping each
+    /// placeholder, given its 1-based number and name.
+    pub fn render_with(
+        &self,
+        text: impl Fn(&T) -> String,
+        slot: impl Fn(usize, &str) -> String,
+    ) -> String {
+        let mut slots = 0;
+        let parts: Vec<String> = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                SnippetPart::Token(ty) => text(ty),
+                SnippetPart::Placeholder(name) => {
+                    slots += 1;
+                    slot(slots, name)
+                }
+            })
+            .collect();
+        parts.join(" ")
+    }
+
+    /// Placeholders as `<name>`, for showing in a completion list.
+    pub fn render(&self, text: impl Fn(&T) -> String) -> String {
+        self.render_with(text, |_, name| format!("<{name}>"))
+    }
+
+    /// Placeholders as numbered tab stops in the snippet syntax LSP clients understand.
+    pub fn render_tabstops(&self, text: impl Fn(&T) -> String) -> String {
+        self.render_with(text, |i, name| format!("${{{i}:{name}}}"))
+    }
+}
+
+/// The snippet for what `parser` matches, or `None` when part of it can't be described, as
+/// with hand-written parsers that don't implement [`Parser::snippet`].
+pub fn snippet<T>(parser: &dyn Parser<T>) -> Option<Snippet<T>> {
+    let mut parts = Vec::new();
+    parser.snippet(&mut parts).then_some(Snippet { parts })
+}
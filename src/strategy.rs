@@ -0,0 +1,36 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use proptest::prelude::*;
+
+use crate::generate::Generator;
+use crate::parse::Shared;
+use crate::token::Token;
+
+/// Inputs `parser` accepts, produced by [`Generator`] from a random seed. Shrinking only
+/// simplifies the seed, so a shrunk case is not necessarily a smaller input.
+pub fn inputs<T>(parser: Shared<T>) -> impl Strategy<Value = Vec<Token<T>>>
+where
+    T: Debug + 'static,
+{
+    any::<u64>().prop_filter_map("parser cannot generate input", move |seed| {
+        Generator::new(seed).tokens(&parser)
+    })
+}
+
+/// Like [`inputs`], with explicit depth and repetition limits.
+pub fn inputs_with<T>(
+    parser: Shared<T>,
+    max_depth: usize,
+    max_repeat: usize,
+) -> impl Strategy<Value = Vec<Token<T>>>
+where
+    T: Debug + 'static,
+{
+    any::<u64>().prop_filter_map("parser cannot generate input", move |seed| {
+        Generator::new(seed)
+            .with_max_depth(max_depth)
+            .with_max_repeat(max_repeat)
+            .tokens(&parser)
+    })
+}
@@ -1,6 +1,29 @@
-use std::fmt::{Display, Formatter};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{Debug, Display, Formatter};
+use core::hash::{Hash, Hasher};
 
-#[derive(Clone)]
+use crate::column::ColumnPolicy;
+
+#[cfg(feature = "derive")]
+pub use roder_derive::TokenKind;
+
+pub type Meta = Arc<dyn Any + Send + Sync>;
+
+pub type Lexer<T> = Box<dyn Fn(&str) -> Vec<Token<T>>>;
+
+pub trait HasKind {
+    type Kind: PartialEq;
+
+    fn kind(&self) -> Self::Kind;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     ln: usize,
     cs: usize,
@@ -20,6 +43,45 @@ impl Span {
         self.ce
     }
 
+    pub const fn contains(&self, ln: usize, col: usize) -> bool {
+        self.ln == ln && self.cs <= col && col <= self.ce
+    }
+
+    pub const fn contains_span(&self, other: &Span) -> bool {
+        self.ln == other.ln && self.cs <= other.cs && other.ce <= self.ce
+    }
+
+    pub const fn overlaps(&self, other: &Span) -> bool {
+        self.ln == other.ln && self.cs <= other.ce && other.cs <= self.ce
+    }
+
+    /// The text this span covers in `source`, taking columns as 1-based character positions
+    /// within the line. `None` if the span lies outside `source`.
+    pub fn text<'s>(&self, source: &'s str) -> Option<&'s str> {
+        self.text_with(source, ColumnPolicy::Chars)
+    }
+
+    /// Like [`text`](Span::text), with columns counted by `columns`.
+    pub fn text_with<'s>(&self, source: &'s str, columns: ColumnPolicy) -> Option<&'s str> {
+        let line = source.lines().nth(self.ln.checked_sub(1)?)?;
+        let start = columns.byte_offset(line, self.cs)?;
+        let end = columns.byte_offset(line, self.ce.checked_add(1)?)?;
+        line.get(start..end)
+    }
+
+    /// The same stretch of `source` with columns counted by `to` instead of `from`. `None` if
+    /// the span lies outside `source` or splits a unit of `from`.
+    pub fn convert(&self, source: &str, from: ColumnPolicy, to: ColumnPolicy) -> Option<Span> {
+        let line = source.lines().nth(self.ln.checked_sub(1)?)?;
+        let start = from.byte_offset(line, self.cs)?;
+        let end = from.byte_offset(line, self.ce.checked_add(1)?)?;
+        Some(Span::new(
+            self.ln,
+            to.column(line, start),
+            to.column(line, end) - 1,
+        ))
+    }
+
     pub const fn new(ln: usize, cs: usize, ce: usize) -> Self {
         Self { ln, cs, ce }
     }
@@ -32,19 +94,84 @@ impl Default for Span {
 }
 
 impl Display for Span {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}:{}-{}", self.ln, self.cs, self.ce)
     }
 }
 
+/// Where a token synthesized by preprocessing (a macro expansion, an include, a desugaring)
+/// came from. Nested expansions link to the provenance of the token they were expanded from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Provenance {
+    span: Span,
+    reason: Cow<'static, str>,
+    parent: Option<Arc<Provenance>>,
+    inserted: bool,
+}
+
+impl Provenance {
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Why the token was synthesized, or for a token error recovery inserted, the rule that
+    /// was missing.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Whether error recovery made the token up to stand in for a missing one.
+    pub fn is_inserted(&self) -> bool {
+        self.inserted
+    }
+
+    pub fn parent(&self) -> Option<&Arc<Provenance>> {
+        self.parent.as_ref()
+    }
+
+    /// This link and every one it was expanded from, innermost first.
+    pub fn chain(&self) -> impl Iterator<Item = &Provenance> {
+        core::iter::successors(Some(self), |p| p.parent.as_deref())
+    }
+
+    pub fn with_parent(mut self, parent: Arc<Provenance>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn new(span: Span, reason: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            span,
+            reason: reason.into(),
+            parent: None,
+            inserted: false,
+        }
+    }
+
+    /// Marks a token error recovery inserted at `span` where `expected` was missing.
+    pub fn inserted(span: Span, expected: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            inserted: true,
+            ..Self::new(span, expected)
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token<T> {
     ty: T,
     span: Span,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    meta: Option<Meta>,
+    leading: Vec<Token<T>>,
+    trailing: Vec<Token<T>>,
+    provenance: Option<Arc<Provenance>>,
 }
 
 impl<T> Token<T> {
     pub const fn span_size(&self) -> usize {
-        self.span.ce - self.span.cs + 1
+        self.span.ce + 1 - self.span.cs
     }
 
     pub const fn ty(&self) -> &T {
@@ -55,8 +182,107 @@ impl<T> Token<T> {
         &self.span
     }
 
+    pub fn meta<M: Any>(&self) -> Option<&M> {
+        self.meta.as_ref().and_then(|m| m.downcast_ref())
+    }
+
+    pub fn raw_meta(&self) -> Option<&Meta> {
+        self.meta.as_ref()
+    }
+
+    pub fn with_meta<M: Any + Send + Sync>(self, meta: M) -> Self {
+        self.with_raw_meta(Arc::new(meta))
+    }
+
+    pub fn with_raw_meta(mut self, meta: Meta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    pub fn provenance(&self) -> Option<&Arc<Provenance>> {
+        self.provenance.as_ref()
+    }
+
+    /// Marks the token as synthesized; `provenance` can be shared by every token of one
+    /// expansion.
+    pub fn with_provenance(mut self, provenance: Arc<Provenance>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    pub fn leading(&self) -> &[Token<T>] {
+        &self.leading
+    }
+
+    pub fn trailing(&self) -> &[Token<T>] {
+        &self.trailing
+    }
+
+    /// Converts the token's type, and those of its trivia, keeping everything else.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Token<U> {
+        self.map_with(&mut f)
+    }
+
+    fn map_with<U>(self, f: &mut impl FnMut(T) -> U) -> Token<U> {
+        Token {
+            ty: f(self.ty),
+            span: self.span,
+            meta: self.meta,
+            leading: self.leading.into_iter().map(|t| t.map_with(f)).collect(),
+            trailing: self.trailing.into_iter().map(|t| t.map_with(f)).collect(),
+            provenance: self.provenance,
+        }
+    }
+
+    pub fn with_ty(mut self, ty: T) -> Self {
+        self.ty = ty;
+        self
+    }
+
+    pub(crate) fn absorb(&mut self, other: Token<T>, ty: T) {
+        self.ty = ty;
+        if self.span.ln == other.span.ln {
+            self.span.ce = self.span.ce.max(other.span.ce);
+        }
+        self.trailing.extend(other.leading);
+        self.trailing.extend(other.trailing);
+    }
+
+    /// Moves a token positioned relative to `outer`, whose first character is 1:1, to where it
+    /// really is.
+    pub(crate) fn relocate(&mut self, outer: &Span) {
+        if self.span.ln == 1 {
+            self.span.cs += outer.cs - 1;
+            self.span.ce += outer.cs - 1;
+        }
+        self.span.ln += outer.ln - 1;
+        for t in self.leading.iter_mut().chain(self.trailing.iter_mut()) {
+            t.relocate(outer);
+        }
+    }
+
+    pub(crate) fn shift_lines(&mut self, delta: isize) {
+        self.span.ln = self.span.ln.saturating_add_signed(delta);
+        for t in self.leading.iter_mut().chain(self.trailing.iter_mut()) {
+            t.shift_lines(delta);
+        }
+    }
+
+    pub fn with_trivia(mut self, leading: Vec<Token<T>>, trailing: Vec<Token<T>>) -> Self {
+        self.leading = leading;
+        self.trailing = trailing;
+        self
+    }
+
     pub const fn new(ty: T, span: Span) -> Self {
-        Self { ty, span }
+        Self {
+            ty,
+            span,
+            meta: None,
+            leading: Vec::new(),
+            trailing: Vec::new(),
+            provenance: None,
+        }
     }
 }
 
@@ -65,6 +291,96 @@ where
     T: Clone,
 {
     fn clone(&self) -> Self {
-        Self::new(self.ty.clone(), self.span.clone())
+        Self {
+            ty: self.ty.clone(),
+            span: self.span.clone(),
+            meta: self.meta.clone(),
+            leading: self.leading.clone(),
+            trailing: self.trailing.clone(),
+            provenance: self.provenance.clone(),
+        }
+    }
+}
+
+impl<T> Debug for Token<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut d = f.debug_struct("Token");
+        d.field("ty", &self.ty).field("span", &self.span);
+        if self.meta.is_some() {
+            d.field("meta", &"..");
+        }
+        if !self.leading.is_empty() {
+            d.field("leading", &self.leading);
+        }
+        if !self.trailing.is_empty() {
+            d.field("trailing", &self.trailing);
+        }
+        if let Some(provenance) = &self.provenance {
+            d.field("provenance", provenance);
+        }
+        d.finish()
+    }
+}
+
+// Metadata is opaque and provenance is only for diagnostics, so neither takes part in
+// comparisons.
+impl<T> PartialEq for Token<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+            && self.span == other.span
+            && self.leading == other.leading
+            && self.trailing == other.trailing
+    }
+}
+
+impl<T> Eq for Token<T> where T: Eq {}
+
+impl<T> Hash for Token<T>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ty.hash(state);
+        self.span.hash(state);
+        self.leading.hash(state);
+        self.trailing.hash(state);
+    }
+}
+
+/// Moves each token's type behind an `Arc`, for token types that can't or shouldn't be cloned
+/// (large payloads, handles). The parse tree then shares payloads with the input instead of
+/// copying them, and parsers are written against `Arc<T>`.
+pub fn share_types<T>(tokens: Vec<Token<T>>) -> Vec<Token<Arc<T>>> {
+    tokens.into_iter().map(|t| t.map(Arc::new)).collect()
+}
+
+pub fn attach_trivia<T>(tokens: Vec<Token<T>>, is_trivia: fn(&T) -> bool) -> Vec<Token<T>> {
+    let mut out: Vec<Token<T>> = vec![];
+    let mut pending = vec![];
+
+    for mut token in tokens {
+        if !is_trivia(token.ty()) {
+            token.leading = core::mem::take(&mut pending);
+            out.push(token);
+            continue;
+        }
+
+        match out.last_mut() {
+            Some(prev) if pending.is_empty() && prev.span().ln() == token.span().ln() => {
+                prev.trailing.push(token)
+            }
+            _ => pending.push(token),
+        }
+    }
+
+    if let Some(last) = out.last_mut() {
+        last.trailing.append(&mut pending);
     }
+    out
 }
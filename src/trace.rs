@@ -0,0 +1,190 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt::{Display, Formatter};
+
+use crate::parse::ParseResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    Ok,
+    Err,
+    None,
+}
+
+impl Outcome {
+    pub fn of<T>(result: &ParseResult<T>) -> Self {
+        match result {
+            ParseResult::Ok(_) => Outcome::Ok,
+            ParseResult::Err(_) => Outcome::Err,
+            ParseResult::None => Outcome::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Step {
+    Enter {
+        rule: Arc<str>,
+        depth: usize,
+        offset: usize,
+    },
+    Exit {
+        rule: Arc<str>,
+        depth: usize,
+        start: usize,
+        end: usize,
+        outcome: Outcome,
+    },
+}
+
+impl Step {
+    pub fn rule(&self) -> &str {
+        match self {
+            Step::Enter { rule, .. } | Step::Exit { rule, .. } => rule,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            Step::Enter { depth, .. } | Step::Exit { depth, .. } => *depth,
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let indent = self.depth() * 2;
+        match self {
+            Step::Enter { rule, offset, .. } => write!(f, "{:indent$}> {rule} @{offset}", ""),
+            Step::Exit {
+                rule,
+                start,
+                end,
+                outcome,
+                ..
+            } => write!(f, "{:indent$}< {rule} {start}..{end} {outcome:?}", ""),
+        }
+    }
+}
+
+/// Records every rule attempt made through a [`Context`](crate::parse::Context) it is attached
+/// to, in the order they happen.
+#[derive(Default)]
+pub struct TraceRecorder {
+    steps: RefCell<Vec<Step>>,
+    depth: Cell<usize>,
+}
+
+impl TraceRecorder {
+    pub(crate) fn enter(&self, rule: &Arc<str>, offset: usize) {
+        let depth = self.depth.get();
+        self.steps.borrow_mut().push(Step::Enter {
+            rule: rule.clone(),
+            depth,
+            offset,
+        });
+        self.depth.set(depth + 1);
+    }
+
+    pub(crate) fn exit(&self, rule: &Arc<str>, start: usize, end: usize, outcome: Outcome) {
+        let depth = self.depth.get() - 1;
+        self.depth.set(depth);
+        self.steps.borrow_mut().push(Step::Exit {
+            rule: rule.clone(),
+            depth,
+            start,
+            end,
+            outcome,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.borrow().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.steps.borrow_mut().clear();
+    }
+
+    pub fn into_steps(self) -> Vec<Step> {
+        self.steps.into_inner()
+    }
+
+    pub fn replay(&self) -> Replay {
+        Replay {
+            steps: self.steps.borrow().clone(),
+            position: 0,
+        }
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Display for TraceRecorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for step in self.steps.borrow().iter() {
+            writeln!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A cursor over recorded steps that can move in both directions.
+pub struct Replay {
+    steps: Vec<Step>,
+    position: usize,
+}
+
+impl Replay {
+    pub fn current(&self) -> Option<&Step> {
+        self.position.checked_sub(1).and_then(|i| self.steps.get(i))
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn forward(&mut self) -> Option<&Step> {
+        if self.position == self.steps.len() {
+            return None;
+        }
+        self.position += 1;
+        self.current()
+    }
+
+    pub fn back(&mut self) -> Option<&Step> {
+        self.position = self.position.checked_sub(1)?;
+        self.current()
+    }
+
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.steps.len());
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// The rules entered but not yet exited at the current position, outermost first.
+    pub fn stack(&self) -> Vec<&Step> {
+        let mut stack = Vec::new();
+        for step in &self.steps[..self.position] {
+            match step {
+                Step::Enter { .. } => stack.push(step),
+                Step::Exit { .. } => {
+                    stack.pop();
+                }
+            }
+        }
+        stack
+    }
+}
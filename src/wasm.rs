@@ -0,0 +1,55 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::json::result_to_json;
+use crate::parse::Parser;
+use crate::token::{Span, Token};
+
+/// A grammar for a browser playground. Build one in Rust and return it from a `#[wasm_bindgen]`
+/// function of your own; JavaScript then calls `parse` on the sample input as the user edits
+/// it. Grammars are Rust code, so editing the grammar itself still means rebuilding.
+#[wasm_bindgen]
+pub struct Playground {
+    parser: Box<dyn Parser<String>>,
+}
+
+impl Playground {
+    /// A playground over `parser`, which sees the input as the [`words`] in it.
+    pub fn new(parser: Box<dyn Parser<String>>) -> Self {
+        Self { parser }
+    }
+}
+
+#[wasm_bindgen]
+impl Playground {
+    /// Parses `input` and gives the tree or the errors as JSON, as
+    /// [`result_to_json`](crate::json::result_to_json) writes them.
+    pub fn parse(&self, input: &str) -> String {
+        result_to_json(&self.parser.parse_all(&words(input)))
+            .expect("trees of string tokens always serialize")
+    }
+}
+
+/// Splits `input` on whitespace into one token per word, spanning where the word sits, with
+/// columns counting characters from 1.
+pub fn words(input: &str) -> Vec<Token<String>> {
+    let mut tokens = Vec::new();
+    for (ln, line) in input.lines().enumerate() {
+        let mut start = None;
+        for (col, c) in line.chars().chain(Some(' ')).enumerate() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(col),
+                (true, Some(cs)) => {
+                    let word: String = line.chars().skip(cs).take(col - cs).collect();
+                    tokens.push(Token::new(word, Span::new(ln + 1, cs + 1, col)));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+    }
+    tokens
+}
@@ -0,0 +1,62 @@
+use roder::backtrack::worst_case;
+use roder::generate::Generator;
+use roder::parse::{Parser, Repeatable};
+use roder::prelude::*;
+
+fn items(item: Box<dyn Parser<u8>>) -> Repeatable<u8> {
+    Repeatable::from("items", true, item)
+}
+
+#[test]
+fn overlapping_prefixes_cost_more_than_factored_ones() {
+    // item := 1 1 1 2 | 1 1 1 3 | 1 1 1 4, against item := 1 1 1 (2 | 3 | 4)
+    let tail = |t: u8| seq([just(1u8), just(1u8), just(1u8), just(t)]);
+    let overlapping = items(Box::new(choice([tail(2), tail(3), tail(4)])));
+    let factored = items(Box::new(seq([
+        just(1u8),
+        just(1u8),
+        just(1u8),
+        choice([just(2u8), just(3u8), just(4u8)]),
+    ])));
+
+    let worst = worst_case(&overlapping, &mut Generator::new(5), 200).unwrap();
+    let best = worst_case(&factored, &mut Generator::new(5), 200).unwrap();
+    assert!(worst.steps_per_token() > best.steps_per_token());
+    assert!(!worst.hit_cap());
+
+    // repeating the input doubles its length and, for this grammar, about doubles the work
+    let lengths: Vec<usize> = worst.growth.iter().map(|(len, _)| *len).collect();
+    let n = worst.input.len();
+    assert_eq!(lengths, [n, 2 * n, 4 * n, 8 * n]);
+    assert!(worst.growth_ratio() < 3.0);
+}
+
+#[test]
+fn overlapping_alternatives_and_nested_repeats_are_spots() {
+    let tail = |t: u8| seq([just(1u8), just(1u8), just(t)]);
+    let overlapping = items(Box::new(choice([tail(2), tail(3)])));
+    let report = worst_case(&overlapping, &mut Generator::new(5), 0).unwrap();
+    let spots: Vec<Vec<u8>> = report
+        .spots
+        .iter()
+        .map(|s| s.input.iter().map(|t| *t.ty()).collect())
+        .collect();
+    // input for the second alternative, once, inside the repetition
+    assert_eq!(spots, [[1, 1, 3]]);
+    assert!(report.spots[0].steps > 3);
+
+    // a repetition of something that may match nothing
+    let nested = items(Box::new(Repeatable::from("inner", true, just(1u8))));
+    let report = worst_case(&nested, &mut Generator::new(5), 0).unwrap();
+    assert_eq!(report.spots.len(), 1);
+    assert_eq!(report.spots[0].rule, "items");
+    assert!(report.spots[0].input.len() >= 8);
+
+    let factored = items(Box::new(seq([
+        just(1u8),
+        just(1u8),
+        choice([just(2u8), just(3u8)]),
+    ])));
+    let report = worst_case(&factored, &mut Generator::new(5), 0).unwrap();
+    assert!(report.spots.is_empty());
+}
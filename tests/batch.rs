@@ -0,0 +1,52 @@
+mod common;
+
+use common::tokens;
+use roder::batch::{documents, documents_in};
+use roder::prelude::*;
+
+#[test]
+fn documents_are_parsed_one_after_another() {
+    // record := 1 2+ ; records are separated by 0
+    let record = seq([just(1u8), many(just(2u8))]);
+    let input = tokens(&[1, 2, 2, 0, 1, 3, 2, 0, 0, 1, 2]);
+
+    let results: Vec<_> = documents(&*record, &input, |t| *t == 0).collect();
+    let ranges: Vec<_> = results.iter().map(|(r, _)| r.clone()).collect();
+    assert_eq!(ranges, [0..3, 4..7, 9..11]);
+
+    assert!(results[0].1.is_ok() && results[2].1.is_ok());
+    let error = results[1].1.as_ref().err().unwrap();
+    assert_eq!(error.span(), &Span::new(1, 6, 6));
+}
+
+#[test]
+fn leftovers_are_errors_of_their_own_document() {
+    let record = seq([just(1u8), many(just(2u8))]);
+    let input = tokens(&[1, 2, 3, 3, 0, 1, 2]);
+
+    let results: Vec<_> = documents(&*record, &input, |t| *t == 0).collect();
+    let ranges: Vec<_> = results.iter().map(|(r, _)| r.clone()).collect();
+    assert_eq!(ranges, [0..4, 5..7]);
+    let error = results[0].1.as_ref().err().unwrap();
+    assert_eq!(
+        (error.message(), error.span()),
+        ("Unexpected trailing input", &Span::new(1, 3, 3))
+    );
+}
+
+#[test]
+fn documents_are_parsed_in_copies_of_a_template() {
+    // 9 is whitespace; each document gets a budget of its own
+    let record = seq([just(1u8), many(just(2u8))]);
+    let input = tokens(&[9, 1, 9, 2, 9, 0, 9, 1, 2, 2, 2, 2, 2, 0, 1, 2]);
+    let template = Context::new(&input)
+        .with_skippable(|t| *t == 9)
+        .with_step_budget(6);
+
+    let results: Vec<_> = documents_in(&*record, template, |t| *t == 0).collect();
+    let ranges: Vec<_> = results.iter().map(|(r, _)| r.clone()).collect();
+    assert_eq!(ranges, [1..4, 7..13, 14..16]);
+    assert!(results[0].1.is_ok() && results[2].1.is_ok());
+    let error = results[1].1.as_ref().err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+}
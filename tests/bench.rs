@@ -0,0 +1,25 @@
+use roder::bench::{bench, CountingAllocator};
+use roder::prelude::*;
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+fn doc(n: usize) -> Vec<Token<u8>> {
+    (0..n)
+        .map(|i| Token::new((i % 2) as u8, Span::new(1, i + 1, i + 1)))
+        .collect()
+}
+
+#[test]
+fn bench_reports_corpus_statistics() {
+    let parser = many(seq([just(0u8), just(1u8)]));
+    let report = bench(&parser, &[doc(4), doc(10), doc(3)]);
+
+    assert_eq!(report.documents, 3);
+    assert_eq!(report.tokens, 17);
+    assert_eq!(report.max_depth, 2);
+    assert_eq!(report.peak_nodes, 1 + 5 * 3);
+    assert!(report.allocations.is_some());
+    assert_eq!(report.hot_rules[0].0.as_ref(), "0");
+    assert!(report.tokens_per_second() > 0.0);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::tokens;
+use roder::cache::{source_key, token_key, ParseCache};
+use roder::prelude::*;
+
+#[test]
+fn unchanged_input_is_not_parsed_again() {
+    let grammar = seq([just(1u8), many(just(2u8))]);
+    let mut cache = ParseCache::new(1);
+
+    assert_eq!(
+        cache
+            .parse_all(&*grammar, &tokens(&[1, 2]))
+            .ok()
+            .unwrap()
+            .node_count(),
+        4
+    );
+    assert_eq!(
+        cache
+            .parse_all(&*grammar, &tokens(&[1, 2]))
+            .ok()
+            .unwrap()
+            .node_count(),
+        4
+    );
+    assert!(cache.parse_all(&*grammar, &tokens(&[2])).is_err());
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 2, 1));
+
+    // moving a token changes the key, since cached trees carry spans
+    let mut moved = tokens(&[1, 2]);
+    moved[1] = Token::new(2, Span::new(2, 1, 1));
+    assert_ne!(token_key(&moved), token_key(&tokens(&[1, 2])));
+    assert_eq!(token_key(&tokens(&[1, 2])), token_key(&tokens(&[1, 2])));
+
+    cache.insert(source_key("1 2"), ParseData::TokenList(tokens(&[1])));
+    assert!(cache.get(source_key("1 2")).is_some());
+    assert!(cache.get(source_key("1 2 2")).is_none());
+
+    assert_eq!(cache.for_version(1).len(), 2);
+    assert!(ParseCache::<u8>::new(1).for_version(2).is_empty());
+}
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 #[derive(Clone)]
 enum TokenType {
     Identifier(String),
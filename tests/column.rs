@@ -0,0 +1,77 @@
+use roder::column::ColumnPolicy;
+use roder::prelude::*;
+
+#[test]
+fn policies_count_columns_differently() {
+    // e + combining acute, a family emoji joined with ZWJ, a CJK character, then `x`
+    let line = "e\u{301}👨\u{200D}👩 中x";
+
+    let col_of_x = |policy: ColumnPolicy| policy.column(line, line.len() - 1);
+    assert_eq!(col_of_x(ColumnPolicy::Bytes), line.len());
+    assert_eq!(col_of_x(ColumnPolicy::Chars), 8);
+    assert_eq!(col_of_x(ColumnPolicy::Graphemes), 5);
+    assert_eq!(col_of_x(ColumnPolicy::Width), 7);
+
+    for policy in [
+        ColumnPolicy::Bytes,
+        ColumnPolicy::Chars,
+        ColumnPolicy::Graphemes,
+        ColumnPolicy::Width,
+    ] {
+        let col = col_of_x(policy);
+        assert_eq!(Span::new(1, col, col).text_with(line, policy), Some("x"));
+    }
+    assert_eq!(
+        Span::new(1, 2, 2).text_with(line, ColumnPolicy::Graphemes),
+        Some("👨\u{200D}👩")
+    );
+    assert_eq!(ColumnPolicy::Width.byte_offset(line, 3), None);
+}
+
+#[test]
+fn carets_line_up_under_wide_characters() {
+    let source = "中文 = ;";
+    // lexed with grapheme columns: `=` is column 4, `;` column 6
+    let input = vec![
+        Token::new(1u8, Span::new(1, 1, 2)),
+        Token::new(2u8, Span::new(1, 4, 4)),
+        Token::new(3u8, Span::new(1, 6, 6)),
+    ];
+    let ctx = Context::new(&input)
+        .with_source(source)
+        .with_columns(ColumnPolicy::Graphemes);
+
+    assert_eq!(ctx.token_text(0), Some("中文"));
+    let error = just(4u8).parse(&ctx, 2).into_result().err().unwrap();
+    assert!(ctx
+        .render_error(&error)
+        .ends_with("\n1 | 中文 = ;\n  |        ^"));
+}
+
+#[test]
+fn spans_convert_between_encodings() {
+    // `é` is 2 bytes and 1 UTF-16 unit, `𝕏` 4 bytes and 2 units
+    let source = "é𝕏 = x";
+    let x = Span::new(1, 6, 6);
+    assert_eq!(x.text(source), Some("x"));
+    assert_eq!(
+        x.convert(source, ColumnPolicy::Chars, ColumnPolicy::Utf16),
+        Some(Span::new(1, 7, 7))
+    );
+    assert_eq!(
+        x.convert(source, ColumnPolicy::Chars, ColumnPolicy::Bytes),
+        Some(Span::new(1, 10, 10))
+    );
+
+    let wide = Span::new(1, 2, 3);
+    assert_eq!(wide.text_with(source, ColumnPolicy::Utf16), Some("𝕏"));
+    assert_eq!(
+        wide.convert(source, ColumnPolicy::Utf16, ColumnPolicy::Chars),
+        Some(Span::new(1, 2, 2))
+    );
+    // half a surrogate pair is no position at all
+    assert_eq!(
+        Span::new(1, 2, 2).convert(source, ColumnPolicy::Utf16, ColumnPolicy::Chars),
+        None
+    );
+}
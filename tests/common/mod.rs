@@ -0,0 +1,9 @@
+use roder::token::{Span, Token};
+
+/// One token per type, side by side on the first line.
+pub fn tokens<T: Clone>(tys: &[T]) -> Vec<Token<T>> {
+    tys.iter()
+        .enumerate()
+        .map(|(i, ty)| Token::new(ty.clone(), Span::new(1, i + 1, i + 1)))
+        .collect()
+}
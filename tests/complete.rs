@@ -0,0 +1,50 @@
+use roder::complete::{expected_at, Expected};
+use roder::parse::Predicate;
+use roder::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Let,
+    Id,
+    Eq,
+    Num,
+    Str,
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[test]
+fn completion_lists_every_viable_continuation() {
+    let value = choice([
+        just(Tok::Num),
+        just(Tok::Str),
+        Box::new(Predicate::from("identifier", false, |t| *t == Tok::Id)),
+        just(Tok::Num),
+    ]);
+    let stmt = seq([just(Tok::Let), just(Tok::Id), just(Tok::Eq), value]);
+    let tokens: Vec<_> = [Tok::Let, Tok::Id, Tok::Eq, Tok::Num]
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| Token::new(t, Span::new(1, i + 1, i + 1)))
+        .collect();
+
+    assert_eq!(
+        expected_at(&stmt, &tokens, 2),
+        vec![Expected::Token(Tok::Eq)]
+    );
+    assert_eq!(
+        expected_at(&stmt, &tokens, 3),
+        vec![
+            Expected::Token(Tok::Num),
+            Expected::Token(Tok::Str),
+            Expected::Rule("identifier".into()),
+        ]
+    );
+    assert!(expected_at(&stmt, &tokens, 1)
+        .iter()
+        .all(|e| *e == Expected::Token(Tok::Id)));
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::tokens;
+use std::collections::HashMap;
+
+use roder::convert::FromParse;
+use roder::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tk {
+    Name(&'static str),
+    Num(i64),
+}
+
+impl std::fmt::Display for Tk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tk::Name(n) => f.write_str(n),
+            Tk::Num(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl FromParse<Tk> for String {
+    fn from_token(token: &Token<Tk>) -> Result<Self, ParseError> {
+        match token.ty() {
+            Tk::Name(n) => Ok(n.to_string()),
+            _ => Err(ParseError::from("name".into(), token.span().clone())),
+        }
+    }
+}
+
+impl FromParse<Tk> for i64 {
+    fn from_token(token: &Token<Tk>) -> Result<Self, ParseError> {
+        match token.ty() {
+            Tk::Num(n) => Ok(*n),
+            _ => Err(ParseError::from("number".into(), token.span().clone())),
+        }
+    }
+}
+
+#[test]
+fn trees_lower_into_collections() {
+    use Tk::*;
+
+    // entries := (name number)+
+    let name = || pred("name", |t: &Tk| matches!(t, Name(_)));
+    let num = || pred("number", |t: &Tk| matches!(t, Num(_)));
+    let entries = many(seq([name(), num()]));
+
+    let input = tokens(&[Name("a"), Num(1), Name("b"), Num(2)]);
+    let tree = entries.parse_all(&input).ok().unwrap();
+
+    let map: HashMap<String, i64> = tree.collect().ok().unwrap();
+    assert_eq!(map["b"], 2);
+    let pairs: Vec<(Token<Tk>, i64)> = tree.to().ok().unwrap();
+    assert_eq!(pairs[0].0.ty(), &Name("a"));
+    assert_eq!(tree.child::<(String, i64)>(1).ok().unwrap().1, 2);
+
+    let err = tree.child::<(String, i64)>(2).err().unwrap();
+    assert_eq!(err.expected(), "at least 3 items");
+    let err = tree.collect::<Vec<(i64, i64)>, _>().err().unwrap();
+    assert_eq!(
+        (err.expected(), err.span()),
+        ("number", &Span::new(1, 1, 1))
+    );
+    let err = tree.optional::<(String, i64)>().err().unwrap();
+    assert_eq!(
+        err.message(),
+        "Unexpected node shape, found a sequence of 2"
+    );
+}
+
+#[test]
+fn short_lists_convert_to_option() {
+    use Tk::*;
+
+    let nums = || many(just(Num(1)));
+
+    let tree = nums().parse_all(&tokens(&[Num(1)])).ok().unwrap();
+    assert_eq!(tree.optional::<i64>().ok().unwrap(), Some(1));
+
+    let tree = nums().parse_all(&tokens(&[Num(1), Num(1)])).ok().unwrap();
+    assert_eq!(
+        tree.optional::<i64>().err().unwrap().expected(),
+        "at most one item"
+    );
+}
@@ -0,0 +1,44 @@
+use std::fs;
+
+use roder::corpus::{CaseOutcome, Corpus};
+use roder::prelude::*;
+
+// one token per whitespace-separated digit
+fn lex(src: &str) -> Vec<Token<u8>> {
+    src.split_whitespace()
+        .enumerate()
+        .map(|(i, w)| Token::new(w.parse().unwrap(), Span::new(1, i + 1, i + 1)))
+        .collect()
+}
+
+#[test]
+fn corpus_compares_and_blesses_expectations() {
+    let dir = std::env::temp_dir().join(format!("roder-corpus-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("good.txt"), "1 2").unwrap();
+    fs::write(dir.join("good.txt.expected"), "(1 2)").unwrap();
+    fs::write(dir.join("bad.txt"), "1 3").unwrap();
+    fs::write(dir.join("bad.txt.expected"), "(1 3)").unwrap();
+    fs::write(dir.join("new.txt"), "1 2").unwrap();
+
+    let parser = seq([just(1u8), just(2u8)]);
+    let corpus = Corpus::new(&dir, lex);
+    let report = corpus.run(&parser).unwrap();
+
+    assert!(!report.is_ok());
+    let outcomes: Vec<_> = report.cases.iter().map(|c| &c.outcome).collect();
+    assert!(matches!(outcomes[0], CaseOutcome::Failed { .. }));
+    assert_eq!(outcomes[1], &CaseOutcome::Passed);
+    assert!(matches!(outcomes[2], CaseOutcome::Missing { .. }));
+    assert!(report.to_string().contains("- (1 3)\n+ error:"));
+
+    let report = Corpus::new(&dir, lex).bless(true).run(&parser).unwrap();
+    assert!(report.is_ok());
+    assert!(Corpus::new(&dir, lex).run(&parser).unwrap().is_ok());
+    assert_eq!(
+        fs::read_to_string(dir.join("new.txt.expected")).unwrap(),
+        "(1 2)"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,28 @@
+use roder::coverage::Coverage;
+use roder::parse::OfType;
+use roder::prelude::*;
+
+#[test]
+fn coverage_reports_untouched_rules_and_alternatives() {
+    let value = roder::choice!["value";
+        OfType::from("number", false, 1u8),
+        OfType::from("string", false, 2u8),
+        OfType::from("null", false, 3u8),
+    ];
+    let coverage = Coverage::new();
+
+    for corpus in [[1u8], [2u8], [1u8]] {
+        let tokens = vec![Token::new(corpus[0], Span::new(1, 1, 1))];
+        let ctx = Context::new(&tokens).with_coverage(&coverage);
+        assert!(value.parse(&ctx, 0).data().is_ok());
+    }
+
+    assert_eq!(coverage.alternative_hits("value"), Some(vec![2, 1, 0]));
+    assert_eq!(coverage.get("number").map(|c| c.attempts), Some(3));
+    assert_eq!(
+        coverage.unmatched(&["value", "number", "null", "list"]),
+        vec!["null", "list"]
+    );
+    assert_eq!(coverage.unmatched_alternatives().len(), 1);
+    assert!(coverage.to_string().starts_with("number: 2/3 matched\n"));
+}
@@ -0,0 +1,47 @@
+use roder::complete::{expected_at, Expected};
+use roder::describe::ExpectedList;
+use roder::parse::Predicate;
+use roder::prelude::*;
+
+#[test]
+fn expectations_read_as_a_sentence() {
+    let list = ExpectedList::new();
+    let items = [
+        Expected::Token(";"),
+        Expected::Token(")"),
+        Expected::Token(";"),
+        Expected::Rule("identifier".into()),
+    ];
+    let name = |t: &&str| t.to_string();
+    assert_eq!(list.format(&items, name), "`;`, `)` or an identifier");
+    assert_eq!(list.format(&items[..1], name), "`;`");
+    assert_eq!(list.format::<&str>(&[], name), "nothing");
+
+    let terse = list
+        .with_quotes("'", "'")
+        .with_articles(false)
+        .with_limit(1);
+    assert_eq!(terse.format(&items, name), "';', …and 2 more");
+    assert_eq!(
+        terse.format(&[Expected::<&str>::Rule("type_name".into())], name),
+        "type name"
+    );
+}
+
+#[test]
+fn errors_take_the_humanized_list() {
+    let value = choice([
+        just(1u8),
+        just(2u8),
+        Box::new(Predicate::from("number", false, |t: &u8| *t > 9)),
+    ]);
+    let stmt = seq([just(0u8), value]);
+    let tokens = vec![
+        Token::new(0u8, Span::new(1, 1, 1)),
+        Token::new(5u8, Span::new(1, 3, 3)),
+    ];
+    let error = stmt.parse_all(&tokens).err().unwrap().remove(0);
+    let expected = ExpectedList::new().format(&expected_at(&stmt, &tokens, 1), |t| t.to_string());
+    let error = error.with_expected(expected);
+    assert_eq!(error.expected(), "`1`, `2` or a number");
+}
@@ -0,0 +1,72 @@
+use roder::diff::{diff, TreeEdit};
+use roder::prelude::*;
+
+fn token(ty: u8, col: usize) -> ParseData<u8> {
+    ParseData::Token(Token::new(ty, Span::new(1, col, col)))
+}
+
+// A statement list, each statement a nested node of its tokens laid out left to right.
+fn tree(statements: &[&[u8]]) -> ParseData<u8> {
+    let mut col = 0;
+    let statements = statements
+        .iter()
+        .map(|s| {
+            ParseData::Nested(
+                s.iter()
+                    .map(|ty| {
+                        col += 1;
+                        token(*ty, col)
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+    ParseData::Nested(statements)
+}
+
+fn summary(edits: &[TreeEdit<u8>]) -> Vec<String> {
+    edits
+        .iter()
+        .map(|e| {
+            let kind = match e {
+                TreeEdit::Inserted(_) => "inserted",
+                TreeEdit::Deleted(_) => "deleted",
+                TreeEdit::Moved { .. } => "moved",
+            };
+            format!("{kind} at {}", e.span().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn identical_structure_gives_no_edits_whatever_the_spans() {
+    let old = tree(&[&[1, 2], &[3]]);
+    let new = ParseData::Nested(vec![
+        ParseData::Nested(vec![token(1, 5), token(2, 9)]),
+        ParseData::Nested(vec![token(3, 12)]),
+    ]);
+    assert!(diff(&old, &new).is_empty());
+}
+
+#[test]
+fn edits_name_inserted_deleted_and_moved_subtrees() {
+    let old = tree(&[&[1, 2], &[3, 4], &[5]]);
+
+    // a statement added at the end, and one dropped from the middle
+    let new = tree(&[&[1, 2], &[5], &[6]]);
+    assert_eq!(
+        summary(&diff(&old, &new)),
+        ["deleted at 1:3-3", "inserted at 1:4-4"]
+    );
+
+    // a token changed inside a statement
+    let new = tree(&[&[1, 2], &[3, 7], &[5]]);
+    assert_eq!(
+        summary(&diff(&old, &new)),
+        ["deleted at 1:4-4", "inserted at 1:4-4"]
+    );
+
+    // a statement moved to the front
+    let new = tree(&[&[5], &[1, 2], &[3, 4]]);
+    assert_eq!(summary(&diff(&old, &new)), ["moved at 1:1-1"]);
+}
@@ -0,0 +1,55 @@
+use roder::edit::Editor;
+use roder::prelude::*;
+
+fn token(ty: u8, col: usize) -> ParseData<u8> {
+    ParseData::Token(Token::new(ty, Span::new(1, col, col)))
+}
+
+fn tys(data: &ParseData<u8>) -> Vec<u8> {
+    match data {
+        ParseData::Token(t) => vec![*t.ty()],
+        ParseData::TokenList(l) => l.iter().map(|t| *t.ty()).collect(),
+        ParseData::Nested(l) => l.iter().flat_map(tys).collect(),
+        ParseData::Error { .. } => vec![],
+    }
+}
+
+#[test]
+fn edits_reshape_the_tree_and_mark_it_dirty() {
+    // [[1 2] [3] [4 5]]
+    let mut tree = ParseData::Nested(vec![
+        ParseData::Nested(vec![token(1, 1), token(2, 2)]),
+        ParseData::Nested(vec![token(3, 3)]),
+        ParseData::TokenList(vec![
+            Token::new(4, Span::new(1, 4, 4)),
+            Token::new(5, Span::new(1, 5, 5)),
+        ]),
+    ]);
+    let mut editor = Editor::new(&mut tree);
+
+    let old = editor.replace(&[0, 1], token(9, 2)).unwrap();
+    assert_eq!(tys(&old), [2]);
+    assert_eq!(editor.dirty(), [vec![0, 1]]);
+
+    // inserting before the edited statement moves its path along
+    assert!(editor.insert(&[], 0, ParseData::Nested(vec![token(7, 1)])));
+    assert_eq!(editor.dirty(), [vec![1, 1], vec![0]]);
+    assert!(editor.is_dirty(&[0, 0]));
+    assert!(!editor.is_dirty(&[2]));
+
+    let removed = editor.remove(&[], 2).unwrap();
+    assert_eq!(tys(&removed), [3]);
+    assert_eq!(
+        editor
+            .splice(&[2], 1..2, [Token::new(6, Span::new(1, 5, 5))])
+            .map(|old| old.len()),
+        Some(1)
+    );
+    assert!(editor.get(&[2]).is_some());
+    assert!(editor.splice(&[0], 0..1, []).is_none());
+    assert!(!editor.insert(&[0], 5, token(8, 1)));
+
+    editor.clean(&[]);
+    assert!(editor.dirty().is_empty());
+    assert_eq!(tys(&tree), [7, 1, 9, 4, 6]);
+}
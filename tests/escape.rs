@@ -0,0 +1,44 @@
+use roder::escape::Escapes;
+use roder::prelude::*;
+
+#[test]
+fn escapes_decode_with_exact_error_spans() {
+    let escapes = Escapes::default();
+
+    // the string's contents start at column 10 of line 3
+    assert_eq!(
+        escapes
+            .decode(r#"a\tb\u{1F600}\x41\""#, 3, 10)
+            .ok()
+            .unwrap(),
+        "a\tb😀A\""
+    );
+
+    let errors = escapes
+        .decode(r"ok\q and \u{D800} and \x", 3, 10)
+        .err()
+        .unwrap();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|e| (e.message(), e.span().clone()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Unknown escape \\q", Span::new(3, 12, 13)),
+            (
+                "\\u{D800} is not a Unicode scalar value",
+                Span::new(3, 19, 26)
+            ),
+            ("Expected two hex digits after \\x", Span::new(3, 32, 33)),
+        ]
+    );
+}
+
+#[test]
+fn escape_sets_are_configurable() {
+    let escapes = Escapes::default().with('e', '\u{1b}').with_unicode(false);
+
+    assert_eq!(escapes.decode(r"\e[0m", 1, 1).ok().unwrap(), "\u{1b}[0m");
+    assert!(escapes.decode(r"\u{41}", 1, 1).is_err());
+}
@@ -0,0 +1,47 @@
+use roder::explain::explain;
+use roder::parse::{Predicate, Sequence};
+use roder::prelude::*;
+
+// Tokens a space apart, as in `0 5 9`, rather than the side-by-side ones of `common::tokens`:
+// with columns no longer one past the offsets, the accounts show they give source positions.
+fn spaced(tys: &[u8]) -> Vec<Token<u8>> {
+    tys.iter()
+        .enumerate()
+        .map(|(i, ty)| Token::new(*ty, Span::new(1, i * 2 + 1, i * 2 + 1)))
+        .collect()
+}
+
+#[test]
+fn failures_are_explained_rule_by_rule() {
+    // stmt := 0 value 9 ; value := 1 | 2 | number
+    let number = Box::new(Predicate::from("number", false, |t: &u8| *t > 9));
+    let value = choice([just(1u8), just(2u8), number]);
+    let stmt = Sequence::from("stmt", false, vec![just(0u8), value, just(9u8)]);
+
+    let account = explain(&stmt, &spaced(&[0, 5, 9]), |t| t.to_string()).unwrap();
+    assert_eq!(
+        account,
+        "Parse failed at 1:3, where `5` was found\n\
+         stmt at 1:1 failed, trying:\n\
+         \x20 0 matched `0` at 1:1\n\
+         \x20 1 or 2 or number at 1:3 failed, trying:\n\
+         \x20   1 at 1:3 failed: expected `1`, found `5`\n\
+         \x20   2 at 1:3 failed: expected `2`, found `5`\n\
+         \x20   number at 1:3 failed: expected a number, found `5`\n"
+    );
+
+    let account = explain(&stmt, &spaced(&[0, 1]), |t| t.to_string()).unwrap();
+    assert!(account.starts_with("Parse failed at the end of input"));
+    assert!(account.contains("9 at the end of input failed: expected `9`"));
+
+    assert!(explain(&stmt, &spaced(&[0, 2, 9]), |t| t.to_string()).is_none());
+
+    // nothing fails inside the grammar when only trailing input is left over
+    let account = explain(&*just(0u8), &spaced(&[0, 7]), |t| t.to_string()).unwrap();
+    assert_eq!(
+        account,
+        "Parse failed at 1:3, where `7` was found
+0 matched `0` at 1:1
+"
+    );
+}
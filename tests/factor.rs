@@ -0,0 +1,63 @@
+mod common;
+
+use common::tokens;
+use roder::factor::left_factor;
+use roder::input::TokenInput;
+use roder::parse::{Choice, Context, Parser, Sequence};
+use roder::prelude::*;
+
+fn steps(parser: &dyn Parser<u8>, input: &dyn TokenInput<u8>) -> usize {
+    let ctx = Context::new(input);
+    parser.parse(&ctx, 0);
+    ctx.steps_taken()
+}
+
+#[test]
+fn common_prefixes_are_hoisted() {
+    // stmt := let id = value ; | let id ; | id ;   with let=1 id=2 '='=3 value=4 ';'=5
+    let [kw, id, eq, value, semi] = [1u8, 2, 3, 4, 5].map(|t| just(t).shared());
+    let alternatives = vec![
+        vec![
+            kw.clone(),
+            id.clone(),
+            eq.clone(),
+            value.clone(),
+            semi.clone(),
+        ],
+        vec![kw.clone(), id.clone(), semi.clone()],
+        vec![id.clone(), semi.clone()],
+    ];
+
+    let original = Choice::from(
+        "stmt",
+        false,
+        alternatives
+            .iter()
+            .map(|a| {
+                let items = a.iter().map(|p| Box::new(p.clone()) as Box<dyn Parser<u8>>);
+                Box::new(Sequence::from("stmt", false, items.collect())) as Box<dyn Parser<u8>>
+            })
+            .collect(),
+    );
+    assert_eq!(original.overlapping(), [(0, 1)]);
+
+    let factored = left_factor("stmt", alternatives);
+    for input in [
+        &[1, 2, 3, 4, 5][..],
+        &[1, 2, 5],
+        &[2, 5],
+        &[1, 2, 3, 5],
+        &[1, 5],
+    ] {
+        let input = tokens(input);
+        assert_eq!(
+            original.parse_all(&input).is_ok(),
+            factored.parse_all(&input).is_ok(),
+            "{input:?}"
+        );
+    }
+
+    // `let id ;` no longer parses `let id` twice
+    let input = tokens(&[1, 2, 5]);
+    assert!(steps(&*factored, &input) < steps(&original, &input));
+}
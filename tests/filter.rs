@@ -0,0 +1,89 @@
+use roder::filter::{drop_where, rewrite_where, Emitter, Pipeline};
+use roder::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tk {
+    Space,
+    Ident(&'static str),
+    Async,
+    Lt,
+    Eq,
+    Le,
+    Semi,
+    Newline,
+}
+
+fn lexed() -> Vec<Token<Tk>> {
+    // `async x <= y` then a newline, on one line
+    [
+        (Tk::Ident("async"), 1, 5),
+        (Tk::Space, 6, 6),
+        (Tk::Ident("x"), 7, 7),
+        (Tk::Space, 8, 8),
+        (Tk::Lt, 9, 9),
+        (Tk::Eq, 10, 10),
+        (Tk::Space, 11, 11),
+        (Tk::Ident("y"), 12, 12),
+        (Tk::Newline, 13, 13),
+    ]
+    .into_iter()
+    .map(|(ty, cs, ce)| Token::new(ty, Span::new(1, cs, ce)))
+    .collect()
+}
+
+#[test]
+fn pipelines_merge_delete_rewrite_and_inject() {
+    let mut pipeline = Pipeline::new()
+        .then(drop_where(|t| *t == Tk::Space))
+        .then(rewrite_where(|t| {
+            (*t.ty() == Tk::Ident("async")).then_some(Tk::Async)
+        }))
+        .then(|token: Token<Tk>, out: &mut Emitter<Tk>| {
+            if *token.ty() == Tk::Eq && out.last().is_some_and(|t| *t.ty() == Tk::Lt) {
+                out.merge(token, Tk::Le);
+            } else if *token.ty() == Tk::Newline {
+                out.inject(Tk::Semi, "automatic semicolon");
+            } else {
+                out.emit(token);
+            }
+        });
+
+    let tokens = pipeline.run(lexed());
+    let types: Vec<_> = tokens.iter().map(|t| t.ty().clone()).collect();
+    assert_eq!(
+        types,
+        [Tk::Async, Tk::Ident("x"), Tk::Le, Tk::Ident("y"), Tk::Semi]
+    );
+    assert_eq!(tokens[2].span(), &Span::new(1, 9, 10));
+    assert_eq!(tokens[4].span(), &Span::new(1, 13, 13));
+    assert_eq!(
+        tokens[4].provenance().map(|p| p.reason()),
+        Some("automatic semicolon")
+    );
+    assert!(tokens[0].provenance().is_none());
+}
+
+#[test]
+fn newlines_only_survive_outside_brackets() {
+    use roder::filter::SignificantNewlines;
+
+    // 0 = newline, 1 = ident, 2/3 = ( ), 9 = ;
+    let input: Vec<_> = [0u8, 1, 2, 1, 0, 1, 3, 0, 0, 1, 0]
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| Token::new(*ty, Span::new(1, i + 1, i + 1)))
+        .collect();
+    let mut pipeline = Pipeline::new().then(SignificantNewlines::new(0u8, vec![(2, 3)]));
+    let tokens = pipeline.run(input);
+
+    let types: Vec<_> = tokens.iter().map(|t| *t.ty()).collect();
+    assert_eq!(types, [1, 2, 1, 1, 3, 0, 1, 0]);
+
+    let statement = seq([
+        just(1u8),
+        seq([just(2u8), just(1u8), just(1u8), just(3u8)]),
+        just(0u8),
+    ]);
+    let program = many(choice([statement, seq([just(1u8), just(0u8)])]));
+    assert!(program.parse_all(&tokens).is_ok());
+}
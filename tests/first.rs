@@ -0,0 +1,114 @@
+mod common;
+
+use common::tokens;
+use roder::complete::Expected;
+use roder::first::Conflict;
+use roder::parse::{Choice, OfType, Parser, Repeatable, TokenPredicate};
+use roder::prelude::*;
+
+#[test]
+fn first_sets_follow_nullable_prefixes() {
+    let maybe = || Box::new(OfType::from("maybe", true, 1u8));
+    let stmt = seq([
+        maybe(),
+        choice([just(2u8), not(just(3u8)), just(4u8)]),
+        just(5u8),
+    ]);
+    let first = stmt.first_set();
+
+    assert!(first.contains(&1) && first.contains(&2) && first.contains(&4));
+    assert!(first.contains(&5));
+    assert!(!first.contains(&3));
+    assert!(!first.is_nullable() && !first.is_open());
+
+    let any = Box::new(TokenPredicate::from("any", false, |_: &Token<u8>| true));
+    let list = Repeatable::from("list", true, any);
+    assert!(list.first_set().is_nullable());
+    assert_eq!(list.first_set().items(), &[Expected::Rule("any".into())]);
+
+    let tolerant = recover(just(1u8), |t| *t == 0);
+    assert!(tolerant.first_set().is_open());
+    assert_eq!(
+        choice([just(1u8), just(1u8)])
+            .first_set()
+            .dedup()
+            .items()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn alternatives_covered_by_earlier_ones_are_shadowed() {
+    let statement = Choice::from(
+        "statement",
+        false,
+        vec![
+            just(1u8),
+            seq([just(1u8), just(2u8)]),
+            choice([just(2u8), just(3u8)]),
+            just(3u8),
+            seq([Box::new(OfType::from("maybe", true, 2u8)), just(4u8)]),
+        ],
+    );
+
+    assert_eq!(statement.shadowed(), [(1, vec![0]), (3, vec![2])]);
+    // `1` always wins, so `1 2` is never reached
+    assert!(statement.parse_all(&tokens(&[1, 2])).is_err());
+
+    // `1 2` needs the `2`, so `1` on its own still gets through
+    let ordered = Choice::from(
+        "ordered",
+        false,
+        vec![seq([just(1u8), just(2u8)]), just(1u8)],
+    );
+    assert!(ordered.shadowed().is_empty());
+    assert!(ordered.parse_all(&tokens(&[1])).is_ok());
+
+    // `1` and `2` between them take the start of anything `(1 | 2) 5` matches
+    let split = Choice::from(
+        "split",
+        false,
+        vec![
+            just(1u8),
+            just(2u8),
+            seq([choice([just(1u8), just(2u8)]), just(5u8)]),
+        ],
+    );
+    assert_eq!(split.shadowed(), [(2, vec![0, 1])]);
+}
+
+#[test]
+fn conflicts_name_the_shared_lookahead() {
+    let maybe = |ty: u8| Box::new(OfType::from("maybe", true, ty)) as Box<dyn Parser<u8>>;
+    let expression = Choice::from(
+        "expression",
+        false,
+        vec![
+            seq([just(1u8), just(2u8)]),
+            choice([just(3u8), just(1u8)]),
+            just(4u8),
+            maybe(5),
+            maybe(6),
+        ],
+    );
+
+    assert_eq!(
+        expression.conflicts(),
+        [
+            Conflict {
+                first: 0,
+                second: 1,
+                on: vec![Expected::Token(1)],
+                nullable: false,
+            },
+            Conflict {
+                first: 3,
+                second: 4,
+                on: vec![],
+                nullable: true,
+            },
+        ]
+    );
+    assert_eq!(expression.overlapping(), [(0, 1)]);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::tokens;
+use std::cmp::Ordering;
+
+use roder::forest::{parse_forest, Truncation};
+use roder::parse::{Deferred, Parser};
+use roder::prelude::*;
+
+// expr := (1 0 1) 0 expr | 1 0 expr | 1, so a chain of two operators groups either way
+fn expr() -> Box<dyn Parser<u8>> {
+    let nested = || Box::new(Deferred::from("expr", expr)) as Box<dyn Parser<u8>>;
+    choice([
+        seq([seq([just(1u8), just(0u8), just(1u8)]), just(0u8), nested()]),
+        seq([just(1u8), just(0u8), nested()]),
+        just(1u8),
+    ])
+}
+
+// How many tokens the left operand of the top operator holds.
+fn left(data: &ParseData<u8>) -> usize {
+    match data {
+        ParseData::Nested(l) => match &l[0] {
+            ParseData::Nested(operand) => operand.len(),
+            _ => 1,
+        },
+        _ => 0,
+    }
+}
+
+#[test]
+fn ambiguous_input_gives_every_tree() {
+    let grammar = expr();
+
+    // 1 0 1 0 1 groups as (1 0 1) 0 1 or 1 0 (1 0 1)
+    let forest = parse_forest(&*grammar, &tokens(&[1, 0, 1, 0, 1]), 16)
+        .ok()
+        .unwrap();
+    assert!(forest.is_ambiguous());
+    assert!(forest.is_complete());
+    assert_eq!(forest.len(), 2);
+
+    // the ordinary parse is the first tree
+    let first = grammar.parse_all(&tokens(&[1, 0, 1, 0, 1])).ok().unwrap();
+    assert_eq!(left(&forest.trees()[0]), left(&first));
+    assert_eq!(left(&first), 3);
+
+    let right_grouped = forest.resolve(|a, b| left(b).cmp(&left(a))).unwrap();
+    assert_eq!(left(&right_grouped), 1);
+
+    let mut forest = parse_forest(&*grammar, &tokens(&[1, 0, 1, 0, 1]), 16)
+        .ok()
+        .unwrap();
+    forest.retain(|tree| left(tree) == 3);
+    assert_eq!(forest.len(), 1);
+    assert_eq!(
+        forest.resolve(|_, _| Ordering::Equal).map(|t| left(&t)),
+        Some(3)
+    );
+
+    let single = parse_forest(&*grammar, &tokens(&[1, 0, 1]), 16)
+        .ok()
+        .unwrap();
+    assert_eq!(single.len(), 1);
+    assert!(parse_forest(&*grammar, &tokens(&[1, 0]), 16).is_err());
+
+    let limited = parse_forest(&*grammar, &tokens(&[1, 0, 1, 0, 1]), 1)
+        .ok()
+        .unwrap();
+    assert_eq!(limited.len(), 1);
+    assert!(!limited.is_complete());
+    assert_eq!(limited.truncated(), Some(Truncation::Limit));
+
+    let none = parse_forest(&*expr(), &tokens(&[1, 0, 1]), 0).ok().unwrap();
+    assert!(none.is_empty());
+    assert_eq!(none.truncated(), Some(Truncation::Limit));
+}
+
+#[test]
+fn searches_cut_short_say_so() {
+    // 24 ones group in more ways than the search will try
+    let input: Vec<u8> = (0..47).map(|i| if i % 2 == 0 { 1 } else { 0 }).collect();
+    let forest = parse_forest(&*expr(), &tokens(&input), usize::MAX)
+        .ok()
+        .unwrap();
+    assert!(!forest.is_complete());
+    assert_eq!(forest.truncated(), Some(Truncation::Runs));
+    assert!(forest.len() <= 4096);
+}
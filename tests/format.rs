@@ -0,0 +1,49 @@
+use roder::format::Doc;
+use roder::prelude::*;
+
+fn list(items: &[&str]) -> Doc {
+    Doc::group(Doc::concat([
+        Doc::text("["),
+        Doc::indent(Doc::concat([
+            Doc::SoftLine,
+            Doc::join(
+                items.iter().map(|i| Doc::text(*i)),
+                Doc::concat([Doc::text(","), Doc::Line]),
+            ),
+        ])),
+        Doc::SoftLine,
+        Doc::text("]"),
+    ]))
+}
+
+#[test]
+fn groups_break_only_when_they_do_not_fit() {
+    let doc = list(&["alpha", "beta", "gamma"]);
+
+    assert_eq!(doc.render(80, 2), "[alpha, beta, gamma]");
+    assert_eq!(doc.render(10, 2), "[\n  alpha,\n  beta,\n  gamma\n]");
+}
+
+#[test]
+fn comments_in_trivia_survive_formatting() {
+    // 0 = comment, 1 = whitespace, 2 = identifier
+    let token = Token::new(2u8, Span::new(2, 1, 1)).with_trivia(
+        vec![
+            Token::new(0u8, Span::new(1, 1, 5)),
+            Token::new(1u8, Span::new(1, 6, 6)),
+        ],
+        vec![],
+    );
+    let text = |t: &Token<u8>| match t.ty() {
+        0 => Some("# doc".to_string()),
+        2 => Some("x".to_string()),
+        _ => None,
+    };
+
+    let doc = Doc::group(Doc::concat([
+        Doc::token(&token, text),
+        Doc::Line,
+        Doc::text("y"),
+    ]));
+    assert_eq!(doc.render(80, 2), "# doc\nx\ny");
+}
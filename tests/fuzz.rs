@@ -0,0 +1,18 @@
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::Unstructured;
+use roder::fuzz::token_stream;
+use roder::prelude::*;
+
+#[test]
+fn generated_streams_are_laid_out_in_order() {
+    let data: Vec<u8> = (0..=255).cycle().take(2048).collect();
+    let mut u = Unstructured::new(&data);
+    let tokens = token_stream::<u8>(&mut u, 64).unwrap();
+
+    for pair in tokens.windows(2) {
+        let (a, b) = (pair[0].span(), pair[1].span());
+        assert!((a.ln(), a.ce()) < (b.ln(), b.cs()));
+    }
+    let _ = many(just(0u8)).parse_all(&tokens);
+}
@@ -0,0 +1,31 @@
+use roder::generate::Generator;
+use roder::parse::{OfType, Predicate};
+use roder::prelude::*;
+
+#[test]
+fn generated_inputs_parse() {
+    // item := 1 (2 | 3 4) ; document := item+ 5
+    let item = seq([just(1u8), choice([just(2u8), seq([just(3u8), just(4u8)])])]);
+    let document = seq([many(item), just(5u8)]);
+
+    for seed in 1..200 {
+        let tokens = Generator::new(seed).tokens(&document).unwrap();
+        assert!(document.parse_all(&tokens).is_ok(), "seed {seed}");
+    }
+}
+
+#[test]
+fn predicates_fall_back_to_other_alternatives() {
+    let number = Box::new(Predicate::from("number", false, |t: &u8| *t > 9));
+    let value = choice([number, just(0u8)]);
+    let tokens = Generator::new(7).tokens(&value).unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(*tokens[0].ty(), 0);
+
+    let maybe = OfType::from("maybe", true, 1u8);
+    let mut gen = Generator::new(3).with_max_depth(0);
+    assert!((0..32).all(|_| gen.tokens(&maybe).is_some_and(|t| t.len() <= 1)));
+    assert!(Generator::new(1)
+        .tokens(&Predicate::from("any", false, |_: &u8| true))
+        .is_none());
+}
@@ -0,0 +1,93 @@
+use roder::highlight::{encode_semantic_tokens, highlight, highlight_rules};
+use roder::parse::Sequence;
+use roder::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+    Keyword,
+    Comment,
+    Function,
+    Variable,
+    Punctuation,
+}
+
+#[test]
+fn tokens_and_trivia_are_classified_in_order() {
+    // 0 = comment, 1 = keyword, 2 = unclassified
+    let comment = Token::new(0u8, Span::new(1, 1, 4));
+    let tree = ParseData::Nested(vec![
+        ParseData::Token(Token::new(1u8, Span::new(2, 1, 3)).with_trivia(vec![comment], vec![])),
+        ParseData::Token(Token::new(2u8, Span::new(2, 5, 5))),
+        ParseData::Token(Token::new(1u8, Span::new(3, 3, 6))),
+    ]);
+
+    let classes = highlight(&tree, |t| match t.ty() {
+        0 => Some(Class::Comment),
+        1 => Some(Class::Keyword),
+        _ => None,
+    });
+    assert_eq!(
+        classes,
+        vec![
+            (Span::new(1, 1, 4), Class::Comment),
+            (Span::new(2, 1, 3), Class::Keyword),
+            (Span::new(3, 3, 6), Class::Keyword),
+        ]
+    );
+
+    let encoded: Vec<_> = classes
+        .into_iter()
+        .map(|(span, class)| (span, class as u32, 0))
+        .collect();
+    assert_eq!(
+        encode_semantic_tokens(&encoded),
+        vec![0, 0, 4, 1, 0, 1, 0, 3, 0, 0, 1, 2, 4, 0, 0]
+    );
+}
+
+#[test]
+fn rules_classify_the_tokens_they_matched() {
+    // 0 = comment, 1 = identifier, 2 and 3 = parentheses
+    let comment = Token::new(0u8, Span::new(1, 1, 4));
+    let tokens = vec![
+        Token::new(1u8, Span::new(2, 1, 1)).with_trivia(vec![comment], vec![]),
+        Token::new(2u8, Span::new(2, 2, 2)),
+        Token::new(3u8, Span::new(2, 3, 3)),
+        Token::new(1u8, Span::new(2, 5, 5)),
+    ];
+    let call = || -> Box<dyn Parser<u8>> {
+        Box::new(Sequence::from(
+            "call",
+            false,
+            vec![just(1u8), just(2u8), just(3u8)],
+        ))
+    };
+    let parser = seq([choice([call(), just(1u8)]), choice([call(), just(1u8)])]);
+
+    let classes = highlight_rules(
+        &*parser,
+        &tokens,
+        |rule| match rule {
+            "call" => Some(Class::Function),
+            "2" | "3" => Some(Class::Punctuation),
+            _ => None,
+        },
+        |t| match t.ty() {
+            0 => Some(Class::Comment),
+            1 => Some(Class::Variable),
+            _ => None,
+        },
+    )
+    .ok()
+    .unwrap();
+    assert_eq!(
+        classes,
+        vec![
+            (Span::new(1, 1, 4), Class::Comment),
+            (Span::new(2, 1, 1), Class::Function),
+            (Span::new(2, 2, 2), Class::Punctuation),
+            (Span::new(2, 3, 3), Class::Punctuation),
+            (Span::new(2, 5, 5), Class::Variable),
+        ]
+    );
+}
@@ -0,0 +1,24 @@
+use roder::html::render_html;
+use roder::prelude::*;
+
+#[test]
+fn html_links_tree_nodes_to_source_tokens() {
+    let source = "x = <1>\ny";
+    let tree = ParseData::Nested(vec![
+        ParseData::Nested(vec![
+            ParseData::Token(Token::new("id", Span::new(1, 1, 1))),
+            ParseData::Token(Token::new("eq", Span::new(1, 3, 3))),
+            ParseData::Token(Token::new("num", Span::new(1, 5, 7))),
+        ]),
+        ParseData::Token(Token::new("id", Span::new(2, 1, 1))),
+    ]);
+    let html = render_html(&tree, source, "a < b");
+
+    assert!(html.contains("<title>a &lt; b</title>"));
+    assert!(html.contains(
+        "<pre><span class=\"tok\" id=\"t0\">x</span> <span class=\"tok\" id=\"t1\">=</span> \
+<span class=\"tok\" id=\"t2\">&lt;1&gt;</span>\n<span class=\"tok\" id=\"t3\">y</span>\n</pre>"
+    ));
+    assert!(html.contains("<details open data-from=\"0\" data-to=\"3\">"));
+    assert!(html.contains("<details open data-from=\"0\" data-to=\"2\"><summary>(3)</summary>"));
+}
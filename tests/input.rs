@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use roder::input::{BufferedTokens, Spliced, TokenInput};
+use roder::parse::{Context, ParseResult, Parser, Predicate};
+use roder::token::{Span, Token};
+
+fn tokens(n: usize) -> impl Iterator<Item = Token<usize>> {
+    (0..n).map(|i| Token::new(i, Span::new(1, i + 1, i + 1)))
+}
+
+#[test]
+fn buffered_tokens_pull_lazily_across_chunks() {
+    let buffered = BufferedTokens::new(tokens(300));
+
+    assert_eq!(buffered.get(200).map(|t| *t.ty()), Some(200));
+    assert_eq!(buffered.get(3).map(|t| *t.ty()), Some(3));
+    assert!(buffered.get(300).is_none());
+    assert_eq!(buffered.len(), 300);
+}
+
+#[test]
+fn errors_do_not_drain_buffered_tokens() {
+    use roder::prelude::{choice, just};
+    use std::cell::Cell;
+
+    let pulled = Cell::new(0);
+    let source = tokens(10_000).inspect(|_| pulled.set(pulled.get() + 1));
+    let buffered = BufferedTokens::new(source);
+    let ctx = Context::new(&buffered);
+
+    let parse = choice([just(7usize), just(8usize)]).parse(&ctx, 0);
+    assert!(parse.data().is_err());
+    assert!(pulled.get() < 1_000);
+}
+
+#[test]
+fn context_accepts_any_token_input() {
+    let shared: Rc<[Token<usize>]> = tokens(2).collect();
+    let ctx = Context::new(&shared);
+    let parser = Predicate::from("zero", false, |t| *t == 0);
+
+    assert!(matches!(parser.parse(&ctx, 0).data(), ParseResult::Ok(_)));
+    assert_eq!(ctx.len(), 2);
+}
+
+#[test]
+fn parses_share_one_buffer_and_index_back_into_it() {
+    use roder::index::token_range;
+    use roder::prelude::{just, many, seq};
+    use std::sync::Arc;
+
+    let buffer: Arc<[Token<usize>]> = [1, 2, 1, 2, 3]
+        .into_iter()
+        .enumerate()
+        .map(|(i, ty)| Token::new(ty, Span::new(1, i + 1, i + 1)))
+        .collect();
+
+    // a speculative parse and the real one, over the same tokens
+    let speculative = Arc::clone(&buffer);
+    let pairs = many(seq([just(1usize), just(2usize)]));
+    let (tree, consumed) = pairs.parse_partial(&speculative).ok().unwrap();
+    assert_eq!(consumed, 4);
+
+    let ctx = Context::new(&buffer);
+    let second = just(2usize)
+        .parse(&ctx, 3)
+        .into_result()
+        .ok()
+        .flatten()
+        .unwrap();
+    assert_eq!(token_range(&tree, &buffer), Some(0..4));
+    assert_eq!(token_range(&second, &buffer), Some(3..4));
+}
+
+#[test]
+fn spliced_inputs_read_through_to_the_original() {
+    use std::cell::Cell;
+
+    let pulled = Cell::new(0);
+    let source = tokens(10_000).inspect(|_| pulled.set(pulled.get() + 1));
+    let buffered = BufferedTokens::new(source);
+    let inserted = vec![
+        Token::new(70, Span::new(1, 2, 2)),
+        Token::new(71, Span::new(1, 2, 2)),
+    ];
+    let spliced = Spliced::new(&buffered, 1, inserted);
+
+    let tys: Vec<usize> = (0..5)
+        .filter_map(|i| spliced.get(i))
+        .map(|t| *t.ty())
+        .collect();
+    assert_eq!(tys, [0, 70, 71, 2, 3]);
+    assert!(spliced.span_last().is_some());
+    assert!(pulled.get() < 1_000);
+    assert_eq!(spliced.len(), 10_001);
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "json")]
+
+mod common;
+
+use common::tokens;
+use roder::json::{errors_to_json, result_to_json, tree_to_json};
+use roder::prelude::*;
+use roder::trace::{Step, TraceRecorder};
+
+#[test]
+fn results_serialize_as_a_tree_or_errors() {
+    let parser = seq([just(1u8), just(2u8)]);
+
+    let parsed = parser.parse_all(&tokens(&[1, 2]));
+    let json = result_to_json(&parsed).unwrap();
+    assert!(json.starts_with(r#"{"tree":{"Nested":[{"Token":{"ty":1,"#));
+    assert_eq!(
+        json,
+        format!(
+            r#"{{"tree":{}}}"#,
+            tree_to_json(parsed.as_ref().ok().unwrap()).unwrap()
+        )
+    );
+
+    let failed = parser.parse_all(&tokens(&[1, 3]));
+    let json = result_to_json(&failed).unwrap();
+    assert!(json.starts_with(r#"{"errors":[{"kind":"Syntax","expected":"2","#));
+    assert_eq!(
+        json,
+        format!(
+            r#"{{"errors":{}}}"#,
+            errors_to_json(failed.as_ref().err().unwrap()).unwrap()
+        )
+    );
+}
+
+#[test]
+fn recorded_steps_round_trip() {
+    let recorder = TraceRecorder::new();
+    let tokens = tokens(&[2]);
+    let ctx = Context::new(&tokens).with_recorder(&recorder);
+    choice([just(1u8), just(2u8)]).parse(&ctx, 0);
+    let steps = recorder.into_steps();
+
+    let json = serde_json::to_string(&steps).unwrap();
+    assert!(json.starts_with(r#"[{"Enter":{"rule":"1 or 2","depth":0,"offset":0}}"#));
+    assert_eq!(serde_json::from_str::<Vec<Step>>(&json).unwrap(), steps);
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn playgrounds_parse_words_into_json() {
+    use roder::wasm::{words, Playground};
+
+    let tokens = words("let x\n  = 1");
+    assert_eq!(tokens[2].span(), &Span::new(2, 3, 3));
+    assert_eq!(tokens[3].ty(), "1");
+
+    let word = |w: &str| just(w.to_string());
+    let playground = Playground::new(seq([word("let"), word("x"), word("="), word("1")]));
+    assert!(playground.parse("let x = 1").starts_with(r#"{"tree":"#));
+    assert!(playground.parse("let x 1").contains(r#""expected":"=""#));
+}
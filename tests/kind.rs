@@ -0,0 +1,42 @@
+use roder::prelude::*;
+use roder_derive::TokenKind;
+
+#[derive(Clone, PartialEq, TokenKind)]
+enum TokenType {
+    Id(String),
+    LBracket,
+    Pair { key: String, value: String },
+}
+
+#[test]
+fn derived_predicates_and_kinds() {
+    let id = TokenType::Id("x".to_string());
+
+    assert!(id.is_id());
+    assert!(TokenType::LBracket.is_l_bracket());
+    assert!(!id.is_pair());
+    assert!(TokenType::Pair {
+        key: "k".to_string(),
+        value: "v".to_string(),
+    }
+    .is_pair());
+    assert_eq!(id.kind(), TokenTypeKind::Id);
+}
+
+#[test]
+fn kind_combinator_matches_by_discriminant() {
+    let tokens = vec![Token::new(
+        TokenType::Id("a".to_string()),
+        Span::new(1, 1, 1),
+    )];
+    let ctx = Context::new(&tokens);
+
+    assert!(matches!(
+        kind(TokenTypeKind::Id).parse(&ctx, 0).data(),
+        ParseResult::Ok(_)
+    ));
+    assert!(matches!(
+        kind(TokenTypeKind::LBracket).parse(&ctx, 0).data(),
+        ParseResult::Err(_)
+    ));
+}
@@ -0,0 +1,89 @@
+use roder::escape::Escapes;
+use roder::literal::{character, float, int, int_as, literal, token_literal, Literal};
+use roder::parse::Context;
+use roder::prelude::*;
+
+#[test]
+fn integers_decode_in_every_base() {
+    let span = Span::new(2, 5, 14);
+    assert_eq!(int("1_000_000", &span).ok(), Some(1_000_000));
+    assert_eq!(int("0xff_FF", &span).ok(), Some(0xffff));
+    assert_eq!(int("0o17", &span).ok(), Some(0o17));
+    assert_eq!(int("0b1010", &span).ok(), Some(10));
+
+    // the bad digit is at column 5 + 4
+    let error = int("0b10201", &span).err().unwrap();
+    assert_eq!(error.message(), "Invalid digit '2' in a base 2 literal");
+    assert_eq!(error.span(), &Span::new(2, 9, 9));
+    assert_eq!(
+        int("0x", &span).err().unwrap().message(),
+        "Expected digits in integer literal"
+    );
+
+    let error = int_as::<u8>("300", &span).err().unwrap();
+    assert_eq!(error.message(), "Integer literal 300 does not fit in u8");
+    assert_eq!(error.span(), &span);
+    assert_eq!(int_as::<i8>("127", &span).ok(), Some(127));
+    assert_eq!(
+        int(
+            "1_000_000_000_000_000_000_000_000_000_000_000_000_000",
+            &span
+        )
+        .err()
+        .unwrap()
+        .message(),
+        "Integer literal is too large"
+    );
+}
+
+#[test]
+fn floats_and_characters_decode() {
+    let span = Span::new(1, 1, 8);
+    assert_eq!(float("1_000.5", &span).ok(), Some(1000.5));
+    assert_eq!(float("2.5e-3", &span).ok(), Some(0.0025));
+    assert_eq!(float("3E2", &span).ok(), Some(300.0));
+
+    let error = float("1.5e+", &span).err().unwrap();
+    assert_eq!(error.message(), "Expected digits");
+    assert_eq!(error.span(), &Span::new(1, 5, 5));
+    assert_eq!(
+        float("1.2.3", &span).err().unwrap().span(),
+        &Span::new(1, 4, 4)
+    );
+    assert_eq!(
+        float("1e999", &span).err().unwrap().message(),
+        "Float literal is out of range"
+    );
+
+    let escapes = Escapes::default();
+    assert_eq!(character("'a'", &span, &escapes).ok(), Some('a'));
+    assert_eq!(character(r"'\n'", &span, &escapes).ok(), Some('\n'));
+    assert_eq!(
+        character("'ab'", &span, &escapes).err().unwrap().message(),
+        "Character literal holds more than one character"
+    );
+    assert_eq!(
+        character(r"'\q'", &span, &escapes).err().unwrap().span(),
+        &Span::new(1, 2, 3)
+    );
+}
+
+#[test]
+fn tokens_yield_their_values() {
+    let source = "x = 0x2A + 1.5 + 'z'";
+    let tokens = vec![
+        Token::new(0u8, Span::new(1, 5, 8)),
+        Token::new(0u8, Span::new(1, 12, 14)),
+        Token::new(0u8, Span::new(1, 18, 20)),
+    ];
+    let ctx = Context::new(&tokens).with_source(source);
+    let values: Vec<_> = (0..3)
+        .map(|i| token_literal(&ctx, i).unwrap().1.ok().unwrap())
+        .collect();
+    assert_eq!(
+        values,
+        [Literal::Int(42), Literal::Float(1.5), Literal::Char('z')]
+    );
+    assert!(token_literal(&ctx, 3).is_none());
+    assert!(literal("12abc", &Span::new(1, 1, 5)).is_err());
+}
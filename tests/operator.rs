@@ -0,0 +1,152 @@
+mod common;
+
+use common::tokens;
+use roder::operator::{Fixity, OperatorTable};
+use roder::parse::{Declare, Expression, Greediness, Repeatable, Sequence};
+use roder::prelude::*;
+
+// Shows a tree as tokens in brackets, e.g. `[1 20 [1 21 1]]`.
+fn show(data: &ParseData<u8>) -> String {
+    match data {
+        ParseData::Token(t) => t.ty().to_string(),
+        ParseData::Nested(items) => {
+            let items: Vec<_> = items.iter().map(show).collect();
+            format!("[{}]", items.join(" "))
+        }
+        _ => "?".to_string(),
+    }
+}
+
+// declaration := 9 op precedence, with operators at 20.. and precedences below 10
+fn declare(data: &ParseData<u8>, table: &OperatorTable<u8>) {
+    if let ParseData::Nested(items) = data {
+        if let (ParseData::Token(op), ParseData::Token(p)) = (&items[1], &items[2]) {
+            table.insert(*op.ty(), Fixity::InfixLeft, u16::from(*p.ty()));
+        }
+    }
+}
+
+fn program() -> Box<dyn Parser<u8>> {
+    let declaration = seq([
+        just(9u8),
+        pred("operator", |t: &u8| *t >= 20),
+        pred("precedence", |t: &u8| *t < 10),
+    ]);
+    let declarations = many(Box::new(Declare::from("declaration", declaration, declare)));
+    let expression = Box::new(Expression::from("expression", just(1u8)));
+    seq([declarations, expression])
+}
+
+#[test]
+fn operators_follow_precedence_and_associativity() {
+    let table = OperatorTable::new();
+    table.insert(21u8, Fixity::InfixLeft, 2);
+    table.insert(22u8, Fixity::InfixRight, 3);
+    table.insert(23u8, Fixity::Prefix, 4);
+    let expression = Expression::from("expression", just(1u8));
+
+    let parse = |tys: &[u8]| {
+        let input = tokens(tys);
+        let ctx = Context::new(&input).with_operators(&table);
+        show(&expression.parse_all_in(&ctx).ok().unwrap())
+    };
+    assert_eq!(parse(&[1, 21, 1, 21, 1]), "[[1 21 1] 21 1]");
+    assert_eq!(parse(&[1, 22, 1, 22, 1]), "[1 22 [1 22 1]]");
+    assert_eq!(parse(&[23, 1, 22, 1, 21, 1]), "[[[23 1] 22 1] 21 1]");
+
+    let input = tokens(&[1, 21]);
+    let ctx = Context::new(&input).with_operators(&table);
+    let errors = expression.parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn declarations_extend_the_table_mid_parse() {
+    let table = OperatorTable::new();
+    table.insert(21u8, Fixity::InfixLeft, 2);
+
+    // `20` is declared looser than `21` by the input itself
+    let input = tokens(&[9, 20, 1, 1, 20, 1, 21, 1]);
+    let ctx = Context::new(&input).with_operators(&table);
+    let tree = program().parse_all_in(&ctx).ok().unwrap();
+    assert_eq!(show(&tree), "[[[9 20 1]] [1 20 [1 21 1]]]");
+    assert_eq!(table.infix(&20), Some((Fixity::InfixLeft, 1)));
+
+    // undeclared, it is just a stray token
+    let fresh = OperatorTable::new();
+    let input = tokens(&[1, 20, 1]);
+    let ctx = Context::new(&input).with_operators(&fresh);
+    assert!(program().parse_all_in(&ctx).is_err());
+}
+
+#[test]
+fn failed_alternatives_take_back_their_declarations() {
+    let table = OperatorTable::new();
+    let declaration = || {
+        seq([
+            just(9u8),
+            pred("operator", |t: &u8| *t >= 20),
+            pred("precedence", |t: &u8| *t < 10),
+        ])
+    };
+    // the first alternative declares `20`, then fails for want of a 4
+    let grammar = choice([
+        seq([
+            Box::new(Declare::from("declaration", declaration(), declare)) as Box<dyn Parser<u8>>,
+            just(4u8),
+        ]),
+        declaration(),
+    ]);
+
+    let input = tokens(&[9, 20, 1]);
+    let ctx = Context::new(&input).with_operators(&table);
+    assert!(grammar.parse_all_in(&ctx).is_ok());
+    assert_eq!(table.infix(&20), None);
+
+    // hand-written parsers get the same through checkpoints
+    let checkpoint = ctx.checkpoint(1);
+    table.insert(20, Fixity::InfixRight, 5);
+    assert_eq!(ctx.rewind(&checkpoint), 1);
+    assert!(table.is_empty());
+}
+
+#[test]
+fn backtracking_takes_back_declarations() {
+    let declaration = || {
+        seq([
+            just(9u8),
+            pred("operator", |t: &u8| *t >= 20),
+            pred("precedence", |t: &u8| *t < 10),
+        ])
+    };
+    let declared = || Box::new(Declare::from("declaration", declaration(), declare));
+    let parse = |grammar: Box<dyn Parser<u8>>, tys: &[u8]| {
+        let table = OperatorTable::new();
+        let input = tokens(tys);
+        let ok = grammar
+            .parse_all_in(&Context::new(&input).with_operators(&table))
+            .is_ok();
+        (ok, table.infix(&20).is_some(), table.infix(&21).is_some())
+    };
+
+    // an optional sequence that fails after declaring
+    let maybe = Sequence::from("maybe", true, vec![declared(), just(4u8)]);
+    let grammar = seq([Box::new(maybe), declaration()]);
+    assert_eq!(parse(grammar, &[9, 20, 1]), (true, false, false));
+
+    // a repetition whose last item fails after declaring
+    let item = seq([declared(), just(4u8)]);
+    let grammar = seq([
+        Box::new(Repeatable::from("items", true, item)) as Box<dyn Parser<u8>>,
+        declaration(),
+    ]);
+    assert_eq!(
+        parse(grammar, &[9, 20, 1, 4, 9, 21, 1]),
+        (true, true, false)
+    );
+
+    // a greedy repetition giving back the item that declared `21`
+    let items = Repeatable::from("items", true, declared()).with_greediness(Greediness::Greedy);
+    let grammar = seq([Box::new(items) as Box<dyn Parser<u8>>, declaration()]);
+    assert_eq!(parse(grammar, &[9, 20, 1, 9, 21, 1]), (true, true, false));
+}
@@ -0,0 +1,702 @@
+mod common;
+
+use common::tokens;
+use roder::prelude::*;
+
+#[test]
+fn parse_all_rejects_trailing_tokens() {
+    let parser = many(just(1u8));
+
+    assert!(parser.parse_all(&tokens(&[1])).is_ok());
+
+    let errors = parser.parse_all(&tokens(&[1, 2])).err().unwrap();
+    assert_eq!(errors[0].span(), &Span::new(1, 2, 2));
+}
+
+#[test]
+fn parse_partial_reports_remainder() {
+    let (_, rest) = many(just(1u8))
+        .parse_partial(&tokens(&[1, 2, 3]))
+        .ok()
+        .unwrap();
+
+    assert_eq!(rest, 1);
+}
+
+#[test]
+fn results_convert_to_std_result() {
+    let input = tokens(&[1, 2]);
+    let ctx = Context::new(&input);
+
+    fn first(ctx: &Context<u8>) -> Result<Option<ParseData<u8>>, ParseError> {
+        just(1u8).parse(ctx, 0).into_result()
+    }
+
+    assert!(matches!(first(&ctx), Ok(Some(ParseData::Token(_)))));
+    assert!(just(2u8).parse(&ctx, 0).data().is_err());
+    assert!(just(1u8).parse(&ctx, 0).into_result().is_ok());
+
+    let data = just(2u8).parse(&ctx, 1).expect("second token");
+    assert!(matches!(data, ParseData::Token(t) if *t.ty() == 2));
+}
+
+#[test]
+fn every_combinator_reports_consumed_tokens() {
+    let input = tokens(&[1, 2, 3, 4]);
+    let ctx = Context::new(&input);
+
+    assert_eq!(just(1u8).parse(&ctx, 0).consumed(), 1);
+    assert_eq!(just(2u8).parse(&ctx, 0).consumed(), 0);
+    assert_eq!(pred("nine", |t: &u8| *t == 9).parse(&ctx, 0).consumed(), 0);
+    assert_eq!(not(just(2u8)).parse(&ctx, 0).consumed(), 0);
+    assert_eq!(
+        seq([just(1u8), just(2u8), just(9u8)])
+            .parse(&ctx, 0)
+            .consumed(),
+        0
+    );
+    assert_eq!(
+        choice([just(9u8), seq([just(1u8), just(2u8)])])
+            .parse(&ctx, 0)
+            .consumed(),
+        2
+    );
+}
+
+#[test]
+fn nested_sequences_inside_repeatable_advance_by_consumption() {
+    // (1 (2 3)) repeated: each item is a two-element Nested spanning three tokens.
+    let input = tokens(&[1, 2, 3, 1, 2, 3, 4]);
+    let ctx = Context::new(&input);
+    let item = seq([just(1u8), seq([just(2u8), just(3u8)])]);
+    let parse = many(item).parse(&ctx, 0);
+
+    assert_eq!(parse.consumed(), 6);
+    assert!(matches!(parse.data(), ParseResult::Ok(ParseData::Nested(l)) if l.len() == 2));
+
+    let doc = seq([
+        many(seq([just(1u8), seq([just(2u8), just(3u8)])])),
+        just(4u8),
+    ]);
+    assert!(doc.parse_all(&input).is_ok());
+}
+
+#[test]
+fn repeatable_stops_on_zero_width_matches() {
+    let input = tokens(&[1]);
+    let ctx = Context::new(&input);
+
+    assert_eq!(many(seq([])).parse(&ctx, 0).consumed(), 0);
+}
+
+#[test]
+fn recover_emits_error_nodes_and_keeps_going() {
+    // statements are `1 3`, with 3 as the terminator; the second one is broken.
+    let input = tokens(&[1, 3, 2, 2, 3, 1, 3]);
+    let tolerant = many(choice([
+        recover(seq([just(1u8), just(3u8)]), |t| *t == 3),
+        just(3u8),
+    ]));
+
+    let tree = tolerant.parse_all(&input).ok().unwrap();
+    let errors = tree.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), &Span::new(1, 3, 3));
+    assert!(
+        matches!(&tree, ParseData::Nested(l) if matches!(&l[1], ParseData::Error { skipped, .. } if skipped.len() == 2))
+    );
+}
+
+#[test]
+fn empty_matches_nothing_and_choice_can_accept_none() {
+    let input = tokens(&[2]);
+    let ctx = Context::new(&input);
+
+    let parse = choice([just(1u8), empty()]).parse(&ctx, 0);
+    assert!(parse.data().is_ok());
+    assert_eq!(parse.consumed(), 0);
+
+    let maybe_one = || Box::new(roder::parse::OfType::from("one", true, 1u8));
+    assert!(maybe_one().parse(&ctx, 0).data().is_none());
+    assert!(roder::choice!["either"; maybe_one()]
+        .parse(&ctx, 0)
+        .data()
+        .is_err());
+    assert!(roder::choice!["either"; maybe_one()]
+        .accepting_none()
+        .parse(&ctx, 0)
+        .data()
+        .is_none());
+    assert!(seq([maybe_one(), just(2u8)]).parse_all(&input).is_ok());
+}
+
+#[test]
+fn sequence_errors_name_what_came_before() {
+    use roder::parse::OfType;
+
+    let expr = || Box::new(OfType::from("expression", false, 1u8));
+    let semi = || Box::new(OfType::from("';'", false, 2u8));
+    let stmt = roder::seq!["statement"; expr(), semi()];
+
+    let errors = stmt.parse_all(&tokens(&[1u8, 3])).err().unwrap();
+    assert_eq!(errors[0].after(), Some("expression"));
+    assert_eq!(
+        errors[0].to_string(),
+        "Syntax error: expected ';' after expression at 1:2-2"
+    );
+
+    let errors = stmt.parse_all(&tokens(&[3])).err().unwrap();
+    assert_eq!(errors[0].after(), None);
+}
+
+#[test]
+fn step_budget_aborts_with_the_deepest_rule() {
+    use roder::parse::OfType;
+
+    let input = tokens(&[1, 1, 1, 1, 2]);
+    let inner =
+        roder::seq!["inner"; OfType::from("one", false, 1u8), OfType::from("three", false, 3u8)];
+    let parser = choice([many(Box::new(inner)), many(just(1u8))]);
+
+    let ctx = Context::new(&input).with_step_budget(4);
+    let errors = parser.parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::BudgetExceeded);
+    assert_eq!(errors[0].expected(), "one");
+    assert!(ctx.budget_exceeded());
+
+    let ctx = Context::new(&input).with_step_budget(1000);
+    assert_eq!(
+        parser.parse_all_in(&ctx).err().unwrap()[0].kind(),
+        ErrorKind::Syntax
+    );
+    assert!(ctx.steps_taken() < 20);
+}
+
+#[test]
+fn cancelled_parses_report_a_distinct_error() {
+    use roder::budget::CancellationToken;
+
+    let input = tokens(&[1, 1]);
+    let cancel = CancellationToken::new();
+    let parser = many(just(1u8));
+
+    let ctx = Context::new(&input).with_cancellation(cancel.clone());
+    assert!(parser.parse_all_in(&ctx).is_ok());
+
+    cancel.cancel();
+    let ctx = Context::new(&input).with_cancellation(cancel);
+    let errors = parser.parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::Cancelled);
+    assert!(ctx.cancelled() && !ctx.budget_exceeded());
+    assert_eq!(ctx.steps_taken(), 1);
+}
+
+#[test]
+fn progress_is_reported_every_n_tokens() {
+    use std::cell::RefCell;
+
+    let input = tokens(&[1; 25]);
+    let seen = RefCell::new(vec![]);
+    let report = |offset| seen.borrow_mut().push(offset);
+    let ctx = Context::new(&input).with_progress(10, &report);
+
+    assert!(many(just(1u8)).parse_all_in(&ctx).is_ok());
+    assert_eq!(*seen.borrow(), vec![10, 20, 25]);
+}
+
+#[test]
+fn balanced_delimiters_report_where_they_went_wrong() {
+    // 1 ( 2 ) 3 [ 4 ] 0 other
+    let parens = || balanced([(1u8, 2u8), (3u8, 4u8)]);
+
+    let parse = parens()
+        .parse_partial(&tokens(&[1, 3, 0, 4, 1, 2, 2, 0]))
+        .ok()
+        .unwrap();
+    assert!(matches!(parse, (ParseData::TokenList(ref l), 7) if l.len() == 7));
+
+    let errors = parens().parse_all(&tokens(&[1, 1, 0, 2])).err().unwrap();
+    assert_eq!(errors[0].message(), "Unclosed 1 opened at 1:1-1");
+
+    let errors = parens().parse_all(&tokens(&[1, 3, 2])).err().unwrap();
+    assert_eq!(
+        errors[0].message(),
+        "Unexpected 2, expected 4 to close 3 opened at 1:2-2"
+    );
+    assert_eq!(errors[0].span(), &Span::new(1, 3, 3));
+
+    let errors = parens().parse_all(&tokens(&[2])).err().unwrap();
+    assert_eq!(errors[0].message(), "Unexpected 2");
+
+    let shallow = roder::parse::Balanced::from("b", false, vec![(1u8, 2u8)]).with_max_depth(1);
+    assert!(shallow.parse_all(&tokens(&[1, 1, 2, 2])).is_err());
+    assert_eq!(parens().name(), Some("1…2 or 3…4"));
+}
+
+#[test]
+fn balanced_pairs_may_open_and_close_alike() {
+    // 7 " 1 inside 3 ( 4 )
+    let quoted = balanced([(7u8, 7u8), (3u8, 4u8)]);
+
+    let parse = quoted.parse_partial(&tokens(&[7, 1, 7, 7])).ok().unwrap();
+    assert!(matches!(parse, (ParseData::TokenList(ref l), 3) if l.len() == 3));
+    assert!(quoted.parse_all(&tokens(&[3, 7, 1, 7, 4])).is_ok());
+
+    let errors = quoted.parse_all(&tokens(&[3, 7, 1, 4])).err().unwrap();
+    assert_eq!(
+        errors[0].message(),
+        "Unexpected 4, expected 7 to close 7 opened at 1:2-2"
+    );
+}
+
+#[test]
+fn parse_prefix_hands_back_the_remainder() {
+    // item := 1 2 ; snippet := item+
+    let snippet = many(seq([just(1u8), just(2u8)]));
+
+    let prefix = snippet
+        .parse_prefix(&tokens(&[1, 2, 1, 2, 7, 7]))
+        .ok()
+        .unwrap();
+    assert_eq!((prefix.consumed, prefix.remaining), (4, 2));
+
+    // `1` must not be followed by `2`, so the greedy parse fails but the prefix `1` stands alone
+    let lone = seq([just(1u8), not(just(2u8))]);
+    let prefix = lone.parse_prefix(&tokens(&[1, 2, 3])).ok().unwrap();
+    assert_eq!((prefix.consumed, prefix.remaining), (1, 2));
+
+    assert!(just(1u8).parse_prefix(&tokens(&[2])).is_err());
+
+    // the greedy parse and one more over the cut input, however long the rest, each entering
+    // and leaving the top rule once
+    let recorder = roder::trace::TraceRecorder::new();
+    let input = tokens(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let ctx = Context::new(&input).with_recorder(&recorder);
+    let prefix = lone.parse_prefix_in(&ctx).ok().unwrap();
+    assert_eq!((prefix.consumed, prefix.remaining), (1, 7));
+    let runs = recorder
+        .into_steps()
+        .iter()
+        .filter(|s| s.depth() == 0)
+        .count();
+    assert_eq!(runs, 4);
+}
+
+#[test]
+fn running_out_of_input_is_not_a_syntax_error() {
+    use roder::parse::{OfType, Sequence};
+
+    // stmt := 1 2 3 ; program := stmt* — a REPL wants `1 2` to ask for more, `1 4` to fail
+    let stmt = || seq([just(1u8), choice([just(2u8), just(5u8)]), just(3u8)]);
+    let program = || many(stmt());
+
+    let errors = program()
+        .parse_all(&tokens(&[1, 2, 3, 1, 2]))
+        .err()
+        .unwrap();
+    assert!(errors[0].is_incomplete());
+    assert_eq!(errors[0].expected(), "3");
+    let errors = program().parse_all(&tokens(&[1])).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::UnexpectedEof);
+    let errors = program().parse_all(&tokens(&[1, 4])).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::Syntax);
+
+    let maybe = Sequence::from("maybe", true, vec![just(1u8), just(2u8)]);
+    assert!(maybe.parse_all(&tokens(&[1])).err().unwrap()[0].is_incomplete());
+    assert!(choice([stmt(), Box::new(OfType::from("x", false, 9u8))])
+        .parse_all(&tokens(&[1, 5]))
+        .err()
+        .unwrap()[0]
+        .is_incomplete());
+}
+
+#[test]
+fn running_out_of_input_under_not_is_still_a_syntax_error() {
+    // `1 2` and `1 3` both fail, so `1` can't be finished by more input
+    let parser = seq([not(seq([just(1u8), just(2u8)])), just(3u8)]);
+    let errors = parser.parse_all(&tokens(&[1])).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::Syntax);
+    assert_eq!(errors[0].expected(), "3");
+}
+
+#[test]
+fn optional_rules_still_back_off_at_the_end_of_input() {
+    use roder::parse::{Repeatable, Sequence};
+
+    // a `maybe` that runs out of input matches nothing, leaving the `1` to what follows
+    let maybe = Sequence::from("maybe", true, vec![just(1u8), just(2u8)]);
+    let parser = seq([Box::new(maybe), just(1u8)]);
+    assert!(parser.parse_all(&tokens(&[1])).is_ok());
+
+    let pairs = Repeatable::from("pairs", true, seq([just(1u8), just(2u8)]));
+    let parser = seq([Box::new(pairs), just(1u8)]);
+    assert!(parser.parse_all(&tokens(&[1, 2, 1])).is_ok());
+    assert!(parser.parse_all(&tokens(&[1, 2, 1, 2, 1])).is_ok());
+}
+
+#[test]
+fn context_quotes_source_in_diagnostics() {
+    use roder::parse::OfType;
+
+    let source = "let x = ;\nlet é = 1;";
+    let input = vec![
+        Token::new(1u8, Span::new(1, 1, 3)),
+        Token::new(2u8, Span::new(1, 5, 5)),
+        Token::new(3u8, Span::new(1, 7, 7)),
+        Token::new(5u8, Span::new(1, 9, 9)),
+        Token::new(1u8, Span::new(2, 1, 3)),
+        Token::new(2u8, Span::new(2, 5, 5)),
+    ];
+    let ctx = Context::new(&input).with_source(source);
+
+    assert_eq!(ctx.token_text(0), Some("let"));
+    assert_eq!(ctx.token_text(5), Some("é"));
+    assert_eq!(Span::new(3, 1, 1).text(source), None);
+
+    let value = OfType::from("value", false, 4u8);
+    let error = value.parse(&ctx, 3).into_result().err().unwrap();
+    assert_eq!(
+        ctx.render_error(&error),
+        "Syntax error: expected value at 1:9-9\n  |\n1 | let x = ;\n  |         ^"
+    );
+    assert_eq!(Context::new(&input).render_error(&error), error.to_string());
+}
+
+#[test]
+fn embedded_streams_parse_inside_a_token() {
+    use roder::parse::Embedded;
+
+    // 7 is a string token; its contents lex to one token per `+`-separated digit
+    fn expand(t: &Token<u8>) -> Option<Vec<Token<u8>>> {
+        let text = t.meta::<&str>()?;
+        let tokens = text
+            .char_indices()
+            .map(|(i, c)| {
+                let ty = if c == '+' { 0 } else { c as u8 - b'0' };
+                Token::new(ty, Span::new(1, i + 1, i + 1))
+            })
+            .collect();
+        Some(tokens)
+    }
+    let sum = || Embedded::from("sum", false, expand, seq([just(1u8), just(0u8), just(2u8)]));
+
+    // `x "1+2"` with the string starting at column 3
+    let input = vec![
+        Token::new(9u8, Span::new(4, 1, 1)),
+        Token::new(7u8, Span::new(4, 3, 7)).with_meta("1+2"),
+    ];
+    let tree = seq([just(9u8), Box::new(sum())])
+        .parse_all(&input)
+        .ok()
+        .unwrap();
+    let ParseData::Nested(outer) = &tree else {
+        panic!("expected a sequence");
+    };
+    assert_eq!(
+        outer[1].first_token().map(|t| t.span()),
+        Some(&Span::new(4, 3, 3))
+    );
+
+    let input = vec![Token::new(7u8, Span::new(4, 3, 7)).with_meta("1+1")];
+    let errors = sum().parse_all(&input).err().unwrap();
+    assert_eq!(errors[0].span(), &Span::new(4, 5, 5));
+
+    // the expansion is parsed under the outer budget and rule path
+    let ctx = Context::new(&input).with_paths();
+    let errors = sum().parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].path(), Some("sum.1 0 2.2"));
+    let ctx = Context::new(&input).with_step_budget(3);
+    let errors = sum().parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::BudgetExceeded);
+
+    // a string cut short is wrong, not unfinished
+    let input = vec![Token::new(7u8, Span::new(4, 3, 7)).with_meta("1+")];
+    assert_eq!(
+        sum().parse_all(&input).err().unwrap()[0].kind(),
+        ErrorKind::Syntax
+    );
+}
+
+#[test]
+fn keyed_sets_reject_duplicate_keys() {
+    use roder::parse::KeyedSet;
+
+    // entry := key 0 value, where keys are 10.. and values 1..
+    fn key(entry: &ParseData<u8>) -> Option<String> {
+        Some(entry.first_token()?.ty().to_string())
+    }
+    let config = || {
+        let entry = seq([
+            pred("key", |t: &u8| *t >= 10),
+            just(0u8),
+            pred("value", |t: &u8| *t < 10),
+        ]);
+        KeyedSet::from("config", false, entry, key)
+    };
+
+    let tree = config()
+        .parse_all(&tokens(&[10, 0, 1, 11, 0, 2]))
+        .ok()
+        .unwrap();
+    let map = config().map(&tree);
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["10", "11"]);
+
+    let errors = config()
+        .parse_all(&tokens(&[10, 0, 1, 11, 0, 2, 10, 0, 3]))
+        .err()
+        .unwrap();
+    assert_eq!(errors[0].span(), &Span::new(1, 7, 7));
+    assert_eq!(
+        errors[0].message(),
+        "Duplicate key 10, first given at 1:1-1"
+    );
+}
+
+#[test]
+fn skippable_tokens_are_stepped_over_but_kept() {
+    // 0 is whitespace: `1 2+` parses around it at either end and between items
+    let input = tokens(&[0, 1, 0, 0, 2, 0, 2, 0]);
+    let ctx = Context::new(&input).with_skippable(|t: &u8| *t == 0);
+    let grammar = seq([just(1u8), many(just(2u8))]);
+    let tree = grammar.parse_all_in(&ctx).ok().unwrap();
+    assert_eq!(tree.first_token().unwrap().span(), &Span::new(1, 2, 2));
+    assert_eq!(ctx.skip(2), 4);
+    assert_eq!(input.len(), 8);
+
+    // without it the whitespace is just another token
+    assert!(grammar.parse_all(&input).is_err());
+}
+
+#[test]
+fn keywords_match_case_insensitively_and_through_aliases() {
+    use roder::parse::Keyword;
+
+    // 1 is an identifier, 7 the canonical `elsif` keyword
+    let source = "ELSIF elif else";
+    let input = vec![
+        Token::new(1u8, Span::new(1, 1, 5)),
+        Token::new(1u8, Span::new(1, 7, 10)),
+        Token::new(1u8, Span::new(1, 12, 15)),
+    ];
+    let ctx = Context::new(&input).with_source(source);
+    let elsif = Keyword::from("elsif", false, |t: &u8| *t == 1, "elsif", 7u8).alias("elif");
+
+    for offset in [0, 1] {
+        let token = elsif.parse(&ctx, offset).into_result().ok().unwrap();
+        assert!(matches!(token, Some(ParseData::Token(t)) if *t.ty() == 7));
+    }
+    assert!(elsif.parse(&ctx, 2).into_result().is_err());
+
+    // without source text there is nothing to compare against
+    assert!(elsif.parse(&Context::new(&input), 0).into_result().is_err());
+}
+
+#[test]
+fn paths_name_the_chain_of_rules() {
+    use roder::parse::{OfType, Sequence};
+
+    // document := item ; item := 1 value ; value := 2
+    let document = || {
+        let value = Box::new(OfType::from("value", false, 2u8));
+        let item = Sequence::from("item", false, vec![just(1u8), value]);
+        Sequence::from(
+            "document",
+            false,
+            vec![Box::new(item) as Box<dyn Parser<u8>>],
+        )
+    };
+
+    let input = tokens(&[1, 2]);
+    let ctx = Context::new(&input).with_paths();
+    assert_eq!(document().parse(&ctx, 0).path(), Some("document"));
+
+    let input = tokens(&[1, 3]);
+    let ctx = Context::new(&input).with_paths();
+    let errors = document().parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].path(), Some("document.item.value"));
+    assert_eq!(document().parse_all(&input).err().unwrap()[0].path(), None);
+}
+
+#[test]
+fn choices_report_the_alternative_that_matched() {
+    use roder::parse::{Choice, OfType};
+
+    let literal = Choice::from(
+        "literal",
+        false,
+        vec![
+            Box::new(OfType::from("number", false, 1u8)),
+            Box::new(OfType::from("string", false, 2u8)),
+        ],
+    );
+    let input = tokens(&[2]);
+    let parse = literal.parse(&Context::new(&input), 0);
+    assert_eq!(parse.alternative(), Some(1));
+    assert_eq!(parse.type_parsed(), "string");
+    assert_eq!(
+        just(2u8).parse(&Context::new(&input), 0).alternative(),
+        None
+    );
+}
+
+#[test]
+fn splices_expand_macros_mid_stream() {
+    use roder::parse::{Deferred, Repeatable, Splice};
+
+    // 5 expands to `1 1`, 6 to `5 1`, 7 to itself and 8 to `1 2`
+    fn expand(token: &Token<u8>) -> Option<Vec<Token<u8>>> {
+        let tys: &[u8] = match token.ty() {
+            5 => &[1, 1],
+            6 => &[5, 1],
+            7 => &[7],
+            8 => &[1, 2],
+            _ => return None,
+        };
+        Some(tokens(tys))
+    }
+    // statements := 1* (macro statements)?
+    fn statements() -> Box<dyn Parser<u8>> {
+        let rest = Box::new(Deferred::from("statements", statements));
+        let ones: Box<dyn Parser<u8>> = Box::new(Repeatable::from("ones", true, just(1u8)));
+        seq([ones, Box::new(Splice::from("macro", true, expand, rest))])
+    }
+
+    let input = tokens(&[1, 6, 1, 5]);
+    let (_, consumed) = statements().parse_partial(&input).ok().unwrap();
+    assert_eq!(consumed, 4);
+
+    let errors = statements().parse_all(&tokens(&[1, 8])).err().unwrap();
+    assert_eq!(errors[0].message(), "Macro expansion only partly used");
+    assert_eq!(errors[0].provenance().unwrap().span(), &Span::new(1, 2, 2));
+
+    let errors = statements().parse_all(&tokens(&[7])).err().unwrap();
+    assert_eq!(errors[0].message(), "Macro expansion too deep");
+
+    // expansions count against the budget of the parse they are part of
+    let errors = statements()
+        .parse_all_in(&Context::new(&tokens(&[7])).with_step_budget(20))
+        .err()
+        .unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::BudgetExceeded);
+}
+
+#[test]
+fn stats_summarize_a_parse() {
+    use roder::parse::ParseStats;
+
+    let input = tokens(&[1, 2, 2, 3]);
+    let ctx = Context::new(&input);
+    let stats = seq([just(1u8), many(just(2u8))]).parse(&ctx, 0).stats(&ctx);
+    assert_eq!(
+        stats,
+        ParseStats {
+            nodes: 5,
+            depth: 2,
+            consumed: 3,
+            total: 4,
+            errors: 0,
+            steps: ctx.steps_taken(),
+        }
+    );
+    assert!(stats.steps >= 4);
+
+    let failed = just(2u8).parse(&ctx, 0).stats(&ctx);
+    assert_eq!((failed.nodes, failed.errors), (0, 1));
+}
+
+#[test]
+fn scratch_buffers_are_reused_across_parses() {
+    use roder::scratch::Scratch;
+
+    let scratch = Scratch::new();
+    let grammar = seq([just(1u8), many(just(2u8))]);
+    let input = tokens(&[1, 2, 2]);
+
+    let tree = grammar
+        .parse_all_in(&Context::new(&input).with_scratch(&scratch))
+        .ok()
+        .unwrap();
+    assert_eq!(scratch.pooled(), 0);
+    scratch.recycle(tree);
+    assert_eq!(scratch.pooled(), 2);
+
+    let tree = grammar
+        .parse_all_in(&Context::new(&input).with_scratch(&scratch))
+        .ok()
+        .unwrap();
+    assert_eq!(tree.node_count(), 5);
+    assert_eq!(scratch.pooled(), 0);
+
+    // a failed attempt hands its buffer straight back
+    let input = tokens(&[1, 3]);
+    let ctx = Context::new(&input).with_scratch(&scratch);
+    assert!(seq([just(1u8), just(2u8)]).parse_all_in(&ctx).is_err());
+    assert_eq!(scratch.pooled(), 1);
+}
+
+#[test]
+fn repetitions_give_back_items_to_what_follows() {
+    use roder::parse::{Greediness, Predicate, Repeatable, Sequence};
+
+    // 1 any* 9, where 9 also counts as "any"
+    let body = |greediness| {
+        let any = Box::new(Predicate::from("any", false, |_: &u8| true));
+        Sequence::from(
+            "block",
+            false,
+            vec![
+                just(1u8),
+                Box::new(Repeatable::from("body", true, any).with_greediness(greediness)),
+                just(9u8),
+            ],
+        )
+    };
+    let input = tokens(&[1, 2, 9, 3, 9]);
+    let body_len = |greediness| {
+        let (data, used) = body(greediness)
+            .parse_partial(&input)
+            .map_err(|e| e[0].to_string())
+            .unwrap();
+        let ParseData::Nested(items) = data else {
+            unreachable!()
+        };
+        let len = match &items[1] {
+            ParseData::Nested(body) => body.len(),
+            _ => 0,
+        };
+        (len, used)
+    };
+
+    // possessive swallows both 9s and leaves nothing to close the block
+    assert!(body(Greediness::Possessive).parse_all(&input).is_err());
+    assert_eq!(body_len(Greediness::Greedy), (3, 5));
+    assert_eq!(body_len(Greediness::Lazy), (1, 3));
+    assert!(body(Greediness::Lazy).parse_all(&input).is_err());
+
+    // an empty body is allowed, so lazy stops before taking anything
+    let (data, used) = body(Greediness::Lazy)
+        .parse_partial(&tokens(&[1, 9, 9]))
+        .ok()
+        .unwrap();
+    assert_eq!(used, 2);
+    assert!(matches!(data, ParseData::Nested(items) if items.len() == 2));
+}
+
+#[test]
+fn giving_back_counts_the_first_try_once() {
+    use roder::coverage::Coverage;
+    use roder::parse::{Greediness, Repeatable};
+
+    let input = tokens(&[1, 1, 2]);
+    let attempts = |greediness| {
+        let items = Repeatable::from("items", false, just(1u8)).with_greediness(greediness);
+        let parser = seq([Box::new(items), just(2u8)]);
+        let coverage = Coverage::new();
+        let ctx = Context::new(&input).with_coverage(&coverage);
+        assert!(parser.parse_all_in(&ctx).is_ok());
+        (coverage.get("items").unwrap().attempts, ctx.steps_taken())
+    };
+
+    assert_eq!(attempts(Greediness::Possessive), (1, 6));
+    assert_eq!(attempts(Greediness::Greedy), (1, 6));
+}
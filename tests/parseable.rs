@@ -0,0 +1,123 @@
+mod common;
+
+use common::tokens;
+use roder::convert::{FromParse, Parseable as _};
+use roder::prelude::*;
+use roder_derive::{Parseable, TokenKind};
+
+#[derive(Clone, Debug, PartialEq, TokenKind)]
+enum Tk {
+    Ident(&'static str),
+    Num(i64),
+    LParen,
+    RParen,
+    Comma,
+    Semi,
+    True,
+}
+
+// call := ident '(' (expr (',' expr)*)? ')'
+#[derive(Parseable)]
+#[parse(token_type = Tk)]
+struct Call {
+    #[parse(kind = TkKind::Ident)]
+    name: Token<Tk>,
+    #[parse(token = Tk::LParen)]
+    _open: Token<Tk>,
+    #[parse(sep = Tk::Comma)]
+    args: Vec<Expr>,
+    #[parse(token = Tk::RParen)]
+    _close: Token<Tk>,
+}
+
+#[derive(Parseable)]
+#[parse(token_type = Tk)]
+enum Expr {
+    Call(Box<Call>),
+    Num(#[parse(kind = TkKind::Num)] Token<Tk>),
+    #[parse(token = Tk::True)]
+    True,
+}
+
+#[derive(Parseable)]
+#[parse(token_type = Tk)]
+struct Program {
+    statements: Vec<Statement>,
+}
+
+#[derive(Parseable)]
+#[parse(token_type = Tk)]
+struct Statement {
+    expr: Expr,
+    #[parse(token = Tk::Semi)]
+    _semi: Token<Tk>,
+}
+
+fn lower(input: Vec<Tk>) -> Result<Program, Vec<ParseError>> {
+    let tree = Program::parser().parse_all(&tokens(&input))?;
+    Program::from_parse(&tree).map_err(|e| vec![e])
+}
+
+#[test]
+fn derived_grammars_parse_and_lower() {
+    use Tk::*;
+
+    // f(1, g(), true); 2;
+    let program = lower(vec![
+        Ident("f"),
+        LParen,
+        Num(1),
+        Comma,
+        Ident("g"),
+        LParen,
+        RParen,
+        Comma,
+        True,
+        RParen,
+        Semi,
+        Num(2),
+        Semi,
+    ])
+    .ok()
+    .unwrap();
+
+    assert_eq!(program.statements.len(), 2);
+    let Expr::Call(call) = &program.statements[0].expr else {
+        panic!("expected a call");
+    };
+    assert_eq!(call.name.ty(), &Ident("f"));
+    assert!(
+        matches!(&call.args[..], [Expr::Num(n), Expr::Call(g), Expr::True]
+        if n.ty() == &Num(1) && g.args.is_empty())
+    );
+    assert!(matches!(&program.statements[1].expr, Expr::Num(_)));
+
+    // trailing commas aren't part of the grammar
+    assert!(lower(vec![Ident("f"), LParen, Num(1), Comma, RParen, Semi]).is_err());
+}
+
+#[test]
+fn rules_are_named_after_types_and_report_the_furthest_variant() {
+    use roder::parse::ParseData::{Nested, Token as Leaf};
+    use Tk::*;
+
+    assert_eq!(Expr::parser().name(), Some("Expr"));
+    assert_eq!(<Vec<Statement>>::parser().name(), Some("Vec<Statement>"));
+    assert_eq!(
+        <Option<Box<Call>>>::parser().name(),
+        Some("Option<Box<Call>>")
+    );
+
+    // a call missing its closing parenthesis; Num gives up at the first token
+    let [f, open, comma] = &tokens(&[Ident("f"), LParen, Comma])[..] else {
+        unreachable!()
+    };
+    let tree = Nested(vec![Nested(vec![
+        Leaf(f.clone()),
+        Leaf(open.clone()),
+        Nested(vec![]),
+        Leaf(comma.clone()),
+    ])]);
+    let err = Expr::from_parse(&tree).err().unwrap();
+    assert_eq!((err.expected(), err.span()), ("Tk::RParen", comma.span()));
+}
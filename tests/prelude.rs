@@ -0,0 +1,80 @@
+use roder::prelude::*;
+
+#[derive(Clone, PartialEq)]
+enum Tok {
+    Id,
+    Eq,
+    Num,
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Tok::Id => "id",
+            Tok::Eq => "=",
+            Tok::Num => "number",
+        })
+    }
+}
+
+#[test]
+fn prelude_builds_a_grammar() {
+    let assign = seq([just(Tok::Id), not(pred("number", |t| *t == Tok::Num))]);
+    let tokens = vec![
+        Token::new(Tok::Id, Span::new(1, 1, 1)),
+        Token::new(Tok::Eq, Span::new(1, 3, 3)),
+        Token::new(Tok::Num, Span::new(1, 5, 5)),
+    ];
+    let ctx = Context::new(&tokens);
+
+    assert!(
+        matches!(assign.parse(&ctx, 0).data(), ParseResult::Ok(ParseData::Nested(l)) if l.len() == 1)
+    );
+    assert!(matches!(
+        choice([just(Tok::Num), just(Tok::Id)])
+            .parse(&ctx, 0)
+            .data(),
+        ParseResult::Ok(_)
+    ));
+}
+
+#[test]
+fn macros_box_their_operands() {
+    let tokens = vec![Token::new(Tok::Num, Span::new(1, 1, 1))];
+    let ctx = Context::new(&tokens);
+    let value = roder::choice!["value"; just(Tok::Id), roder::seq!["number"; just(Tok::Num)]];
+
+    assert!(matches!(
+        value.parse(&ctx, 0).data(),
+        ParseResult::Ok(ParseData::Nested(_))
+    ));
+}
+
+#[test]
+fn shared_subrules_can_be_reused() {
+    let tokens = vec![Token::new(Tok::Eq, Span::new(1, 1, 1))];
+    let ctx = Context::new(&tokens);
+    let eq = just(Tok::Eq).shared();
+    let either = choice([Box::new(eq.clone()) as Box<dyn Parser<Tok>>, just(Tok::Id)]);
+
+    assert!(matches!(either.parse(&ctx, 0).data(), ParseResult::Ok(_)));
+    assert!(matches!(eq.parse(&ctx, 0).data(), ParseResult::Ok(_)));
+}
+
+#[test]
+fn rules_are_named_after_what_they_match() {
+    let tokens = vec![Token::new(Tok::Eq, Span::new(1, 1, 1))];
+    let ctx = Context::new(&tokens);
+
+    let value = choice([just(Tok::Num), seq([just(Tok::Id), just(Tok::Eq)])]);
+    let error = value.parse(&ctx, 0).into_result().err().unwrap();
+    assert_eq!(error.expected(), "number or (id =)");
+    assert_eq!(many(just(Tok::Id)).name(), Some("many id"));
+    assert_eq!(
+        pred("digit", |t: &Tok| *t == Tok::Num).name(),
+        Some("digit")
+    );
+
+    let long = choice((0..20).map(|_| just(Tok::Num)));
+    assert!(long.name().is_some_and(|n| n.ends_with("or …")));
+}
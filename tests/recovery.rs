@@ -0,0 +1,140 @@
+mod common;
+
+use common::tokens;
+use std::rc::Rc;
+
+use roder::parse::{ErrorProduction, Repeatable, Sequence};
+use roder::prelude::*;
+use roder::recovery::{Recovery, RecoveryStrategy, SkipTo};
+
+// stmt := 1 2 3 ; program := stmt+, with 3 as the statement terminator
+fn program(strategy: Rc<dyn RecoveryStrategy<u8>>) -> Repeatable<u8> {
+    let stmt = Sequence::from("stmt", false, vec![just(1u8), just(2u8), just(3u8)])
+        .with_recovery(strategy.clone());
+    Repeatable::from("program", false, Box::new(stmt)).with_recovery(strategy)
+}
+
+#[test]
+fn skip_to_inserts_missing_items_and_skips_junk() {
+    let strategy: Rc<dyn RecoveryStrategy<u8>> = Rc::new(SkipTo::new(|t: &u8| *t == 3));
+
+    // `1 3`: the 2 is missing and is stood in for by an empty error node
+    let tree = program(strategy.clone())
+        .parse_all(&tokens(&[1, 3, 1, 2, 3]))
+        .ok()
+        .unwrap();
+    let errors = tree.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), &Span::new(1, 2, 2));
+
+    // `1 9 9 3`: the junk before the terminator is skipped, then 2 is still missing
+    let tree = program(strategy.clone())
+        .parse_all(&tokens(&[1, 9, 9, 3]))
+        .ok()
+        .unwrap();
+    assert!(
+        matches!(&tree, ParseData::Nested(s) if matches!(&s[0], ParseData::Nested(items)
+        if matches!(&items[1], ParseData::Error { skipped, .. } if skipped.len() == 2)))
+    );
+    assert_eq!(tree.errors().len(), 2);
+
+    // strategies are per rule: here only the program recovers, skipping to the next `1`
+    let stmt = seq([just(1u8), just(2u8), just(3u8)]);
+    let program = Repeatable::from("program", false, stmt)
+        .with_recovery(Rc::new(SkipTo::new(|t: &u8| *t == 1)));
+    let tree = program
+        .parse_all(&tokens(&[9, 1, 2, 3, 1, 7, 1, 2, 3]))
+        .ok()
+        .unwrap();
+    let errors = tree.errors();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[1].span(), &Span::new(1, 6, 6));
+}
+
+#[test]
+fn strategies_can_give_up() {
+    let abort: Rc<dyn RecoveryStrategy<u8>> =
+        Rc::new(|_: &ParseError, _: &Context<u8>, _: usize| Recovery::Abort);
+    assert!(program(abort).parse_all(&tokens(&[1, 3])).is_err());
+
+    let budget: Rc<dyn RecoveryStrategy<u8>> = Rc::new(SkipTo::new(|t: &u8| *t == 3));
+    let input = tokens(&[1, 3, 1, 2, 3]);
+    let ctx = Context::new(&input).with_step_budget(2);
+    let errors = program(budget).parse_all_in(&ctx).err().unwrap();
+    assert_eq!(errors[0].kind(), ErrorKind::BudgetExceeded);
+}
+
+#[test]
+fn missing_tokens_are_inserted() {
+    use roder::parse::OfType;
+    use roder::recovery::InsertMissing;
+
+    // stmt := 1 2 semicolon ; program := stmt+, where only the semicolon is worth making up
+    let program = || {
+        let strategy =
+            InsertMissing::new(|e: &ParseError| (e.expected() == "semicolon").then_some(3));
+        let semicolon = Box::new(OfType::from("semicolon", false, 3u8));
+        let stmt = Sequence::from("stmt", false, vec![just(1u8), just(2u8), semicolon])
+            .with_recovery(Rc::new(strategy));
+        Repeatable::from("program", false, Box::new(stmt))
+    };
+
+    let tree = program().parse_all(&tokens(&[1, 2, 1, 2, 3])).ok().unwrap();
+    let ParseData::Nested(statements) = &tree else {
+        panic!("expected statements");
+    };
+    let ParseData::Nested(first) = &statements[0] else {
+        panic!("expected a statement");
+    };
+    let ParseData::Token(semi) = &first[2] else {
+        panic!("expected the inserted token");
+    };
+    assert_eq!(*semi.ty(), 3);
+    assert_eq!(semi.span(), &Span::new(1, 3, 2));
+    assert_eq!(semi.span_size(), 0);
+    assert!(semi.provenance().unwrap().is_inserted());
+
+    let errors = tree.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "Missing token");
+    assert_eq!(errors[0].expected(), "semicolon");
+
+    // at the end of input the token goes after the last one
+    let tree = program().parse_all(&tokens(&[1, 2])).ok().unwrap();
+    assert_eq!(tree.errors()[0].span(), &Span::new(1, 3, 2));
+    assert!(program().parse_all(&tokens(&[1, 3])).is_err());
+
+    // lexers counting columns from 0 put the next statement at column 0
+    let input: Vec<_> = [(1, 1), (2, 2), (1, 0), (2, 1), (3, 2)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (ty, cs))| Token::new(ty, Span::new(i / 2 + 1, cs, cs)))
+        .collect();
+    let tree = program().parse_all(&input).ok().unwrap();
+    assert_eq!(tree.errors()[0].span(), &Span::new(2, 0, 0));
+}
+
+#[test]
+fn error_productions_carry_the_authors_message() {
+    // stmt := 1 2 3 | <bad statement> 3 ; program := stmt+
+    let bad = ErrorProduction::from("statement", |t: &u8| *t == 3, "Statements start with 1");
+    let stmt = choice([
+        seq([just(1u8), just(2u8), just(3u8)]),
+        seq([Box::new(bad), just(3u8)]),
+    ]);
+    let program = Repeatable::from("program", false, stmt);
+
+    let tree = program
+        .parse_all(&tokens(&[1, 2, 3, 7, 8, 3, 1, 2, 3]))
+        .ok()
+        .unwrap();
+    let errors = tree.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "Statements start with 1");
+    assert_eq!(errors[0].expected(), "statement");
+    assert_eq!(errors[0].span(), &Span::new(1, 4, 4));
+    assert!(roder::snapshot::render(&tree, false).contains("\"Statements start with 1\""));
+
+    // a lone terminator leaves the error rule nothing to match
+    assert!(program.parse_all(&tokens(&[1, 2, 3, 3])).is_err());
+}
@@ -0,0 +1,52 @@
+use roder::relex::{relex, LineEdit};
+use roder::token::{Span, Token};
+
+// one token per word, typed by the word itself
+fn lex(text: &str, first_line: usize) -> Vec<Token<String>> {
+    let mut tokens = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let mut col = 1;
+        for word in line.split(' ') {
+            if !word.is_empty() {
+                let span = Span::new(first_line + i, col, col + word.len() - 1);
+                tokens.push(Token::new(word.to_string(), span));
+            }
+            col += word.len() + 1;
+        }
+    }
+    tokens
+}
+
+#[test]
+fn relexing_matches_a_full_lex() {
+    let old = "a b\nc d\ne f\n";
+    let new = "a b\nc x\ny\ne f\n";
+    let edit = LineEdit {
+        start: 2,
+        old_end: 2,
+        new_end: 3,
+    };
+
+    let mut calls = vec![];
+    let tokens = relex(lex(old, 1), new, edit, |text, ln| {
+        calls.push(text.to_string());
+        lex(text, ln)
+    });
+
+    assert_eq!(tokens, lex(new, 1));
+    assert_eq!(calls, vec!["c x\ny\n"]);
+}
+
+#[test]
+fn edits_from_line_zero_start_at_the_beginning() {
+    let old = "a b\nc d\n";
+    let new = "z\na b\nc d\n";
+    let edit = LineEdit {
+        start: 0,
+        old_end: 0,
+        new_end: 1,
+    };
+
+    let tokens = relex(lex(old, 1), new, edit, lex);
+    assert_eq!(tokens, lex(new, 1));
+}
@@ -0,0 +1,53 @@
+use roder::column::ColumnPolicy;
+use roder::prelude::*;
+use roder::rewrite::{RewriteError, SourceRewriter, TextEdit};
+
+#[test]
+fn edits_apply_against_the_original_text() {
+    let source = "let x = 1;\nlet yé = x + 2;\n";
+    let mut rewriter = SourceRewriter::new(source);
+
+    // rename `x` in both places, quote the 2 and drop the first `let`
+    rewriter
+        .add(TextEdit::replace(&Span::new(1, 5, 5), "count"))
+        .unwrap();
+    rewriter
+        .add(TextEdit::replace(&Span::new(2, 10, 10), "count"))
+        .unwrap();
+    rewriter
+        .add(TextEdit::insert_before(&Span::new(2, 14, 14), "\""))
+        .unwrap();
+    rewriter
+        .add(TextEdit::insert_after(&Span::new(2, 14, 14), "\""))
+        .unwrap();
+    rewriter.add(TextEdit::delete(&Span::new(1, 1, 4))).unwrap();
+    assert_eq!(
+        rewriter.apply().unwrap(),
+        "count = 1;\nlet yé = count + \"2\";\n"
+    );
+
+    assert_eq!(
+        rewriter.add(TextEdit::replace(&Span::new(1, 3, 6), "")),
+        Err(RewriteError::Overlap(0))
+    );
+}
+
+#[test]
+fn nodes_can_span_lines() {
+    let source = "f(a,\n  b)";
+    let node = ParseData::Nested(vec![
+        ParseData::Token(Token::new(1u8, Span::new(1, 3, 3))),
+        ParseData::Token(Token::new(1u8, Span::new(2, 3, 3))),
+    ]);
+    let mut rewriter = SourceRewriter::new(source);
+    rewriter
+        .add(TextEdit::node(&node, "a, b").unwrap())
+        .unwrap();
+    assert_eq!(rewriter.apply().unwrap(), "f(a, b)");
+
+    let mut rewriter = SourceRewriter::new(source).with_columns(ColumnPolicy::Bytes);
+    rewriter
+        .add(TextEdit::replace(&Span::new(3, 1, 1), ""))
+        .unwrap();
+    assert_eq!(rewriter.apply(), Err(RewriteError::OutOfSource(0)));
+}
@@ -0,0 +1,71 @@
+use roder::column::ColumnPolicy;
+use roder::prelude::*;
+use roder::roundtrip::{verify_lossless, verify_tokens, Divergence, DivergenceKind};
+use roder::token::attach_trivia;
+
+// 0 is whitespace
+fn lexed() -> Vec<Token<u8>> {
+    vec![
+        Token::new(1u8, Span::new(1, 1, 3)),
+        Token::new(0, Span::new(1, 4, 4)),
+        Token::new(2, Span::new(1, 5, 5)),
+        Token::new(3, Span::new(3, 1, 2)),
+    ]
+}
+
+#[test]
+fn lossless_trees_cover_the_source() {
+    let source = "let x\n\nén";
+    let tokens = attach_trivia(lexed(), |t| *t == 0);
+    let tree = ParseData::TokenList(tokens.clone());
+    assert_eq!(verify_lossless(&tree, source, ColumnPolicy::Chars), Ok(()));
+
+    // without the trivia the space is unaccounted for
+    let bare = ParseData::TokenList(vec![
+        tokens[0].clone().with_trivia(vec![], vec![]),
+        tokens[1].clone(),
+        tokens[2].clone(),
+    ]);
+    assert_eq!(
+        verify_lossless(&bare, source, ColumnPolicy::Chars),
+        Err(Divergence {
+            kind: DivergenceKind::Missing,
+            span: Span::new(1, 4, 4),
+        })
+    );
+
+    // the blank line may be skipped, but not one with text on it
+    assert_eq!(
+        verify_lossless(&tree, "let x\n?\nén", ColumnPolicy::Chars).map_err(|d| d.span),
+        Err(Span::new(2, 1, 1))
+    );
+    assert_eq!(
+        verify_lossless(&tree, "let x\n\nén!", ColumnPolicy::Chars).map_err(|d| d.kind),
+        Err(DivergenceKind::Missing)
+    );
+    assert_eq!(
+        verify_lossless(&tree, "let x\n\né", ColumnPolicy::Chars).map_err(|d| d.kind),
+        Err(DivergenceKind::OutOfSource)
+    );
+}
+
+#[test]
+fn lossy_trees_keep_the_token_sequence() {
+    let tokens = lexed();
+    let significant: Vec<_> = tokens.iter().filter(|t| *t.ty() != 0).cloned().collect();
+    let tree = ParseData::Nested(vec![
+        ParseData::Token(significant[0].clone()),
+        ParseData::TokenList(significant[1..].to_vec()),
+    ]);
+    assert_eq!(verify_tokens(&tree, &significant), Ok(()));
+
+    let divergence = verify_tokens(&tree, &tokens).err().unwrap();
+    assert_eq!(divergence.kind, DivergenceKind::Mismatch);
+    assert_eq!(divergence.span, Span::new(1, 5, 5));
+
+    let short = ParseData::Token(significant[0].clone());
+    assert_eq!(
+        verify_tokens(&short, &significant).map_err(|d| d.kind),
+        Err(DivergenceKind::Length)
+    );
+}
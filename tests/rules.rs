@@ -0,0 +1,175 @@
+mod common;
+
+use common::tokens;
+use roder::parse::Repeatable;
+use roder::prelude::*;
+use roder::rules::RuleSet;
+
+#[test]
+fn entry_points_share_rules() {
+    // expression := 1 (2 1)* ; document := (expression 3)+
+    let mut rules = RuleSet::new();
+    let expression = rules.insert(
+        "expression",
+        seq([
+            just(1u8),
+            Box::new(Repeatable::from("tail", true, seq([just(2u8), just(1u8)]))),
+        ]),
+    );
+    rules.insert(
+        "document",
+        many(seq([
+            Box::new(expression) as Box<dyn Parser<u8>>,
+            just(3u8),
+        ])),
+    );
+
+    assert!(rules.parse_all("expression", &tokens(&[1, 2, 1])).is_ok());
+    assert!(rules.parse_all("document", &tokens(&[1, 2, 1])).is_err());
+    assert!(rules
+        .parse_all("document", &tokens(&[1, 3, 1, 2, 1, 3]))
+        .is_ok());
+
+    let errors = rules.parse_all("type", &tokens(&[1])).err().unwrap();
+    assert_eq!(errors[0].message(), "Unknown entry point");
+    assert_eq!(
+        rules.names().collect::<Vec<_>>(),
+        ["document", "expression"]
+    );
+}
+
+#[test]
+fn flags_gate_rules_and_alternatives() {
+    // v2 adds `4` as a statement and a `5` rule of its own
+    let build = |flags: &[&str]| {
+        let mut rules = RuleSet::new().with_flags(flags.iter().copied());
+        let v2 = rules.when("v2", just(4u8));
+        rules.insert(
+            "statement",
+            choice([just(1u8), just(2u8)].into_iter().chain(v2)),
+        );
+        rules.insert_if("v2", "extra", just(5u8));
+        rules
+    };
+
+    let v1 = build(&[]);
+    assert!(v1.parse_all("statement", &tokens(&[4])).is_err());
+    assert!(v1.get("extra").is_none());
+
+    let v2 = build(&["v2"]);
+    assert!(v2.enabled("v2"));
+    assert!(v2.parse_all("statement", &tokens(&[4])).is_ok());
+    assert!(v2.parse_all("extra", &tokens(&[5])).is_ok());
+}
+
+#[test]
+fn diffs_name_added_removed_and_changed_rules() {
+    let old = || {
+        let mut rules = RuleSet::new();
+        rules.insert("literal", choice([just(1u8), just(2u8)]));
+        rules.insert("call", seq([just(5u8), just(6u8)]));
+        rules.insert("pair", choice([just(1u8), seq([just(2u8), just(3u8)])]));
+        rules.insert("legacy", just(9u8));
+        rules
+    };
+
+    let mut new = RuleSet::new();
+    new.insert("literal", choice([just(1u8), just(2u8), just(3u8)]));
+    new.insert("call", seq([just(5u8), just(7u8)]));
+    // same start and snippet, but the second alternative ends differently
+    new.insert("pair", choice([just(1u8), seq([just(2u8), just(4u8)])]));
+    new.insert("lambda", just(8u8));
+
+    let diff = RuleSet::diff(&old(), &new);
+    assert_eq!(diff.added, ["lambda"]);
+    assert_eq!(diff.removed, ["legacy"]);
+    assert_eq!(diff.changed, ["call", "literal", "pair"]);
+    assert!(RuleSet::diff(&old(), &old()).is_empty());
+}
+
+#[test]
+fn rules_no_entry_point_reaches_are_unused() {
+    // document := pair+ ; pair := value 3 value ; old := legacy legacy
+    let mut rules = RuleSet::new();
+    let value = rules.insert("value", choice([just(1u8), just(2u8)]));
+    let pair = rules.insert(
+        "pair",
+        seq([
+            Box::new(value.clone()) as Box<dyn Parser<u8>>,
+            just(3u8),
+            Box::new(value),
+        ]),
+    );
+    rules.insert("document", many(Box::new(pair)));
+    let legacy = rules.insert("legacy", just(9u8));
+    rules.insert(
+        "old",
+        seq([
+            Box::new(legacy.clone()) as Box<dyn Parser<u8>>,
+            Box::new(legacy),
+        ]),
+    );
+
+    assert_eq!(rules.unused(&["document"]), ["legacy", "old"]);
+    assert!(rules.unused(&["document", "old"]).is_empty());
+    assert_eq!(
+        rules.unused(&["value"]),
+        ["document", "legacy", "old", "pair"]
+    );
+}
+
+#[test]
+fn analysis_finds_unreachable_rules_loops_and_depths() {
+    use roder::rules::Depth;
+
+    // statement := expr 9 ; expr := term (0 term)* ; term := 1 | 5 expr 6 ;
+    // number := 1 ; pair := number number ; forever := 1 forever
+    let mut rules = RuleSet::new();
+    let nested = seq([just(5u8), rules.reference("expr"), just(6u8)]);
+    let term = rules.insert("term", choice([just(1u8), nested]));
+    let more = Repeatable::from("more", true, seq([just(0u8), Box::new(term.clone())]));
+    let expr = rules.insert(
+        "expr",
+        seq([Box::new(term) as Box<dyn Parser<u8>>, Box::new(more)]),
+    );
+    rules.insert("statement", seq([Box::new(expr), just(9u8)]));
+    let number = rules.insert("number", just(1u8));
+    rules.insert(
+        "pair",
+        seq([
+            Box::new(number.clone()) as Box<dyn Parser<u8>>,
+            Box::new(number),
+        ]),
+    );
+    rules.insert("forever", seq([just(1u8), rules.reference("forever")]));
+
+    assert!(rules
+        .parse_all("statement", &tokens(&[5, 1, 0, 1, 6, 9]))
+        .is_ok());
+
+    let analysis = rules.analyze(&["statement"]);
+    assert_eq!(analysis.unreachable, ["forever", "number", "pair"]);
+    assert_eq!(analysis.cycles, [vec!["expr", "term"], vec!["forever"]]);
+    let depth = |name: &str| analysis.depths[name];
+    let bounded = |d| Depth {
+        min: Some(d),
+        max: Some(d),
+    };
+    assert_eq!(depth("number"), bounded(1));
+    assert_eq!(depth("pair"), bounded(2));
+    assert_eq!(depth("term").min, Some(1));
+    assert_eq!(
+        depth("statement"),
+        Depth {
+            min: Some(3),
+            max: None
+        }
+    );
+    assert_eq!(
+        depth("forever"),
+        Depth {
+            min: None,
+            max: None
+        }
+    );
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use roder::prelude::*;
+use roder::session::ParseSession;
+
+// one token per word: `include` is 0, any other word is 1 and keeps its text as meta
+fn lex(text: &str) -> Vec<Token<u8>> {
+    let mut tokens = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let mut col = 1;
+        for word in line.split(' ') {
+            let span = Span::new(i + 1, col, col + word.len() - 1);
+            let ty = if word == "include" { 0 } else { 1 };
+            tokens.push(Token::new(ty, span).with_meta(word.to_string()));
+            col += word.len() + 1;
+        }
+    }
+    tokens
+}
+
+fn includes(tree: &ParseData<u8>) -> Vec<(String, Span)> {
+    let ParseData::Nested(items) = tree else {
+        return vec![];
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            ParseData::Nested(d) => {
+                let path = d[1].first_token()?;
+                Some((path.meta::<String>()?.clone(), path.span().clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn sessions_follow_includes_across_files() {
+    // file := (include name | name)+
+    let file = many(choice([seq([just(0u8), just(1u8)]), just(1u8)]));
+    let sources: HashMap<&str, &str> =
+        [("lib", "x\ninclude util"), ("util", "include lib\ninclude")]
+            .into_iter()
+            .collect();
+
+    let mut session = ParseSession::new(lex, includes);
+    let main = session.parse(
+        "main",
+        "include lib\ninclude missing".into(),
+        &*file,
+        |path, _| sources.get(path).map(|s| s.to_string()),
+    );
+
+    assert_eq!(session.files().count(), 3);
+    let util = session.id("util").unwrap();
+    assert!(session.file(util).tree().is_none());
+    assert_eq!(
+        session.render_errors(),
+        [
+            "main: Cannot resolve include missing: expected missing at 2:9-15",
+            "util: Unexpected end of input: expected 1 at 2:1-7\n  \
+             included from lib at 2:9-12\n  \
+             included from main at 1:9-11",
+        ]
+    );
+    assert!(session.file(main).included_from().is_none());
+}
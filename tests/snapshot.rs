@@ -0,0 +1,63 @@
+mod common;
+
+use common::tokens;
+use roder::assert_parse_snapshot;
+use roder::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Id,
+    Eq,
+    Num,
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Tok::Id => "id",
+            Tok::Eq => "=",
+            Tok::Num => "number",
+        })
+    }
+}
+
+#[test]
+fn snapshots_are_stable_and_optionally_spanned() {
+    let assign = seq([just(Tok::Id), seq([just(Tok::Eq), just(Tok::Num)])]);
+    let input = tokens(&[Tok::Id, Tok::Eq, Tok::Num]);
+
+    assert_parse_snapshot!(assign, input, "(\n  Id\n  (Eq Num)\n)");
+    assert_parse_snapshot!(
+        seq([just(Tok::Id), just(Tok::Eq), just(Tok::Num)]),
+        input,
+        spans,
+        "(Id@1:1-1 Eq@1:2-2 Num@1:3-3)"
+    );
+    assert_parse_snapshot!(
+        assign,
+        tokens(&[Tok::Id, Tok::Num]),
+        "error: expected = at 1:2-2: Syntax error\n"
+    );
+}
+
+#[test]
+fn assertion_macros_check_outcomes() {
+    use roder::{assert_fails, assert_parses};
+
+    let assign = seq([just(Tok::Id), just(Tok::Eq), just(Tok::Num)]);
+
+    assert_parses!(assign, tokens(&[Tok::Id, Tok::Eq, Tok::Num]));
+    assert_parses!(
+        assign,
+        tokens(&[Tok::Id, Tok::Eq, Tok::Num]),
+        ParseData::Nested(l) if l.len() == 3
+    );
+    assert_fails!(assign, tokens(&[Tok::Id, Tok::Num]));
+    assert_fails!(assign, tokens(&[Tok::Id, Tok::Num]), "at 1:2-2");
+}
+
+#[test]
+#[should_panic(expected = "errors do not mention")]
+fn assert_fails_reports_a_missing_needle() {
+    roder::assert_fails!(just(Tok::Id), tokens(&[Tok::Eq]), "Unexpected end");
+}
@@ -0,0 +1,79 @@
+use roder::parse::{Deferred, OfType, Predicate, Repeatable, Sequence};
+use roder::prelude::*;
+use roder::snippet::{snippet, SnippetPart};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Id,
+    Equals,
+    Str,
+    Semicolon,
+}
+
+fn text(t: &Tok) -> String {
+    match t {
+        Tok::Equals => "=".to_string(),
+        Tok::Semicolon => ";".to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&text(self))
+    }
+}
+
+// item := id = value ;?
+fn item() -> Box<dyn Parser<Tok>> {
+    Box::new(Sequence::from(
+        "item",
+        false,
+        vec![
+            Box::new(Predicate::from("id", false, |t| *t == Tok::Id)),
+            Box::new(OfType::from("=", false, Tok::Equals)),
+            Box::new(Predicate::from("value", false, |t| *t == Tok::Str)),
+            Box::new(OfType::from(";", true, Tok::Semicolon)),
+        ],
+    ))
+}
+
+#[test]
+fn rules_expand_into_templates() {
+    let template = snippet(&*item()).unwrap();
+    assert_eq!(
+        template.parts(),
+        [
+            SnippetPart::Placeholder("id".into()),
+            SnippetPart::Token(Tok::Equals),
+            SnippetPart::Placeholder("value".into()),
+        ]
+    );
+    assert_eq!(template.render(text), "<id> = <value>");
+    assert_eq!(template.render_tabstops(text), "${1:id} = ${2:value}");
+
+    // repetitions appear once, optional ones not at all, and recursion stops at a placeholder
+    let items = Repeatable::from("items", false, item());
+    assert_eq!(snippet(&items).unwrap().render(text), "<id> = <value>");
+    assert!(snippet(&Repeatable::from("items", true, item()))
+        .unwrap()
+        .parts()
+        .is_empty());
+    let nested = seq([just(Tok::Id), Box::new(Deferred::from("block", item))]);
+    assert_eq!(snippet(&*nested).unwrap().render(text), "Id <block>");
+}
+
+#[test]
+fn undescribable_parsers_give_no_snippet() {
+    struct Opaque;
+    impl Parser<Tok> for Opaque {
+        fn parse(&self, ctx: &Context<Tok>, offset: usize) -> Parse<Tok> {
+            just(Tok::Id).parse(ctx, offset)
+        }
+    }
+
+    assert!(snippet(&Opaque).is_none());
+    assert!(snippet(&*seq([just(Tok::Id), Box::new(Opaque)])).is_none());
+    // a choice falls through to an alternative that can describe itself
+    assert!(snippet(&*choice([Box::new(Opaque), just(Tok::Str)])).is_some());
+}
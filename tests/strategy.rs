@@ -0,0 +1,17 @@
+#![cfg(feature = "proptest")]
+
+use proptest::prelude::*;
+use roder::prelude::*;
+use roder::strategy::inputs;
+
+fn list() -> Shared<u8> {
+    seq([just(0u8), many(seq([just(1u8), just(2u8)])), just(3u8)]).shared()
+}
+
+proptest! {
+    #[test]
+    fn every_generated_list_parses(tokens in inputs(list())) {
+        prop_assert!(list().parse_all(&tokens).is_ok());
+        prop_assert_eq!(tokens.len() % 2, 0);
+    }
+}
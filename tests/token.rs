@@ -0,0 +1,179 @@
+use roder::index::NodeIndex;
+use roder::parse::{Context, ParseData, ParseResult, Parser, TokenPredicate};
+use roder::token::{attach_trivia, Provenance, Span, Token};
+
+#[test]
+fn meta_is_reachable_from_token_predicate() {
+    let tokens = vec![Token::new(0u8, Span::new(1, 1, 3)).with_meta(String::from("abc"))];
+    let ctx = Context::new(&tokens);
+
+    let parser = TokenPredicate::from("lexeme", false, |t| {
+        t.meta::<String>().is_some_and(|m| m == "abc")
+    });
+    assert!(matches!(parser.parse(&ctx, 0).data(), ParseResult::Ok(_)));
+
+    assert!(tokens[0].meta::<u32>().is_none());
+}
+
+#[test]
+fn trivia_is_attached_to_neighbouring_tokens() {
+    // 0 = trivia, 1 = significant
+    let tokens = vec![
+        Token::new(0u8, Span::new(1, 1, 5)),
+        Token::new(1u8, Span::new(2, 1, 1)),
+        Token::new(0u8, Span::new(2, 3, 8)),
+        Token::new(0u8, Span::new(3, 1, 4)),
+        Token::new(1u8, Span::new(4, 1, 1)),
+    ];
+
+    let tokens = attach_trivia(tokens, |t| *t == 0);
+
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0].leading().len(), 1);
+    assert_eq!(tokens[0].trailing().len(), 1);
+    assert_eq!(tokens[1].leading().len(), 1);
+    assert_eq!(tokens[1].leading()[0].span().ln(), 3);
+}
+
+#[test]
+fn tokens_compare_by_type_span_and_trivia() {
+    let plain = Token::new(1u8, Span::new(1, 1, 3));
+    let with_meta = Token::new(1u8, Span::new(1, 1, 3)).with_meta(7u32);
+
+    assert_eq!(plain, with_meta);
+    assert_ne!(plain, Token::new(1u8, Span::new(1, 2, 3)));
+    assert_eq!(plain.span(), &Span::new(1, 1, 3));
+    assert_eq!(
+        format!("{:?}", plain.span()),
+        "Span { ln: 1, cs: 1, ce: 3 }"
+    );
+
+    let set: std::collections::HashSet<_> = [plain, with_meta].into_iter().collect();
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn node_index_finds_smallest_enclosing_node() {
+    let a = Token::new(1u8, Span::new(1, 1, 3));
+    let b = Token::new(1u8, Span::new(1, 5, 7));
+    let c = Token::new(1u8, Span::new(2, 1, 2));
+    let tree = ParseData::Nested(vec![
+        ParseData::Nested(vec![ParseData::Token(a), ParseData::Token(b)]),
+        ParseData::Token(c),
+    ]);
+    let index = NodeIndex::build(&tree);
+
+    assert!(matches!(index.node_at(1, 6), Some(ParseData::Token(t)) if t.span().cs() == 5));
+    assert!(matches!(index.node_at(1, 4), Some(ParseData::Nested(l)) if l.len() == 2));
+    assert!(matches!(
+        index.node_at(1, 9),
+        Some(ParseData::Nested(l)) if matches!(l[0], ParseData::Nested(_))
+    ));
+    assert!(index.node_at(3, 1).is_none());
+    assert!(Span::new(1, 1, 3).overlaps(&Span::new(1, 3, 4)));
+}
+
+#[test]
+fn errors_on_synthesized_tokens_point_back_at_the_expansion() {
+    use roder::parse::OfType;
+    use std::sync::Arc;
+
+    // `inc!(x)` on line 3 expands to `x += 1`, and `inc!` was itself used inside `twice!` on line 5
+    let outer = Arc::new(Provenance::new(Span::new(5, 1, 9), "twice!"));
+    let inner = Arc::new(Provenance::new(Span::new(3, 1, 7), "inc!").with_parent(outer));
+    let tokens = vec![
+        Token::new(1u8, Span::new(3, 1, 7)).with_provenance(inner.clone()),
+        Token::new(3u8, Span::new(3, 1, 7)).with_provenance(inner.clone()),
+    ];
+    assert_eq!(inner.chain().count(), 2);
+    assert_eq!(tokens[0], Token::new(1u8, Span::new(3, 1, 7)));
+
+    let ctx = Context::new(&tokens);
+    let plus = OfType::from("'+='", false, 2u8);
+    let error = plus.parse(&ctx, 1).into_result().err().unwrap();
+    assert_eq!(error.provenance().map(|p| p.reason()), Some("inc!"));
+    assert_eq!(
+        ctx.render_error(&error),
+        "Syntax error: expected '+=' at 3:1-7\n\
+         note: expanded from inc! at 3:1-7\n\
+         note: expanded from twice! at 5:1-9"
+    );
+}
+
+#[test]
+fn doc_comments_attach_to_the_following_node() {
+    use roder::prelude::{just, many, seq};
+
+    // 0 = comment, 1 = `def`, 2 = name
+    let source = "## Adds one.\n# not a doc\ndef inc\n## Stray\ndef dec";
+    let lexed = vec![
+        Token::new(0u8, Span::new(1, 1, 12)),
+        Token::new(0u8, Span::new(2, 1, 11)),
+        Token::new(1u8, Span::new(3, 1, 3)),
+        Token::new(2u8, Span::new(3, 5, 7)),
+        Token::new(0u8, Span::new(4, 1, 8)),
+        Token::new(1u8, Span::new(5, 1, 3)),
+        Token::new(2u8, Span::new(5, 5, 7)),
+    ];
+    let tokens = attach_trivia(lexed, |t| *t == 0);
+    let tree = many(seq([just(1u8), just(2u8)]))
+        .parse_all(&tokens)
+        .ok()
+        .unwrap();
+
+    let is_doc = |c: &Token<u8>| c.span().text(source).is_some_and(|t| t.starts_with("##"));
+    let ParseData::Nested(defs) = &tree else {
+        panic!("expected a list of definitions");
+    };
+    let docs: Vec<_> = defs
+        .iter()
+        .map(|d| {
+            d.doc_comments(is_doc)
+                .iter()
+                .filter_map(|c| c.span().text(source))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(docs, [vec!["## Adds one."], vec!["## Stray"]]);
+}
+
+#[test]
+fn non_clone_types_parse_through_arcs() {
+    use roder::prelude::{just, pred, seq};
+    use roder::token::share_types;
+    use std::sync::Arc;
+
+    // no Clone: the payload is never copied
+    #[derive(Debug, PartialEq)]
+    enum Blob {
+        Header,
+        Data(Vec<u8>),
+    }
+
+    impl std::fmt::Display for Blob {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(match self {
+                Blob::Header => "header",
+                Blob::Data(_) => "data",
+            })
+        }
+    }
+
+    let tokens = share_types(vec![
+        Token::new(Blob::Header, Span::new(1, 1, 1)),
+        Token::new(Blob::Data(vec![0; 1024]), Span::new(1, 2, 2)),
+    ]);
+    let parser = seq([
+        just(Arc::new(Blob::Header)),
+        pred("data", |b: &Arc<Blob>| matches!(**b, Blob::Data(_))),
+    ]);
+
+    let tree = parser.parse_all(&tokens).ok().unwrap();
+    let ParseData::Nested(items) = &tree else {
+        panic!("expected a sequence");
+    };
+    let ParseData::Token(data) = &items[1] else {
+        panic!("expected a token");
+    };
+    assert!(Arc::ptr_eq(data.ty(), tokens[1].ty()));
+}
@@ -0,0 +1,61 @@
+use roder::prelude::*;
+use roder::trace::{Outcome, Step, TraceRecorder};
+
+#[test]
+fn recorder_captures_choice_backtracking() {
+    let tokens = vec![
+        Token::new(1u8, Span::new(1, 1, 1)),
+        Token::new(3u8, Span::new(1, 3, 3)),
+    ];
+    let recorder = TraceRecorder::new();
+    let ctx = Context::new(&tokens).with_recorder(&recorder);
+    let parser = choice([seq([just(1u8), just(2u8)]), seq([just(1u8), just(3u8)])]);
+
+    assert!(parser.parse(&ctx, 0).data().is_ok());
+
+    let steps = recorder.into_steps();
+    assert!(matches!(&steps[0], Step::Enter { rule, depth: 0, .. } if &**rule == "(1 2) or (1 3)"));
+    let failed = steps
+        .iter()
+        .filter(|s| {
+            matches!(
+                s,
+                Step::Exit {
+                    outcome: Outcome::Err,
+                    ..
+                }
+            )
+        })
+        .count();
+    assert_eq!(failed, 2);
+    assert!(matches!(
+        steps.last(),
+        Some(Step::Exit {
+            start: 0,
+            end: 2,
+            outcome: Outcome::Ok,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn replay_steps_both_ways() {
+    let tokens = vec![Token::new(1u8, Span::new(1, 1, 1))];
+    let recorder = TraceRecorder::new();
+    let ctx = Context::new(&tokens).with_recorder(&recorder);
+    seq([just(1u8)]).parse(&ctx, 0);
+
+    let mut replay = recorder.replay();
+    replay.forward();
+    replay.forward();
+    assert_eq!(replay.stack().len(), 2);
+    assert_eq!(replay.current().map(Step::rule), Some("1"));
+    replay.back();
+    assert_eq!(replay.stack().len(), 1);
+
+    assert_eq!(
+        recorder.to_string(),
+        "> 1 @0\n  > 1 @0\n  < 1 0..1 Ok\n< 1 0..1 Ok\n"
+    );
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "tracing")]
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use roder::prelude::*;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Writes down every span and event as a line of text.
+#[derive(Clone, Default)]
+struct Log(Arc<Mutex<Vec<String>>>);
+
+impl Log {
+    fn push(&self, line: String) -> u64 {
+        let mut lines = self.0.lock().unwrap();
+        lines.push(line);
+        lines.len() as u64
+    }
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push_str(&format!(" {}={value:?}", field.name()));
+    }
+}
+
+impl Subscriber for Log {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields(String::new());
+        span.record(&mut fields);
+        Id::from_u64(self.push(format!("{}{}", span.metadata().name(), fields.0)))
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        self.push(format!("event{}", fields.0));
+    }
+
+    fn enter(&self, span: &Id) {
+        self.push(format!("enter {}", span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        self.push(format!("exit {}", span.into_u64()));
+    }
+}
+
+#[test]
+fn rules_emit_spans_and_outcomes() {
+    let tokens = vec![Token::new(2u8, Span::new(1, 1, 1))];
+    let log = Log::default();
+
+    tracing::subscriber::with_default(log.clone(), || {
+        choice([just(1u8), just(2u8)])
+            .parse_all(&tokens)
+            .ok()
+            .unwrap();
+    });
+
+    let lines = log.0.lock().unwrap().clone();
+    assert_eq!(
+        lines,
+        [
+            "parse rule=\"1 or 2\" offset=0",
+            "enter 1",
+            "parse rule=\"1\" offset=0",
+            "enter 3",
+            "event message=exit outcome=Err end=0",
+            "exit 3",
+            "parse rule=\"2\" offset=0",
+            "enter 7",
+            "event message=exit outcome=Ok end=1",
+            "exit 7",
+            "event message=exit outcome=Ok end=1",
+            "exit 1",
+        ]
+    );
+}
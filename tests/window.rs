@@ -0,0 +1,46 @@
+use roder::prelude::*;
+
+#[test]
+fn errors_carry_the_tokens_around_them() {
+    let stmt = seq([just('a'), just('b'), just('c'), just('d'), just(';')]);
+    let tokens: Vec<_> = "abcxef"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| Token::new(c, Span::new(1, i + 1, i + 1)))
+        .collect();
+
+    let error = stmt.parse_all(&tokens).err().unwrap().remove(0);
+    assert!(error.window().is_none());
+    let error = error.with_window(&tokens, 2, |t| t.ty().to_string());
+    let window = error.window().unwrap();
+    assert_eq!(window.before, ["b", "c"]);
+    assert_eq!(window.after, ["x", "e"]);
+    assert!(error.to_string().ends_with("near `b c ^ x e`"));
+
+    // at the end of input there is nothing after the failure
+    let short = tokens[..2].to_vec();
+    let error = stmt.parse_all(&short).err().unwrap().remove(0);
+    let error = error.with_window(&short, 3, |t| t.ty().to_string());
+    assert_eq!(error.window().unwrap().to_string(), "a b ^");
+}
+
+#[test]
+fn the_window_is_where_the_failing_rule_started() {
+    let parser = seq([choice([just('1'), just('2')]), just('4'), just('5')]);
+    let tokens: Vec<_> = "3456789"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| Token::new(c, Span::new(1, i + 1, i + 1)))
+        .collect();
+
+    let error = parser.parse_all(&tokens).err().unwrap().remove(0);
+    assert_eq!(error.offset(), Some(0));
+    assert_eq!(error.span(), &Span::new(1, 1, 1));
+    let error = error.with_window(&tokens, 2, |t| t.ty().to_string());
+    assert_eq!(error.window().unwrap().to_string(), "^ 3 4");
+
+    // errors built by hand are placed by their span
+    let error = roder::parse::ParseError::from("x".into(), Span::new(1, 3, 3));
+    let error = error.with_window(&tokens, 2, |t| t.ty().to_string());
+    assert_eq!(error.window().unwrap().to_string(), "3 4 ^ 5 6");
+}